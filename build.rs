@@ -1,41 +0,0 @@
-use std::env;
-use std::path::Path;
-use std::path::PathBuf;
-use std::{io, fs};
-
-fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
-    fs::create_dir_all(&dst)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        } else {
-            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        }
-    }
-    Ok(())
-}
-
-fn main() {
-    println!("cargo:warning=CWD is {:?}", env::current_dir().unwrap());
-    println!("cargo:warning=OUT_DIR is {:?}", env::var("OUT_DIR").unwrap());
-    println!("cargo:warning=CARGO_MANIFEST_DIR is {:?}", env::var("CARGO_MANIFEST_DIR").unwrap());
-    println!("cargo:warning=PROFILE is {:?}", env::var("PROFILE").unwrap());
-
-    let output_path = get_output_path();
-    println!("cargo:warning=Calculated build path: {}", output_path.to_str().unwrap());
-
-    let input_path = Path::new("dicts");
-    let output_path = Path::new(&output_path).join("dicts");
-    let res = copy_dir_all(input_path, output_path);
-    println!("cargo:warning={:#?}",res)
-}
-
-fn get_output_path() -> PathBuf {
-    //<root or manifest path>/target/<profile>/
-    let manifest_dir_string = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let build_type = env::var("PROFILE").unwrap();
-    let path = Path::new(&manifest_dir_string).join("target").join(build_type);
-    return PathBuf::from(path);
-}
\ No newline at end of file
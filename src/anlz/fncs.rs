@@ -61,12 +61,12 @@ fn test_theta() {
 fn test_lab_mom() {
 
     let dict_lepto = EposDict::upload(
-        BufReader::new(File::open("./dicts/EPOS_LEPTONS.particles.txt").unwrap()),
+        BufReader::new(crate::fmt::decoder::EPOS_LEPTONS.as_bytes()),
         DctCoding::EPOS,
         None,
     );
     let dict_EPOS = EposDict::upload(
-        BufReader::new(File::open("./dicts/EPOS.particles.txt").unwrap()),
+        BufReader::new(crate::fmt::decoder::EPOS_PARTICLES.as_bytes()),
         DctCoding::EPOS,
         Some(dict_lepto.codes().cloned().collect())
     );
@@ -1,10 +1,10 @@
 use rayon::prelude::*;
-use std::{collections::HashSet, f64::consts::PI, fmt::Debug, sync::Arc};
+use std::{collections::{HashMap, HashSet}, f64::consts::PI, fmt::Debug, sync::Arc};
 use super::{fncs::*, impls};
 
-use crate::fmt::{oscar::{OSCEposBlock, OSCEposDataFile}, phqmd::PHQMDDataFile};
+use crate::fmt::{database::ParticleDatabase, oscar::{OSCEposBlock, OSCEposDataFile}, phqmd::PHQMDDataFile};
 
-pub trait ScalarCriteria<'a, S, T>: Send + Sync//: PartialEq + Debug + Clone + Send
+pub trait ScalarCriteria<'a, S: ?Sized, T>: Send + Sync//: PartialEq + Debug + Clone + Send
 where T: Particle<Decoder = S> + 'static,
     // S: 'a
 {
@@ -22,11 +22,25 @@ pub enum StandardCriteria {
     FinCnt,
     FinChargedCnt,
     ParticleIdCounter(i32),
+    /// count of particles of named code set, e.g. `ParticleSetCounter("baryons", codes)`
+    ParticleSetCounter(String, HashSet<i32>),
     PseudorapidityFilterCnt(f64, f64),
+    /// net strangeness
+    Strangeness,
+    /// net charm
+    Charm,
+    /// net beauty
+    Beauty,
+    /// count of final particles with open strangeness
+    FinStrangeCnt,
+    /// net isospin projection `I3`
+    Isospin3,
+    /// sum of spin degeneracies `2J + 1`
+    SpinDegeneracy,
 }
 
 
-impl<'a, S, T> ScalarCriteria<'a, S, T> for StandardCriteria
+impl<'a, S: ?Sized, T> ScalarCriteria<'a, S, T> for StandardCriteria
 where T: Particle<Decoder = S> + 'static, {
     fn get_criteria_value(&self, p: &T, dec: &S) -> f64 {//+ Clone + Send + 'a{
         match self {
@@ -64,22 +78,196 @@ where T: Particle<Decoder = S> + 'static, {
                     0.0
                 }
             },
+            StandardCriteria::ParticleSetCounter(_, codes) => {
+                if codes.contains(&p.code(dec)) {
+                    1.0
+                } else {
+                    0.0
+                }
+            },
+            StandardCriteria::Strangeness => {
+                        p.strangeness(dec)
+                    },
+            StandardCriteria::Charm => {
+                        p.charm(dec)
+                    },
+            StandardCriteria::Beauty => {
+                        p.beauty(dec)
+                    },
+            StandardCriteria::FinStrangeCnt => {
+                        if p.is_final(dec) && p.strangeness(dec) != 0. {1.} else {0.}
+                    },
+            StandardCriteria::Isospin3 => {
+                        p.isospin3(dec)
+                    },
+            StandardCriteria::SpinDegeneracy => {
+                        p.spin_degeneracy(dec)
+                    },
         }
     }
 
     fn name(&self) -> String {
-        format!("{:?}", self)
+        match self {
+            StandardCriteria::ParticleSetCounter(name, _) => format!("ParticleSetCounter({})", name),
+            _ => format!("{:?}", self),
+        }
     }
 }
 
+impl std::str::FromStr for StandardCriteria {
+    type Err = String;
+
+    /// Parses criteria written as its name, e.g. `FinCnt` or `PseudorapidityFilterCnt(3.5, 5.8)`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, args) = match s.split_once('(') {
+            Some((name, rest)) => {
+                let args = rest.strip_suffix(')').ok_or_else(|| format!("unclosed arguments of criteria `{}`", s))?;
+                (name.trim(), args.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()).collect::<Vec<_>>())
+            },
+            None => (s, vec![]),
+        };
+        let bad_arg = |x: &str| format!("bad argument `{}` of criteria `{}`", x, s);
+        match (name, args.as_slice()) {
+            ("FinEnergy", []) => Ok(StandardCriteria::FinEnergy),
+            ("ECharge", []) => Ok(StandardCriteria::ECharge),
+            ("BCharge", []) => Ok(StandardCriteria::BCharge),
+            ("LCharge", []) => Ok(StandardCriteria::LCharge),
+            ("FinCnt", []) => Ok(StandardCriteria::FinCnt),
+            ("FinChargedCnt", []) => Ok(StandardCriteria::FinChargedCnt),
+            ("Strangeness", []) => Ok(StandardCriteria::Strangeness),
+            ("Charm", []) => Ok(StandardCriteria::Charm),
+            ("Beauty", []) => Ok(StandardCriteria::Beauty),
+            ("FinStrangeCnt", []) => Ok(StandardCriteria::FinStrangeCnt),
+            ("Isospin3", []) => Ok(StandardCriteria::Isospin3),
+            ("SpinDegeneracy", []) => Ok(StandardCriteria::SpinDegeneracy),
+            ("ParticleIdCounter", [id]) => Ok(StandardCriteria::ParticleIdCounter(id.parse().map_err(|_| bad_arg(id))?)),
+            ("PseudorapidityFilterCnt", [mn, mx]) => Ok(StandardCriteria::PseudorapidityFilterCnt(
+                mn.parse().map_err(|_| bad_arg(mn))?,
+                mx.parse().map_err(|_| bad_arg(mx))?,
+            )),
+            _ => Err(format!("unknown criteria `{}`", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    pub fn holds(&self, a: f64, b: f64) -> bool {
+        match self {
+            Comparison::Lt => a < b,
+            Comparison::Le => a <= b,
+            Comparison::Gt => a > b,
+            Comparison::Ge => a >= b,
+            Comparison::Eq => a == b,
+            Comparison::Ne => a != b,
+        }
+    }
+}
+
+/// Event-level condition on value of scalar criteria,
+/// e.g. `PseudorapidityFilterCnt(3.5, 5.8) >= 1`
+#[derive(Debug, PartialEq, Clone)]
+pub struct EventSelection {
+    pub criteria: StandardCriteria,
+    pub op: Comparison,
+    pub value: f64,
+}
+
+impl std::str::FromStr for EventSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pos = s.find(['<', '>', '=', '!']).ok_or_else(|| format!("no comparison in selection `{}`", s))?;
+        let (criteria, rest) = s.split_at(pos);
+        let (op, value) = [
+            (">=", Comparison::Ge), ("<=", Comparison::Le), ("==", Comparison::Eq), ("!=", Comparison::Ne),
+            (">", Comparison::Gt), ("<", Comparison::Lt),
+        ].into_iter().find_map(
+            |(x, op)| rest.strip_prefix(x).map(|value| (op, value))
+        ).ok_or_else(|| format!("bad comparison in selection `{}`", s))?;
+        Ok(Self {
+            criteria: criteria.parse()?,
+            op,
+            value: value.trim().parse().map_err(|_| format!("bad value in selection `{}`", s))?,
+        })
+    }
+}
+
+impl EventSelection {
+    /// Criteria is summed over final particles of event, as in statistics output
+    pub fn passes<Event: HEPEvent>(&self, event: &Event, dec: &<Event::P as Particle>::Decoder) -> bool
+    where Event::P: 'static
+    {
+        let x: f64 = event.particles().filter(|p| p.is_final(dec)).map(
+            |p| self.criteria.get_criteria_value(p, dec)
+        ).sum();
+        self.op.holds(x, self.value)
+    }
+}
+
+#[test]
+fn test_parse_event_selection() {
+    let s: EventSelection = "PseudorapidityFilterCnt(3.5, 5.8) >= 1".parse().unwrap();
+    assert_eq!(s.criteria, StandardCriteria::PseudorapidityFilterCnt(3.5, 5.8));
+    assert_eq!(s.op, Comparison::Ge);
+    assert_eq!(s.value, 1.0);
+    let s: EventSelection = "FinChargedCnt<10".parse().unwrap();
+    assert_eq!((s.criteria, s.op, s.value), (StandardCriteria::FinChargedCnt, Comparison::Lt, 10.0));
+    let s: EventSelection = "ParticleIdCounter(1120)!=0".parse().unwrap();
+    assert_eq!(s.criteria, StandardCriteria::ParticleIdCounter(1120));
+    let s: EventSelection = "FinStrangeCnt >= 2".parse().unwrap();
+    assert_eq!((s.criteria, s.op, s.value), (StandardCriteria::FinStrangeCnt, Comparison::Ge, 2.0));
+    let s: EventSelection = "Isospin3 < 0".parse().unwrap();
+    assert_eq!((s.criteria, s.op, s.value), (StandardCriteria::Isospin3, Comparison::Lt, 0.0));
+    assert!("FinCnt".parse::<EventSelection>().is_err());
+    assert!("Unknown > 1".parse::<EventSelection>().is_err());
+    assert!("PseudorapidityFilterCnt(3.5) > 1".parse::<EventSelection>().is_err());
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum VecCriteria {
     FinMomentum,
 }
 
+/// Decoder of particles: particle database for particles decoded by their codes,
+/// `()` for dictionary records carrying all their values
+pub trait ParticleDecoder {
+    fn database(&self) -> Option<&dyn ParticleDatabase>;
+}
+
+impl ParticleDecoder for dyn ParticleDatabase {
+    fn database(&self) -> Option<&dyn ParticleDatabase> {
+        Some(self)
+    }
+}
+
+impl ParticleDecoder for () {
+    fn database(&self) -> Option<&dyn ParticleDatabase> {
+        None
+    }
+}
+
+/// Value `what` of particle `p` given by `value` of particle database
+fn decode<P: Particle + ?Sized>(p: &P, dec: &P::Decoder, what: &str, value: impl Fn(&dyn ParticleDatabase, i32) -> Option<f64>) -> f64 {
+    let code = p.code(dec);
+    let dct = dec.database().unwrap_or_else(|| panic!("Unable to get {} of particle {} without particle database", what, code));
+    value(dct, code).unwrap_or_else(|| dct.unknown(code, what))
+}
+
+/// Particle of event. Values missing in input files are taken from particle database by particle code
 pub trait Particle {
 
-    type Decoder;
+    type Decoder: ?Sized + ParticleDecoder;
 
     fn momentum_energy(&self, dec: &Self::Decoder) -> f64;
 
@@ -92,13 +280,44 @@ pub trait Particle {
     }
 
     /// Returns Electric charge
-    fn e_charge(&self, dec: &Self::Decoder) -> f64;
+    fn e_charge(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Electricity charge", |d, code| d.charge(code))
+    }
 
     /// Returns Baryon charge
-    fn b_charge(&self, dec: &Self::Decoder) -> f64;
+    fn b_charge(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Baryon charge", |d, code| d.baryon(code))
+    }
 
     /// Returns Lepton charge
-    fn l_charge(&self, dec: &Self::Decoder) -> f64;
+    fn l_charge(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Lepton charge", |d, code| Some(d.lepton(code)))
+    }
+
+    /// Returns Strangeness, `-1` for strange quark
+    fn strangeness(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Strangeness", |d, code| d.strangeness(code))
+    }
+
+    /// Returns Charm, `+1` for charm quark
+    fn charm(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Charm", |d, code| d.charm(code))
+    }
+
+    /// Returns Beauty, `-1` for bottom quark
+    fn beauty(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Beauty", |d, code| d.beauty(code))
+    }
+
+    /// Returns Isospin projection `I3`
+    fn isospin3(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Isospin", |d, code| d.isospin3(code))
+    }
+
+    /// Returns spin degeneracy `2J + 1`, zero if unknown
+    fn spin_degeneracy(&self, dec: &Self::Decoder) -> f64 {
+        decode(self, dec, "Spin degeneracy", |d, code| Some(d.spin_degeneracy(code).unwrap_or(0) as f64))
+    }
 
     fn is_final(&self, dec: &Self::Decoder) -> bool;
 
@@ -122,10 +341,85 @@ pub struct ScalarAnalyzerResults(Vec<String>, Vec<Vec<f64>>);
 impl ScalarAnalyzerResults {
     pub fn headers(&self) -> &Vec<String> {&self.0}
     pub fn values(&self) -> &Vec<Vec<f64>> {&self.1}
+
+    /// Appends per-event rows of the next chunk of events
+    pub fn append(&mut self, mut other: Self) {
+        if self.0.is_empty() {
+            self.0 = other.0;
+        }
+        self.1.append(&mut other.1);
+    }
+}
+
+/// Distribution results: (name, total items, bins, bin counters)
+pub type DistributionResults = Vec<(String, usize, Vec<(f64, f64)>, Vec<usize>)>;
+
+/// Adds distribution results of the next chunk of events to `target`
+pub fn merge_distribution_results(target: &mut DistributionResults, other: DistributionResults) {
+    if target.is_empty() {
+        *target = other;
+        return;
+    }
+    target.iter_mut().zip(other).for_each(
+        |(a, b)| {
+            if a.0 != b.0 {
+                panic!("Wrong distribution ananlyze behaviour! Report this error");
+            }
+            a.1 += b.1;
+            a.3.iter_mut().zip(b.3.iter()).for_each(|(x, y)| *x += y );
+        }
+    );
 }
 
 pub fn IS_FINAL_FILTER<'a, Event: HEPEvent>(x: &'a Event::P, dec: &<Event::P as Particle>::Decoder) -> bool { x.is_final(dec) }
 
+impl<Event: HEPEvent + Sync> HEPEventAnalyzer<'_, Event> {
+    /// Streaming mode: reads events from `source` in chunks of at most `chunk_size` events
+    /// and calls `f` with analyzer of each chunk.
+    /// Only one chunk is kept in memory, so memory usage does not depend on input size.
+    /// 
+    /// returns count of processed events
+    pub fn for_each_chunk<E>(
+        source: impl Iterator<Item = Result<Event, E>>,
+        chunk_size: usize,
+        mut f: impl FnMut(&HEPEventAnalyzer<'_, Event>)
+    ) -> Result<usize, E> {
+        let chunk_size = chunk_size.max(1);
+        let mut total = 0;
+        let mut chunk = Vec::with_capacity(chunk_size);
+        for event in source {
+            chunk.push(event?);
+            if chunk.len() >= chunk_size {
+                total += chunk.len();
+                f(&HEPEventAnalyzer::new(&chunk));
+                chunk.clear();
+            }
+        }
+        if !chunk.is_empty() {
+            total += chunk.len();
+            f(&HEPEventAnalyzer::new(&chunk));
+        }
+        Ok(total)
+    }
+}
+
+impl<Event: HEPEvent> HEPEventAnalyzer<'_, Event>
+where Event::P: Particle<Decoder = dyn ParticleDatabase>
+{
+    /// Counts of particles passing `filter` and missing in particle database, by particle code
+    pub fn unknown_particles(
+        &self,
+        filter: impl Fn(&Event::P, &<Event::P as Particle>::Decoder) -> bool,
+        dec: &<Event::P as Particle>::Decoder
+    ) -> HashMap<i32, usize> {
+        let mut counts = HashMap::new();
+        self.events.iter().flat_map(|e| e.particles()).filter(|p| filter(p, dec)).map(|p| p.code(dec)).filter(|code| !dec.knows(*code)).for_each(
+            |code| *counts.entry(code).or_default() += 1
+        );
+        counts
+    }
+}
+
 impl<'a, Event: HEPEvent> HEPEventAnalyzer<'a, Event>
 where &'a[Event]: rayon::iter::IntoParallelIterator<Item = &'a Event>
 {
@@ -191,11 +485,11 @@ where &'a[Event]: rayon::iter::IntoParallelIterator<Item = &'a Event>
     }
 
     /// `criteria` - scalar criteria to calculate, calculated **after** filter
-    pub fn calculate_criteria // <T: Sync + ScalarCriteria<'a, <Event::P as Particle>::Decoder, Event::P>>
+    pub fn calculate_criteria<'c> // <T: Sync + ScalarCriteria<'a, <Event::P as Particle>::Decoder, Event::P>>
     (   
             &self,
             filter: impl (Fn(&Event::P, &<Event::P as Particle>::Decoder) -> bool) + Sync,
-            criteria: Vec<& (impl ScalarCriteria<'a, <Event::P as Particle>::Decoder, Event::P> +?Sized) >,//Vec<T>,
+            criteria: Vec<& (impl ScalarCriteria<'c, <Event::P as Particle>::Decoder, Event::P> +?Sized) >,//Vec<T>,
             dec: &<Event::P as Particle>::Decoder
     ) -> ScalarAnalyzerResults
     where
@@ -225,13 +519,13 @@ where &'a[Event]: rayon::iter::IntoParallelIterator<Item = &'a Event>
         ScalarAnalyzerResults(headers, results)   
     }
 
-    pub fn calculate_distribution_criteria // <T: Sync + ScalarCriteria<'a, <Event::P as Particle>::Decoder, Event::P>>
+    pub fn calculate_distribution_criteria<'c> // <T: Sync + ScalarCriteria<'a, <Event::P as Particle>::Decoder, Event::P>>
     (   
             &self,
             filter: impl (Fn(&Event::P, &<Event::P as Particle>::Decoder) -> bool) + Sync,
-            criteria: Vec<& (impl DistributionCritetia<'a, <Event::P as Particle>::Decoder, Event::P> +?Sized) >,//Vec<T>,
+            criteria: Vec<& (impl DistributionCritetia<'c, <Event::P as Particle>::Decoder, Event::P> +?Sized) >,//Vec<T>,
             dec: &<Event::P as Particle>::Decoder,
-    ) -> Vec<(String, usize, Vec<(f64, f64)>, Vec<usize> )>
+    ) -> DistributionResults
     where
         <Event as HEPEvent>::P: 'static ,
         <Event::P as Particle>::Decoder: Sync
@@ -311,7 +605,7 @@ where &'a[Event]: rayon::iter::IntoParallelIterator<Item = &'a Event>
 /// Distribution criteria trait
 /// use to calculate distribution of some event characteristics
 /// for example, getting distribution of momentum of particles per each event
-pub trait DistributionCritetia<'a, S, T>: Send + Sync//: PartialEq + Debug + Clone + Send
+pub trait DistributionCritetia<'a, S: ?Sized, T>: Send + Sync//: PartialEq + Debug + Clone + Send
 where T: Particle<Decoder = S> + 'static,
 {
     /// get bins (HAVE TO BE SET BEFORE CALCULATING DISTRIBUTION)
@@ -334,11 +628,6 @@ where T: Particle<Decoder = S> + 'static,
     fn name(&self) -> String;
 }
 
-///
-pub struct ParticleCodeSelector {
-    codes: HashSet<i64>
-}
-
 pub enum StandardDistributionCriteraDefiner<Event: HEPEvent> {
 
     /// distribution of particle momentum angle direction
@@ -348,8 +637,8 @@ pub enum StandardDistributionCriteraDefiner<Event: HEPEvent> {
     PdirTheta,
     /// pseudorapidity distribution
     PNu,
-    PNu_selected(Vec<i32>),
-    PTheta_selected(Vec<i32>),
+    PNu_selected(HashSet<i32>),
+    PTheta_selected(HashSet<i32>),
     Custom(Box::<dyn (Fn(&Event::P, &<Event::P as Particle>::Decoder) -> f64) + Sync + Send>)
 }
 
@@ -384,12 +673,12 @@ macro_rules! standard_criteria {
 fn test_dcrit_macro_creation() {
     /*let criteria = standard_criteria!(
         StandardDistributionCriteraDefiner::PdirTheta,
-        PHQMDDataFile<'_>,
+        PHQMDDataFile,
         0., 0., 0usize, "test".to_string()
     );*/
     /*let x = templated!(
         StandardDistributionCriteraDefiner::PdirTheta,
-        <OSCEposDataFile<'_> as crate::fmt::generic::GenericDataContainer>::Block
+        <OSCEposDataFile as crate::fmt::generic::GenericDataContainer>::Block
     );*/
 }
 
@@ -432,7 +721,7 @@ pub fn cuttest() {
     println!("{:?}", s.get_bins());
 }
 
-impl<'a, S, Event: HEPEvent> DistributionCritetia<'a, S, Event::P> for StandardDistributionCriteria<Event>
+impl<'a, S: ?Sized, Event: HEPEvent> DistributionCritetia<'a, S, Event::P> for StandardDistributionCriteria<Event>
     where Event::P: Particle<Decoder = S> + 'static,
 {
     fn get_bins(&self) -> &[(f64, f64)] {
@@ -527,6 +816,8 @@ impl ParticleListOutput {
 #[derive(Debug, Clone)]
 pub struct ParticleListCompiler {
     pub id_filter: HashSet<i32>,
+    /// name of particle selection, lists without name are named with their codes
+    pub name: Option<String>,
     pub data: Vec<ParticleListOutput>,
 }
 
@@ -535,13 +826,30 @@ impl ParticleListCompiler {
     pub fn new(id_filter: HashSet<i32>) -> Self {
         Self {
             id_filter,
+            name: None,
+            data: Vec::new(),
+        }
+    }
+
+    /// List of particles of named selection
+    pub fn named(name: String, id_filter: HashSet<i32>) -> Self {
+        Self {
+            id_filter,
+            name: Some(name),
             data: Vec::new(),
         }
     }
+
     pub fn clean_clone(&self) -> Self {
         Self {
             id_filter: self.id_filter.clone(),
+            name: self.name.clone(),
             data: vec![]
         }
     }
+
+    /// Appends particles collected on the next chunk of events
+    pub fn append(&mut self, mut other: Self) {
+        self.data.append(&mut other.data);
+    }
 }
\ No newline at end of file
@@ -1,45 +1,43 @@
 use super::generic::*;
-use crate::fmt::{decoder::{EposDict, EposDictParticle}, generic::*, hepmc::{HepMCBlock, HepMCParticle}, oscar::*, phqmd::{PHQMDBlock, PHQMDParticle}, qgsm::{QGSMBlock, QGSMParticle}};
+use crate::fmt::{database::{warn_undefined, ParticleDatabase}, istat::FinalState, decoder::EposDictParticle, generic::*, hepmc::{HepMCBlock, HepMCParticle}, hepmc2::{HepMC2Block, HepMC2Particle}, oscar2013::{OSCAR2013Block, OSCAR2013Particle}, phsd::{PHSDBlock, PHSDParticle}, ampt::{AMPTBlock, AMPTParticle}, lhe::{LHEBlock, LHEParticle}, urqmd::{UrQMDBlock, UrQMDCollisionBlock, UrQMDParticle}, oscar::*, phqmd::{PHQMDBlock, PHQMDParticle}, qgsm::{QGSMBlock, QGSMParticle}, cache::{CacheBlock, CacheParticle}};
 
 impl Particle for EposDictParticle {
     type Decoder = ();
 
-    fn momentum_energy(&self, dec: &Self::Decoder) -> f64 {
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
         0.0
     }
 
-    fn momentum(&self, dec: &Self::Decoder) -> &(f64, f64, f64) {
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
         &(0.0, 0.0, 0.0)
     }
 
-    fn mass_energy(&self, dec: &Self::Decoder) -> f64 {
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
         self.mass.unwrap()
     }
 
-    fn e_charge(&self, dec: &Self::Decoder) -> f64 {
+    fn e_charge(&self, _dec: &Self::Decoder) -> f64 {
         self.charge.unwrap()
     }
 
-    fn b_charge(&self, dec: &Self::Decoder) -> f64 {
+    fn b_charge(&self, _dec: &Self::Decoder) -> f64 {
         //                                             1000010020
         // println!(">{} : {}\n", self.id_PDG.unwrap(), self.id_PDG.unwrap().abs() - 1000000000);
-        if (self.id_PDG.unwrap().abs() > 1000000000) {
-            // nuclei
-            let code = self.id_PDG.unwrap();
-            let baryon = (code.abs() % 10000) / 10;
-            (baryon as f64) * (code.signum() as f64)
+        if let Some(nucleus) = self.nucleus() {
+            nucleus.baryon()
         } else {
-            if self.id_PDG.unwrap() > 0 {
-                [   self.ifl1.unwrap_or_else(|| { println!("[WARNING::EPOS DICT]: Using undefined values! {:?}", self); 0 }),
-                    self.ifl2.unwrap_or_else(|| { println!("[WARNING::EPOS DICT]: Using undefined values! {:?}", self); 0 }),
-                    self.ifl3.unwrap_or_else(|| { println!("[WARNING::EPOS DICT]: Using undefined values! {:?}", self); 0 })
+            // particles without PDG code are defined by their flavours
+            if self.id_PDG.unwrap_or(0) >= 0 {
+                [   self.ifl1.unwrap_or_else(|| { warn_undefined(self, "ifl1"); 0 }),
+                    self.ifl2.unwrap_or_else(|| { warn_undefined(self, "ifl2"); 0 }),
+                    self.ifl3.unwrap_or_else(|| { warn_undefined(self, "ifl3"); 0 })
                 ].iter().map(
                         |&x| { if (x > 0) { 1. } else if (x < 0) {-1.} else {0.} }
                     ).sum::<f64>() / 3.0
             } else {
-                -[  self.ifl1.unwrap_or_else(|| { println!("[WARNING::EPOS DICT]: Using undefined values (anti)! {:?}", self); 0 }),
-                    self.ifl2.unwrap_or_else(|| { println!("[WARNING::EPOS DICT]: Using undefined values (anti)! {:?}", self); 0 }),
-                    self.ifl3.unwrap_or_else(|| { println!("[WARNING::EPOS DICT]: Using undefined values (anti)! {:?}", self); 0 })
+                -[  self.ifl1.unwrap_or_else(|| { warn_undefined(self, "ifl1"); 0 }),
+                    self.ifl2.unwrap_or_else(|| { warn_undefined(self, "ifl2"); 0 }),
+                    self.ifl3.unwrap_or_else(|| { warn_undefined(self, "ifl3"); 0 })
                 ].iter().map(
                         |&x| { if (x > 0) { 1. } else if (x < 0) {-1.} else {0.} }
                     ).sum::<f64>() / 3.0
@@ -47,94 +45,68 @@ impl Particle for EposDictParticle {
         }
     }
 
-    fn l_charge(&self, dec: &Self::Decoder) -> f64 {
+    fn l_charge(&self, _dec: &Self::Decoder) -> f64 {
         self.lepton_charge
     }
 
-    fn is_final(&self, dec: &Self::Decoder) -> bool {
+    fn strangeness(&self, _dec: &Self::Decoder) -> f64 {
+        match self.nucleus() {
+            Some(x) => x.strangeness(),
+            None => -self.quark_number(3) as f64,
+        }
+    }
+
+    fn charm(&self, _dec: &Self::Decoder) -> f64 {
+        self.quark_number(4) as f64
+    }
+
+    fn beauty(&self, _dec: &Self::Decoder) -> f64 {
+        -self.quark_number(5) as f64
+    }
+
+    fn isospin3(&self, _dec: &Self::Decoder) -> f64 {
+        match self.nucleus() {
+            Some(x) => x.isospin3(),
+            None => (self.quark_number(1) - self.quark_number(2)) as f64 / 2.0,
+        }
+    }
+
+    fn spin_degeneracy(&self, _dec: &Self::Decoder) -> f64 {
+        self.degeneracy.or_else(|| self.id_PDG.and_then(crate::fmt::pdg::spin_degeneracy)).unwrap_or(0) as f64
+    }
+
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
         true
     }
 
-    fn code(&self, dec: &Self::Decoder) -> i32 {
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
         self.id_PDG.unwrap()
     }
 }
 
 impl Particle for OscarParticle {
 
-    type Decoder = EposDict;
+    type Decoder = dyn ParticleDatabase;
 
-    fn code(&self, dec: &Self::Decoder) -> i32 {
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
         self.code
     }
 
-    fn momentum_energy(&self, _dct: &EposDict) -> f64 {
+    fn momentum_energy(&self, _dct: &dyn ParticleDatabase) -> f64 {
         // self.p0
         (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
     }
 
-    fn momentum(&self, dec: &Self::Decoder) -> &(f64, f64, f64) {
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
         &self.p
     }
 
-    fn mass_energy(&self, _dct: &EposDict) -> f64 {
+    fn mass_energy(&self, _dct: &dyn ParticleDatabase) -> f64 {
         self.mass
     }
 
-    fn e_charge(&self, dct: &EposDict) -> f64 {
-        if let Some(pd) = dct.get(&self.code) {
-            pd.charge.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values! {:?}", pd); 0. })
-        } else {
-            if let Some(pd) = dct.get(&-self.code) {
-                -pd.charge.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values (anti)! {:?}", pd); 0. })
-            } else {
-                match EposDict::get_nuclei_from_code(self.code, self.mass, None) {
-                    Some(pt) => pt.e_charge(&()),
-                    None =>  panic!("Unable to get Electricity charge : Undefined Particle! {:?}", self),
-                }
-            }
-        }
-    }
-
-    fn b_charge(&self, dct: &EposDict) -> f64 {
-        if let Some(pd) = dct.get(&self.code) {
-            /*[   pd.ifl1.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values! {:?}", pd); 0 }),
-                pd.ifl2.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values! {:?}", pd); 0 }),
-                pd.ifl3.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values! {:?}", pd); 0 })
-            ].iter().map(
-                    |&x| { if (x > 0) { 1. } else if (x < 0) {-1.} else {0.} }
-                ).sum::<f64>() / 3.0*/
-            pd.b_charge(&())
-        } else {
-            if let Some(pd) = dct.get(&-self.code) {
-                /*-[   pd.ifl1.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values (anti)! {:?}", pd); 0 }),
-                    pd.ifl2.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values (anti)! {:?}", pd); 0 }),
-                    pd.ifl3.unwrap_or_else(|| { println!("[WARNING::EPOS]: Using undefined values (anti)! {:?}", pd); 0 })
-                ].iter().map(
-                        |&x| { if (x > 0) { 1. } else if (x < 0) {-1.} else {0.} }
-                    ).sum::<f64>() / 3.0*/
-                -pd.b_charge(&())
-            } else {
-                match EposDict::get_nuclei_from_code(self.code, self.mass, None) {
-                    Some(pt) => pt.b_charge(&()),
-                    None =>  panic!("Unable to get Baryon charge : Undefined Particle! {:?}", self),
-                }
-            }
-        }
-    }
-
-    fn l_charge(&self, dct: &EposDict) -> f64 {
-        if dct.is_lepton(&self.code)  {
-            1.0
-        } else if dct.is_lepton(&-self.code) {
-            -1.0
-        } else {
-            0.0
-        }
-    }
-
-    fn is_final(&self, dct: &EposDict) -> bool {
-        self.state == 0
+    fn is_final(&self, dct: &dyn ParticleDatabase) -> bool {
+        dct.final_state().contains(self.state)
     }
 }
 
@@ -148,73 +120,38 @@ impl HEPEvent for OSCEposBlock {
 
 impl Particle for PHQMDParticle {
 
-    type Decoder = EposDict;
+    type Decoder = dyn ParticleDatabase;
 
-    fn code(&self, dec: &Self::Decoder) -> i32 {
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
         self.code
     }
 
-    /*fn energy(&self, dec: &EposDict) -> f64 {
+    /*fn energy(&self, dec: &dyn ParticleDatabase) -> f64 {
         // SELF.E is worse!!!!
         (
             self.momentum_energy(dec).powi(2) + self.mass_energy(dec).powi(2)
         ).sqrt()
     }*/
 
-    fn momentum_energy(&self, _dct: &EposDict) -> f64 {
+    fn momentum_energy(&self, _dct: &dyn ParticleDatabase) -> f64 {
         //(self.E.powi(2) - self.mass_energy(_dct).powi(2)).sqrt()
 
         (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
     }
 
-    fn mass_energy(&self, dct: &EposDict) -> f64 {
-        if let Some(x) = dct.get(&self.code) {
-            x.mass.unwrap_or_else(
-                || { println!("[WARNING]: Using undefined values! {:?}", x); 0. }
-            )
-        } else if let Some(x) = dct.get(& (-self.code)) {
-            x.mass.unwrap_or_else(
-                || { println!("[WARNING]: Using undefined values (anti)! {:?}", x); 0. }
-            )
-        } else {
-            panic!("Unable to get mass : Undefined Particle!, {:?}", self)
-        }
+    fn mass_energy(&self, dct: &dyn ParticleDatabase) -> f64 {
+        dct.mass(self.code).unwrap_or_else(|| dct.unknown(self.code, "mass"))
     }
 
-    fn e_charge(&self, dct: &EposDict) -> f64 {
+    fn e_charge(&self, _dct: &dyn ParticleDatabase) -> f64 {
         self.charge.into()
     }
 
-    fn b_charge(&self, dct: &EposDict) -> f64 {
-        if let Some(pd) = dct.get(&self.code) {
-                pd.b_charge(&())
-        } else {
-           if let Some(pd) = dct.get(&-self.code) {
-                -pd.b_charge(&())
-            } else {
-                match EposDict::get_nuclei_from_code(self.code, -1.0, None) {
-                    Some(pt) => pt.b_charge(&()),
-                    None =>  panic!("Unable to get Baryon charge : Undefined Particle! {:?}", self),
-                }
-            }
-        }
-    }
-
-    fn l_charge(&self, dct: &EposDict) -> f64 {
-        if dct.is_lepton(&self.code)  {
-            1.0
-        } else if dct.is_lepton(&-self.code) {
-            -1.0
-        } else {
-            0.0
-        }
-    }
-
-    fn is_final(&self, dct: &EposDict) -> bool {
+    fn is_final(&self, _dct: &dyn ParticleDatabase) -> bool {
         true
     }
 
-    fn momentum(&self, dec: &Self::Decoder) -> &(f64, f64, f64) {
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
         &self.p
     }
 }
@@ -229,37 +166,41 @@ impl HEPEvent for PHQMDBlock {
 
 
 impl Particle for QGSMParticle {
-    type Decoder = EposDict;
+    type Decoder = dyn ParticleDatabase;
 
-    fn momentum_energy(&self, dec: &Self::Decoder) -> f64 {
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
         (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
     }
 
-    fn momentum(&self, dec: &Self::Decoder) -> &(f64, f64, f64) {
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
         &self.p
     }
 
-    fn mass_energy(&self, dec: &Self::Decoder) -> f64 {
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
         self.mass
     }
 
-    fn e_charge(&self, dec: &Self::Decoder) -> f64 {
+    fn e_charge(&self, _dec: &Self::Decoder) -> f64 {
         self.charge as f64
     }
 
-    fn b_charge(&self, dec: &Self::Decoder) -> f64 {
+    fn b_charge(&self, _dec: &Self::Decoder) -> f64 {
         self.baryon_number as f64
     }
 
-    fn l_charge(&self, dec: &Self::Decoder) -> f64 {
+    fn l_charge(&self, _dec: &Self::Decoder) -> f64 {
         self.lepton_number as f64
     }
 
-    fn is_final(&self, dec: &Self::Decoder) -> bool {
+    fn strangeness(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        self.strangeness as f64
+    }
+
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
         true
     }
 
-    fn code(&self, dec: &Self::Decoder) -> i32 {
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
         self.code
     }
 }
@@ -272,72 +213,317 @@ impl HEPEvent for QGSMBlock {
 }
 
 impl Particle for HepMCParticle {
-    type Decoder = EposDict;
+    type Decoder = dyn ParticleDatabase;
 
-    fn momentum_energy(&self, dec: &Self::Decoder) -> f64 {
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
         (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
     }
 
-    fn momentum(&self, dec: &Self::Decoder) -> &(f64, f64, f64) {
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
         &self.p
     }
 
-    fn mass_energy(&self, dec: &Self::Decoder) -> f64 {
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
         self.mass
     }
 
-    fn e_charge(&self, dct: &EposDict) -> f64 {
-        if let Some(pd) = dct.get(&self.code) {
-                pd.e_charge(&())
-        } else {
-           if let Some(pd) = dct.get(&-self.code) {
-                -pd.e_charge(&())
-            } else {
-                match EposDict::get_nuclei_from_code(self.code, self.mass, None) {
-                    Some(pt) => pt.e_charge(&()),
-                    None =>  panic!("Unable to get Electricity charge : Undefined Particle! {:?}", self),
-                }
-            }
-        }
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        self.status.eq(&1)
     }
 
-    fn b_charge(&self, dct: &EposDict) -> f64 {
-        if let Some(pd) = dct.get(&self.code) {
-                pd.b_charge(&())
-        } else {
-           if let Some(pd) = dct.get(&-self.code) {
-                -pd.b_charge(&())
-            } else {
-                match EposDict::get_nuclei_from_code(self.code, self.mass, None) {
-                    Some(pt) => pt.b_charge(&()),
-                    None =>  panic!("Unable to get Baryon charge : Undefined Particle! {:?}", self),
-                }
-            }
-        }
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for HepMCBlock {
+    type P = HepMCParticle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+impl Particle for HepMC2Particle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.mass
+    }
+
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        self.status.eq(&1)
+    }
+
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for HepMC2Block {
+    type P = HepMC2Particle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+impl Particle for OSCAR2013Particle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.mass
+    }
+
+    fn e_charge(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        self.charge as f64
+    }
+
+    /// particle lists contain particles existing at output time
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        true
+    }
+
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for OSCAR2013Block {
+    type P = OSCAR2013Particle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
     }
+}
 
-    fn l_charge(&self, dct: &EposDict) -> f64 {
-        if dct.is_lepton(&self.code)  {
-            1.0
-        } else if dct.is_lepton(&-self.code) {
-            -1.0
+
+impl Particle for PHSDParticle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    /// off-shell mass, E^2 - p^2
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.offshell_mass()
+    }
+
+    fn energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.e
+    }
+
+    fn e_charge(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        self.charge as f64
+    }
+
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        true
+    }
+
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for PHSDBlock {
+    type P = PHSDParticle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+impl Particle for AMPTParticle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.mass
+    }
+
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        true
+    }
+
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for AMPTBlock {
+    type P = AMPTParticle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+impl Particle for LHEParticle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.mass
+    }
+
+    /// ISTUP == 1: outgoing particle
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        self.status.eq(&1)
+    }
+
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for LHEBlock {
+    type P = LHEParticle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+/// charges are taken from UrQMD output, the dictionary is not used
+impl Particle for UrQMDParticle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    fn mass_energy(&self, _dec: &Self::Decoder) -> f64 {
+        self.mass
+    }
+
+    fn e_charge(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        self.charge as f64
+    }
+
+    /// UrQMD baryons have |ityp| < 100
+    fn b_charge(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        if self.ityp.abs() < 100 {
+            self.ityp.signum() as f64
         } else {
             0.0
         }
     }
 
+    /// UrQMD does not propagate leptons
+    fn l_charge(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        0.0
+    }
+
+    /// Isospin projection written in the file as `2*I3`
+    fn isospin3(&self, _dct: &dyn ParticleDatabase) -> f64 {
+        self.iso3 as f64 / 2.0
+    }
+
+    fn is_final(&self, _dec: &Self::Decoder) -> bool {
+        true
+    }
+
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
+        self.code
+    }
+}
+
+impl HEPEvent for UrQMDBlock {
+    type P = UrQMDParticle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+impl HEPEvent for UrQMDCollisionBlock {
+    type P = UrQMDParticle;
+    fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
+        self.event.iter()
+    }
+}
+
+/// Values written in the source file are used as is,
+/// the other ones are taken from dictionary of the source format
+impl Particle for CacheParticle {
+    type Decoder = dyn ParticleDatabase;
+
+    fn momentum_energy(&self, _dec: &Self::Decoder) -> f64 {
+        (self.p.0.powi(2) + self.p.1.powi(2) + self.p.2.powi(2)).sqrt()
+    }
+
+    fn momentum(&self, _dec: &Self::Decoder) -> &(f64, f64, f64) {
+        &self.p
+    }
+
+    fn mass_energy(&self, dct: &dyn ParticleDatabase) -> f64 {
+        self.mass.or_else(|| dct.mass(self.code))
+            .unwrap_or_else(|| dct.unknown(self.code, "mass"))
+    }
+
+    fn e_charge(&self, dct: &dyn ParticleDatabase) -> f64 {
+        self.charge.map(|q| q as f64).or_else(|| dct.charge(self.code))
+            .unwrap_or_else(|| dct.unknown(self.code, "Electricity charge"))
+    }
+
+    fn b_charge(&self, dct: &dyn ParticleDatabase) -> f64 {
+        self.baryon.map(|b| b as f64).or_else(|| dct.baryon(self.code))
+            .unwrap_or_else(|| dct.unknown(self.code, "Baryon charge"))
+    }
+
+    fn l_charge(&self, dct: &dyn ParticleDatabase) -> f64 {
+        self.lepton.map(|l| l as f64).unwrap_or_else(|| dct.lepton(self.code))
+    }
+
     fn is_final(&self, dec: &Self::Decoder) -> bool {
-        self.status.eq(&1)
+        // EPOS sets of particles are defined by EPOS status of cached particles
+        match dec.final_state() {
+            FinalState::Final => self.is_final,
+            x => x.contains(self.status),
+        }
     }
 
-    fn code(&self, dec: &Self::Decoder) -> i32 {
+    fn code(&self, _dec: &Self::Decoder) -> i32 {
         self.code
     }
 }
 
-impl HEPEvent for HepMCBlock {
-    type P = HepMCParticle;
+impl HEPEvent for CacheBlock {
+    type P = CacheParticle;
     fn particles(&self) -> impl Iterator<Item=&Self::P> + Clone {
         self.event.iter()
     }
-}
\ No newline at end of file
+}
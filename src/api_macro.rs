@@ -1,6 +1,8 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::BufReader;
+use std::path::Path;
 use std::time::SystemTime;
 
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
@@ -11,6 +13,7 @@ use crate::fmt;
 use crate::{standard_criteria, anlz::{fncs::lab_momentum, DistributionCritetia, HEPEventAnalyzer, ScalarCriteria, StandardCriteria, StandardDistributionCriteria}};
 use crate::fmt::oscar::OSCEposDataFile;
 use crate::fmt::{
+    database::ParticleDatabase,
     decoder::EposDict,
     generic::GenericDataContainer,
     oscar::{OSCEposBlock, OscarParticle},
@@ -28,64 +31,112 @@ pub fn get_decoder(at: &cli::AcceptedTypes) -> fmt::decoder::DctCoding {
         cli::AcceptedTypes::EPOS => {
             fmt::decoder::DctCoding::EPOS
         },
-        cli::AcceptedTypes::PHQMD | cli::AcceptedTypes::UrQmdF19 | cli::AcceptedTypes::QGSM | cli::AcceptedTypes::HepMC => {
+        cli::AcceptedTypes::PHQMD | cli::AcceptedTypes::UrQmdF19 | cli::AcceptedTypes::QGSM | cli::AcceptedTypes::HepMC | cli::AcceptedTypes::HepMC2
+            | cli::AcceptedTypes::Oscar2013 | cli::AcceptedTypes::Oscar2013Bin
+            | cli::AcceptedTypes::PHSD
+            | cli::AcceptedTypes::AMPT
+            | cli::AcceptedTypes::LHE
+            | cli::AcceptedTypes::UrQMDF13
+            | cli::AcceptedTypes::UrQMDF14
+            | cli::AcceptedTypes::UrQMDF15 => {
             fmt::decoder::DctCoding::PDG
         },
+        cli::AcceptedTypes::Cache => {
+            panic!("Cache is decoded with dictionary of its source format")
+        },
     }
 }
 
-pub fn generate_dictionary(x: &AcceptedTypes) -> EposDict {
-    let decoder = get_decoder(x);
+/// Text of dictionary table `name`: the file in `dir` if it is there, the embedded table otherwise
+fn dict_table(dir: Option<&Path>, name: &str, embedded: &'static str) -> std::io::Result<Cow<'static, str>> {
+    match dir.map(|x| x.join(name)).filter(|x| x.is_file()) {
+        Some(path) => {
+            println!(">> DICTIONARY TABLE [{}]", path.display());
+            std::fs::read_to_string(&path).map(Cow::Owned).map_err(
+                |e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+            )
+        },
+        None => Ok(Cow::Borrowed(embedded)),
+    }
+}
+
+/// Builds dictionary of input type `x`.
+/// Tables are embedded into the binary, `--dict-dir` replaces them and `--extra-dict` tables are added on top.
+/// `--particle-table pdg` takes particles of PDG-coded inputs from the RPP table instead of the EPOS one,
+/// `--input-codes` sets numbering of particle codes of input.
+/// Private codes of `CODE_REMAP.txt` and `--code-remap` tables entries of the input format are added last,
+/// `--on-unknown` sets reaction on particles missing in dictionary, `--final-state` sets analysed particles
+pub fn generate_dictionary(x: &AcceptedTypes, args: &Args) -> std::io::Result<Box<dyn ParticleDatabase>> {
+    let decoder = args.input_codes.unwrap_or_else(|| get_decoder(x));
+    let dir = args.dict_dir();
+    if let Some(dir) = &dir && !dir.is_dir() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("dictionary directory {} not found", dir.display())));
+    }
+    let dir = dir.as_deref();
     let dict_lepto = EposDict::upload(
-            BufReader::new(File::open("./dicts/EPOS_LEPTONS.particles.txt").unwrap()),
+            BufReader::new(dict_table(dir, "EPOS_LEPTONS.particles.txt", fmt::decoder::EPOS_LEPTONS)?.as_bytes()),
             decoder.clone(),
             None,
     );
-    let mut dict = EposDict::upload(
-        BufReader::new(File::open("./dicts/EPOS.particles.txt").unwrap()),
-        decoder,
-        Some(dict_lepto.codes().cloned().collect())
-    );
+    let leptons: HashSet<i32> = dict_lepto.codes().cloned().collect();
+    let mut dict = match args.particle_table {
+        cli::ParticleTable::Epos => EposDict::upload(
+            BufReader::new(dict_table(dir, "EPOS.particles.txt", fmt::decoder::EPOS_PARTICLES)?.as_bytes()),
+            decoder,
+            Some(leptons.clone())
+        ),
+        cli::ParticleTable::Pdg => {
+            if decoder != fmt::decoder::DctCoding::PDG {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("PDG particle table decodes PDG codes only, use EPOS table for {:?} codes", decoder)));
+            }
+            EposDict::upload_pdg(
+                BufReader::new(dict_table(dir, "PDG.particles.txt", fmt::pdg::PDG_PARTICLES)?.as_bytes())
+            )
+        },
+    };
     dict.upload_nuclei(
-        BufReader::new(File::open("./dicts/NUCLEI_LIST.txt").unwrap())
+        BufReader::new(dict_table(dir, "NUCLEI_LIST.txt", fmt::decoder::NUCLEI_LIST)?.as_bytes())
     );
-    match x {
-        AcceptedTypes::PHQMD => {
-            dict.insert_code(100121, {
-                let mut p = dict.get(&1000010020).unwrap().clone();
-                // p.id_PDG = Some(100121);
-                p
-            }, false);
-        },
-        _ => {},
+    for path in &args.extra_dict {
+        println!(">> DICTIONARY TABLE [{}]", path);
+        let data = File::open(path).map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+        dict.extend(EposDict::upload(BufReader::new(data), decoder, Some(leptons.clone())));
+    }
+    // private codes of generators
+    let bad_table = |e: fmt::error::ParseError| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string());
+    let mut remaps = fmt::remap::parse_remap_table(
+        BufReader::new(dict_table(dir, "CODE_REMAP.txt", fmt::remap::CODE_REMAP)?.as_bytes())
+    ).map_err(|e| bad_table(e.in_file("CODE_REMAP.txt")))?;
+    for path in &args.code_remap {
+        println!(">> CODE REMAP TABLE [{}]", path);
+        let data = File::open(path).map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+        remaps.extend(fmt::remap::parse_remap_table(BufReader::new(data)).map_err(|e| bad_table(e.in_file(path)))?);
     }
+    let format = clap::ValueEnum::to_possible_value(x).map(|v| v.get_name().to_string()).unwrap_or_default();
+    for entry in remaps.iter().filter(|e| e.applies_to(&format)) {
+        dict.remap(entry).map_err(bad_table)?;
+    }
+    dict.set_on_unknown(args.on_unknown);
+    dict.set_final_state(args.final_state);
 
     drop(dict_lepto);
-    dict
+    Ok(Box::new(dict))
 }
 
 #[macro_export]
 macro_rules! run_criteria_list_inner {
-    ($args:expr, $calc_target:expr, $criteria_vec:expr, $plist_collector:expr, $d_buf_criteria:expr, $dict:expr, $DataFile:ty) => {
+    ($args:expr, $calc_target:expr, $criteria_vec:expr, $plist_collector:expr, $d_buf_criteria:expr, $dict:expr, $selected_nu:expr, $DataFile:ty) => {
         {
             let criteria: Vec< &dyn ScalarCriteria<'_, _, _> > = $criteria_vec;
-            let plist_crit = $plist_collector;
+            let plist_crit: Vec<crate::anlz::ParticleListCompiler> = $plist_collector;
             let start = SystemTime::now();
-            let files = $args.filenames.iter().fold(None,
-                |fo:Option<$DataFile>, x| {
-                    println!(">> FILE READING [{}]", x);
-                    let f = File::open(&x).unwrap();
-                    let x = <$DataFile>::upload(BufReader::new(f), $dict).unwrap();
-                    println!("{} events loaded", x.len());
-                    if let Some(mut fo) = fo {
-                        fo.push_back( x );
-                        Some(fo)            
-                    } else {
-                        Some( x )
-                    }
-                }
-            ).unwrap();
-            let distribution_critera_buf = $d_buf_criteria;
+            let mut distribution_critera_buf = $d_buf_criteria;
+            // pseudorapidity distributions of particle selections: name, codes, bins
+            distribution_critera_buf.extend($selected_nu.iter().map(
+                |(name, codes, min, max, cnt): &(String, std::collections::HashSet<i32>, f64, f64, usize)| StandardDistributionCriteria::new(
+                    crate::anlz::StandardDistributionCriteraDefiner::PNu_selected(codes.clone()), *min, *max, *cnt, name.clone()
+                )
+            ));
             let d_criteria: Vec<&_> = distribution_critera_buf.iter().to_owned().map(
                 |x| {
                     x as &dyn DistributionCritetia<
@@ -93,35 +144,109 @@ macro_rules! run_criteria_list_inner {
                         >
                 }
             ).collect::<Vec<_>>();
-            let end = start.elapsed().unwrap();
-            println!("READING DONE: {} s", end.as_secs_f64());
-            let events = {
-                let mut events = files.borrow_blocks();
-                // if $args.lab {
-                //     events.par_iter_mut().for_each(
-                //         |x|{
-                //             x.event.iter_mut().for_each(
-                //                 |p| {
-                //                     let mp = lab_momentum(p, $dict);
-                //                     p.p = mp;
-                //                 }
-                //             );
-                //         }
-                //     );
-                // }
-                events
-            };
-            let analyzer = HEPEventAnalyzer::new(&events);
-            let distr_res = if $calc_target.contains(&CalcTarget::Distribution) {
-                analyzer.calculate_distribution_criteria(crate::anlz::IS_FINAL_FILTER::<<$DataFile as GenericDataContainer>::Block>, d_criteria, $dict) 
-            } else {Default::default()};
-            let stat_res = if $calc_target.contains(&CalcTarget::Statistics) {
-                analyzer.calculate_criteria(crate::anlz::IS_FINAL_FILTER::<<$DataFile as GenericDataContainer>::Block>, criteria, $dict)
-            } else {Default::default()};
-
-            let list_res = if $calc_target.contains(&CalcTarget::ParticleList) {
-                analyzer.calculate_particle_list(crate::anlz::IS_FINAL_FILTER::<<$DataFile as GenericDataContainer>::Block>, plist_crit, $dict)
+            let mut distr_res: crate::anlz::DistributionResults = Default::default();
+            let mut stat_res: crate::anlz::ScalarAnalyzerResults = Default::default();
+            let mut list_res: Vec<crate::anlz::ParticleListCompiler> = if $calc_target.contains(&CalcTarget::ParticleList) {
+                plist_crit.iter().map(|x| x.clean_clone()).collect()
             } else {Default::default()};
+            // particles missing in dictionary: census of the run, skipped by `--on-unknown skip`
+            let census = crate::fmt::database::ParticleCensus::default();
+            let on_unknown = crate::fmt::database::ParticleDatabase::on_unknown($dict);
+            // `--final-state all` takes non-final particles of all formats
+            let final_state = crate::fmt::database::ParticleDatabase::final_state($dict);
+            let in_final_state = |p: &<<$DataFile as GenericDataContainer>::Block as HEPEvent>::P, d: &<<<$DataFile as GenericDataContainer>::Block as HEPEvent>::P as crate::anlz::Particle>::Decoder| {
+                final_state == crate::fmt::istat::FinalState::All || crate::anlz::IS_FINAL_FILTER::<<$DataFile as GenericDataContainer>::Block>(p, d)
+            };
+            let filter = |p: &<<$DataFile as GenericDataContainer>::Block as HEPEvent>::P, d: &<<<$DataFile as GenericDataContainer>::Block as HEPEvent>::P as crate::anlz::Particle>::Decoder| {
+                in_final_state(p, d)
+                    && (on_unknown != crate::fmt::database::UnknownParticlePolicy::Skip || d.knows(crate::anlz::Particle::code(p, d)))
+            };
+            let read_file = |x: &String| {
+                println!(">> FILE READING [{}]", x);
+                let mut distr_res: crate::anlz::DistributionResults = Default::default();
+                let mut stat_res: crate::anlz::ScalarAnalyzerResults = Default::default();
+                let mut list_res: Vec<crate::anlz::ParticleListCompiler> = if $calc_target.contains(&CalcTarget::ParticleList) {
+                    plist_crit.iter().map(|x| x.clean_clone()).collect()
+                } else {Default::default()};
+                let mut reader = crate::fmt::generic::open_reader::<$DataFile>(x, $args.on_error, $args.exclude_bad, !$args.sequential)
+                    .unwrap_or_else(|e| { eprintln!("{}: {}", x, e); std::process::exit(1) });
+                let mut cache = if $calc_target.contains(&CalcTarget::Cache) {
+                    Some(crate::fmt::cache::CacheWriter::create(x, $args.input_type()).unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) }))
+                } else { None };
+                let events = reader.by_ref().inspect(
+                    |ev| if let (Some(w), Ok(block)) = (cache.as_mut(), ev) {
+                        crate::fmt::generic::EventWriter::write_event(w, block).unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) });
+                    }
+                );
+                let n = HEPEventAnalyzer::for_each_chunk(events, $args.chunk_size,
+                    |analyzer| {
+                        let unknown = analyzer.unknown_particles(&in_final_state, $dict);
+                        census.add(x, &unknown);
+                        if on_unknown == crate::fmt::database::UnknownParticlePolicy::Abort && !unknown.is_empty() {
+                            eprintln!("ERROR: particles missing in dictionary (use --on-unknown skip or zero to analyse them):");
+                            census.report().iter().for_each(|x| eprintln!("{}", x));
+                            std::process::exit(1);
+                        }
+                        if $calc_target.contains(&CalcTarget::Distribution) {
+                            crate::anlz::merge_distribution_results(
+                                &mut distr_res,
+                                analyzer.calculate_distribution_criteria(&filter, d_criteria.clone(), $dict)
+                            );
+                        }
+                        if $calc_target.contains(&CalcTarget::Statistics) {
+                            stat_res.append(
+                                analyzer.calculate_criteria(&filter, criteria.clone(), $dict)
+                            );
+                        }
+                        if $calc_target.contains(&CalcTarget::ParticleList) {
+                            let chunk_lists = analyzer.calculate_particle_list(
+                                &filter,
+                                list_res.iter().map(|x| x.clean_clone()).collect(),
+                                $dict
+                            );
+                            list_res.iter_mut().zip(chunk_lists).for_each(|(a, b)| a.append(b));
+                        }
+                    }
+                ).unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) });
+                // file summary is printed in input order
+                let mut summary = vec![format!(">> FILE DONE [{}]: {} events processed", x, n)];
+                let skipped = crate::fmt::generic::EventReader::skipped(&reader);
+                if skipped != Default::default() {
+                    summary.push(format!("{} events and {} lines skipped on errors", skipped.events, skipped.lines));
+                }
+                let integrity = crate::fmt::generic::EventReader::integrity(&reader);
+                summary.push(format!(
+                    "integrity: {} ok, {} short, {} truncated{}",
+                    integrity.ok, integrity.short, integrity.truncated,
+                    if integrity.excluded && integrity.bad() > 0 { " (excluded from analysis)" } else { "" }
+                ));
+                if let Some(w) = cache {
+                    crate::fmt::generic::EventWriter::finish(w).unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) });
+                    summary.push(format!("cache written: {}{}", x, crate::fmt::cache::CACHE_SUFFIX));
+                }
+                (summary, stat_res, distr_res, list_res)
+            };
+            let per_file: Vec<_> = if $args.sequential {
+                $args.filenames.iter().map(read_file).collect()
+            } else {
+                use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+                $args.filenames.par_iter().map(read_file).collect()
+            };
+            // results are merged in input order, so they do not depend on reading order
+            per_file.into_iter().for_each(
+                |(summary, stat, distr, list)| {
+                    summary.iter().for_each(|x| println!("{}", x));
+                    stat_res.append(stat);
+                    crate::anlz::merge_distribution_results(&mut distr_res, distr);
+                    list_res.iter_mut().zip(list).for_each(|(a, b)| a.append(b));
+                }
+            );
+            if !census.is_empty() {
+                println!(">> UNKNOWN PARTICLES ({:?}):", on_unknown);
+                census.report().iter().for_each(|x| println!("{}", x));
+            }
+            let end = start.elapsed().unwrap();
+            println!("READING AND ANALYSIS DONE: {} s", end.as_secs_f64());
 
             (stat_res, distr_res, list_res)
         }
@@ -136,12 +261,13 @@ macro_rules! run_criteria_list {
         $calc_target:expr,
         $criteria_vec:expr,
         $plist_collector:expr,
+        $selected_nu:expr,
         [$(
             ($Definer: ident::$DefinerVariant: ident, $DEG_MIN:expr, $DEG_MAX:expr, $DEG_CNT:expr, $NAME:expr $(, arg=($( $ARG:expr, )*) )? )
         ),*]
     ) => {
         
-        match $args.ftype {
+        match $args.input_type() {
             AcceptedTypes::EPOS => {
                 run_criteria_list_inner!(
                     { $args },
@@ -155,7 +281,7 @@ macro_rules! run_criteria_list {
                                 {
                                     standard_criteria!(
                                         $Definer::$DefinerVariant,
-                                        OSCEposDataFile<'_>,
+                                        OSCEposDataFile,
                                         $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG ,)* )?
                                     )
                                 }
@@ -163,7 +289,8 @@ macro_rules! run_criteria_list {
                         )
                     },
                     $dict,
-                    OSCEposDataFile<'_>
+                    $selected_nu,
+                    OSCEposDataFile
                 )
             },
             AcceptedTypes::UrQmdF19 => {
@@ -179,7 +306,7 @@ macro_rules! run_criteria_list {
                                 {
                                     standard_criteria!(
                                         $Definer::$DefinerVariant,
-                                        OSC97UrQMDDataFile<'_>,
+                                        OSC97UrQMDDataFile,
                                         $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
                                     )
                                 }
@@ -187,7 +314,8 @@ macro_rules! run_criteria_list {
                         )
                     },
                     $dict,
-                    OSC97UrQMDDataFile<'_>
+                    $selected_nu,
+                    OSC97UrQMDDataFile
                 )
             },
             AcceptedTypes::PHQMD => {
@@ -203,7 +331,7 @@ macro_rules! run_criteria_list {
                                 {
                                     standard_criteria!(
                                         $Definer::$DefinerVariant,
-                                        PHQMDDataFile<'_>,
+                                        PHQMDDataFile,
                                         $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
                                     )
                                 }
@@ -211,7 +339,8 @@ macro_rules! run_criteria_list {
                         )
                     },
                     $dict,
-                    PHQMDDataFile<'_>
+                    $selected_nu,
+                    PHQMDDataFile
                 )
             },
             AcceptedTypes::QGSM => {
@@ -227,7 +356,7 @@ macro_rules! run_criteria_list {
                                 {
                                     standard_criteria!(
                                         $Definer::$DefinerVariant,
-                                        QGSMDataFile<'_>,
+                                        QGSMDataFile,
                                         $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
                                     )
                                 }
@@ -235,7 +364,8 @@ macro_rules! run_criteria_list {
                         )
                     },
                     $dict,
-                    QGSMDataFile<'_>
+                    $selected_nu,
+                    QGSMDataFile
                 )
             },
             AcceptedTypes::HepMC => {
@@ -251,7 +381,232 @@ macro_rules! run_criteria_list {
                                 {
                                     standard_criteria!(
                                         $Definer::$DefinerVariant,
-                                        HepMCDataFile<'_>,
+                                        HepMCDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    HepMCDataFile
+                )
+            },
+            AcceptedTypes::HepMC2 => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        HepMC2DataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    HepMC2DataFile
+                )
+            },
+            AcceptedTypes::Oscar2013 => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        OSCAR2013DataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    OSCAR2013DataFile
+                )
+            },
+            AcceptedTypes::Oscar2013Bin => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        OSCAR2013BinDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    OSCAR2013BinDataFile
+                )
+            },
+            AcceptedTypes::PHSD => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        PHSDDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    PHSDDataFile
+                )
+            },
+            AcceptedTypes::AMPT => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        AMPTDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    AMPTDataFile
+                )
+            },
+            AcceptedTypes::LHE => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        LHEDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    LHEDataFile
+                )
+            },
+            AcceptedTypes::UrQMDF13 | AcceptedTypes::UrQMDF14 => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        UrQMDDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    UrQMDDataFile
+                )
+            },
+            AcceptedTypes::UrQMDF15 => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        UrQMDCollisionDataFile,
+                                        $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
+                                    )
+                                }
+                            ),* ,
+                        )
+                    },
+                    $dict,
+                    $selected_nu,
+                    UrQMDCollisionDataFile
+                )
+            },
+            AcceptedTypes::Cache => {
+                run_criteria_list_inner!(
+                    { $args },
+                    { $calc_target },
+                    { $criteria_vec },
+                    { $plist_collector },
+                    {
+                        vec!(
+                            $(
+                                #[allow(unused_assignments)]
+                                {
+                                    standard_criteria!(
+                                        $Definer::$DefinerVariant,
+                                        CacheDataFile,
                                         $DEG_MIN, $DEG_MAX, $DEG_CNT, $NAME $(, arg=$($ARG, )* )?
                                     )
                                 }
@@ -259,7 +614,8 @@ macro_rules! run_criteria_list {
                         )
                     },
                     $dict,
-                    HepMCDataFile<'_>
+                    $selected_nu,
+                    CacheDataFile
                 )
             },
         }
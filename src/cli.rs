@@ -1,8 +1,13 @@
 use clap::{Parser, *};
 
+use std::path::PathBuf;
+
+use crate::anlz::{EventSelection, StandardCriteria};
+use crate::fmt::decoder::DctCoding;
+
 
 #[derive(
-    clap::ValueEnum, Clone, Debug, Default
+    clap::ValueEnum, Clone, Debug, Default, PartialEq, Eq
 )]
 pub enum AcceptedTypes {
     #[default]
@@ -11,6 +16,26 @@ pub enum AcceptedTypes {
     PHQMD,
     QGSM,
     HepMC,
+    /// HepMC2 IO_GenEvent ASCII
+    HepMC2,
+    /// OSCAR2013 ASCII particle lists (SMASH)
+    Oscar2013,
+    /// OSCAR2013 binary particle lists (SMASH)
+    Oscar2013Bin,
+    /// PHSD phsd.dat
+    PHSD,
+    /// AMPT ampt.dat
+    AMPT,
+    /// Les Houches Event file (.lhe)
+    LHE,
+    /// UrQMD freeze-out file13
+    UrQMDF13,
+    /// UrQMD standard output file14 (final state or time steps)
+    UrQMDF14,
+    /// UrQMD collision history file15
+    UrQMDF15,
+    /// hega-rs binary event cache (written with `-t cache`)
+    Cache,
 }
 
 #[derive(
@@ -20,7 +45,9 @@ pub enum CalcTarget {
     #[default]
     Statistics,
     Distribution,
-    ParticleList
+    ParticleList,
+    /// write parsed events of each input into binary cache `<input>.hegc`
+    Cache,
 }
 
 #[derive(
@@ -40,6 +67,69 @@ pub enum CalcMode {
     InTime
 }
 
+/// Table of particle masses, charges and quantum numbers
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq
+)]
+pub enum ParticleTable {
+    /// EPOS particle list, decodes EPOS codes and PDG codes
+    #[default]
+    Epos,
+    /// PDG Review of Particle Physics mass and width table, decodes PDG codes only
+    Pdg,
+}
+
+/// Output format of `convert`
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq
+)]
+pub enum OutputFormat {
+    /// HepMC3 ASCII (Asciiv3)
+    #[value(name = "hepmc3")]
+    HepMC3,
+    /// OSCAR2013 ASCII particle lists
+    Oscar2013,
+    /// QGSM-style text
+    QGSM,
+}
+
+impl OutputFormat {
+    /// Suffix appended to input path of converted file
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            OutputFormat::HepMC3 => ".hepmc",
+            OutputFormat::Oscar2013 => ".oscar",
+            OutputFormat::QGSM => ".qgsm",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Convert input files into another format.
+    /// Each input is written to `<input>.hepmc`, `<input>.oscar` or `<input>.qgsm`,
+    /// particle codes are mapped to PDG codes
+    Convert {
+        /// Output format
+        #[clap(long)]
+        to: OutputFormat,
+    },
+    /// Write events passing all selections into `<input>.skim` in the input format.
+    /// File header and kept lines are copied as is
+    Skim {
+        /// Event selection `<criteria> <op> <value>`, e.g. `"PseudorapidityFilterCnt(3.5, 5.8) >= 1"`.
+        /// Criteria is summed over final particles of event; may be repeated
+        #[clap(long)]
+        select: Vec<EventSelection>,
+        /// Keep only particles with these codes (codes of the input format)
+        #[clap(long, value_delimiter = ',', allow_negative_numbers = true)]
+        keep_codes: Vec<i32>,
+        /// Keep only final particles
+        #[clap(long)]
+        final_only: bool,
+    },
+}
+
 /*
 #[derive(Debug, clap::Args)]
 #[group(required = true, multiple = false)]
@@ -56,8 +146,8 @@ pub struct FileInput {
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Type of file
-    pub ftype: AcceptedTypes,
+    /// Type of file. Detected from file contents if not set
+    pub ftype: Option<AcceptedTypes>,
 
     /// Take if need to check in Lab system [change Pz momentum]
     // #[clap(long)]
@@ -71,10 +161,97 @@ pub struct Args {
     pub mode: CalcMode,
 
     /// List of files, delimeter ','. Use "quotes" if path contains whitespaces
-    #[clap(short, long, num_args = 1.., value_delimiter = ',')]
+    #[clap(short, long, num_args = 1.., value_delimiter = ',', global = true)]
     pub filenames: Vec<String>,
 
     #[clap(short, long="output", default_value="results.csv.stat")]
-    pub o: String
+    pub o: String,
+
+    /// Count of events kept in memory at once.
+    /// Files are read as a stream and analysed chunk by chunk
+    #[clap(long, default_value="10000")]
+    pub chunk_size: usize,
 
-}
\ No newline at end of file
+    /// Reaction on corrupted input
+    #[clap(long, default_value="fail", global = true)]
+    pub on_error: crate::fmt::error::ErrorPolicy,
+
+    /// Reaction on particles missing in particle dictionary:
+    /// stop with their census, skip them or analyse them as neutral massless particles
+    #[clap(long, default_value="abort", global = true)]
+    pub on_unknown: crate::fmt::database::UnknownParticlePolicy,
+
+    /// Particles analysed as final state of event: final particles, EPOS last generation without
+    /// hadronic cascade (no-hacas), EPOS core particles (core) or all particles
+    #[clap(long, default_value="final", global = true)]
+    pub final_state: crate::fmt::istat::FinalState,
+
+    /// Exclude events which particle count differs from their header
+    /// (short events and event truncated by the end of file) from analysis
+    #[clap(long, global = true)]
+    pub exclude_bad: bool,
+
+    /// Read files one by one, each line by line.
+    /// By default files are read in parallel and each file is parsed by event ranges in parallel;
+    /// results are identical
+    #[clap(long, global = true)]
+    pub sequential: bool,
+
+    /// Directory with dictionary tables (EPOS.particles.txt, EPOS_LEPTONS.particles.txt, NUCLEI_LIST.txt, PDG.particles.txt, CODE_REMAP.txt)
+    /// replacing the embedded ones; tables missing there are taken embedded.
+    /// Defaults to HEGA_DICT_DIR environment variable
+    #[clap(long, global = true)]
+    pub dict_dir: Option<PathBuf>,
+
+    /// Particle table of dictionary
+    #[clap(long, default_value="epos", global = true)]
+    pub particle_table: ParticleTable,
+
+    /// Numbering of particle codes of input files, overrides numbering of the input format
+    /// (EPOS for EPOS output, PDG for the others), e.g. `qgsjet` for cosmic-ray generator outputs
+    #[clap(long, global = true)]
+    pub input_codes: Option<DctCoding>,
+
+    /// Numbering of particle codes written into particle lists, codes of input files by default.
+    /// Particles without code in this numbering are written as NA
+    #[clap(long, global = true)]
+    pub output_codes: Option<DctCoding>,
+
+    /// Extra particle tables in EPOS.particles.txt format, delimeter ','.
+    /// Their particles are added to the dictionary and replace particles with the same codes
+    #[clap(long, num_args = 1.., value_delimiter = ',', global = true)]
+    pub extra_dict: Vec<String>,
+
+    /// Extra code remap tables in CODE_REMAP.txt format, delimeter ','.
+    /// Lines `<format> <code> <PDG code or particle>` decode private codes of generators
+    #[clap(long, num_args = 1.., value_delimiter = ',', global = true)]
+    pub code_remap: Vec<String>,
+
+    /// Particle selection: particle codes, names, common names (p, pbar, K0S, Lambda) and classes
+    /// (charged-hadrons, baryons, antibaryons, strange-hadrons, leptons, photons, nuclei, nuclei(Z)), delimeter ','.
+    /// Each selection adds a particle list, pseudorapidity distribution and statistics counter; may be repeated
+    #[clap(long, global = true)]
+    pub particles: Vec<String>,
+
+    /// Extra statistics criteria added after default columns, e.g. `Strangeness`, `FinStrangeCnt`,
+    /// `PseudorapidityFilterCnt(2.0, 3.0)`; may be repeated
+    #[clap(long, global = true)]
+    pub criteria: Vec<StandardCriteria>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// Type of input files, resolved on startup
+    pub fn input_type(&self) -> &AcceptedTypes {
+        self.ftype.as_ref().expect("input type is not resolved")
+    }
+
+    /// Directory of dictionary tables given by `--dict-dir` or environment
+    pub fn dict_dir(&self) -> Option<PathBuf> {
+        self.dict_dir.clone().or_else(
+            || std::env::var_os(crate::fmt::decoder::DICT_DIR_ENV).filter(|x| !x.is_empty()).map(PathBuf::from)
+        )
+    }
+}
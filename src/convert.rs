@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::time::SystemTime;
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::cli::{AcceptedTypes, Args, OutputFormat};
+use crate::fmt::{
+    ampt::AMPTDataFile, cache::{CacheDataFile, ToCache}, database::ParticleDatabase,
+    generic::{open_reader, EventReader, EventWriter, GenericDataContainer},
+    hepmc::{HepMCDataFile, HepMCWriter}, hepmc2::HepMC2DataFile, lhe::LHEDataFile,
+    oscar::{OSC97UrQMDDataFile, OSCEposDataFile}, oscar2013::{OSCAR2013BinDataFile, OSCAR2013DataFile, OSCAR2013Writer},
+    phqmd::PHQMDDataFile, phsd::PHSDDataFile, qgsm::{QGSMDataFile, QGSMWriter},
+    urqmd::{UrQMDCollisionDataFile, UrQMDDataFile},
+};
+
+// Conversion of input files into another format
+
+/// Writes all events of `reader`, returns count of written events
+fn write_events<R: EventReader, W: EventWriter>(reader: &mut R, mut writer: W) -> Result<usize, std::io::Error>
+where R::Block: ToCache
+{
+    let mut n = 0;
+    for block in reader {
+        writer.write_event(&block?)?;
+        n += 1;
+    }
+    writer.finish()?;
+    Ok(n)
+}
+
+/// Converts one file, returns summary lines
+fn convert_file<'a, C: GenericDataContainer<'a>>(path: &str, args: &Args, dict: &dyn ParticleDatabase, to: OutputFormat) -> Result<Vec<String>, std::io::Error>
+where C::Block: ToCache
+{
+    println!(">> FILE CONVERTING [{}]", path);
+    let mut reader = open_reader::<C>(path, args.on_error, args.exclude_bad, !args.sequential)?;
+    let out_path = format!("{}{}", path, to.suffix());
+    let out = File::create(&out_path)?;
+    let n = match to {
+        OutputFormat::HepMC3 => write_events(&mut reader, HepMCWriter::new(out, dict)),
+        OutputFormat::Oscar2013 => write_events(&mut reader, OSCAR2013Writer::new(out, dict)),
+        OutputFormat::QGSM => write_events(&mut reader, QGSMWriter::new(out, dict)),
+    }?;
+    let mut summary = vec![format!(">> FILE DONE [{}]: {} events written to {}", path, n, out_path)];
+    if reader.take_header().is_none() {
+        summary.push("file header not found".to_string());
+    }
+    let skipped = reader.skipped();
+    if skipped != Default::default() {
+        summary.push(format!("{} events and {} lines skipped on errors", skipped.events, skipped.lines));
+    }
+    let integrity = reader.integrity();
+    summary.push(format!(
+        "integrity: {} ok, {} short, {} truncated{}",
+        integrity.ok, integrity.short, integrity.truncated,
+        if integrity.excluded && integrity.bad() > 0 { " (excluded from output)" } else { "" }
+    ));
+    Ok(summary)
+}
+
+fn convert_files<'a, C: GenericDataContainer<'a>>(args: &Args, dict: &dyn ParticleDatabase, to: OutputFormat)
+where C::Block: ToCache
+{
+    let convert = |x: &String| {
+        convert_file::<C>(x, args, dict, to).unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) })
+    };
+    let per_file: Vec<_> = if args.sequential {
+        args.filenames.iter().map(convert).collect()
+    } else {
+        args.filenames.par_iter().map(convert).collect()
+    };
+    // summaries are printed in input order
+    per_file.iter().flatten().for_each(|x| println!("{}", x));
+}
+
+/// Converts every input file into format `to`
+pub fn run_convert(args: &Args, dict: &dyn ParticleDatabase, to: OutputFormat) {
+    let start = SystemTime::now();
+    match args.input_type() {
+        AcceptedTypes::EPOS => convert_files::<OSCEposDataFile>(args, dict, to),
+        AcceptedTypes::UrQmdF19 => convert_files::<OSC97UrQMDDataFile>(args, dict, to),
+        AcceptedTypes::PHQMD => convert_files::<PHQMDDataFile>(args, dict, to),
+        AcceptedTypes::QGSM => convert_files::<QGSMDataFile>(args, dict, to),
+        AcceptedTypes::HepMC => convert_files::<HepMCDataFile>(args, dict, to),
+        AcceptedTypes::HepMC2 => convert_files::<HepMC2DataFile>(args, dict, to),
+        AcceptedTypes::Oscar2013 => convert_files::<OSCAR2013DataFile>(args, dict, to),
+        AcceptedTypes::Oscar2013Bin => convert_files::<OSCAR2013BinDataFile>(args, dict, to),
+        AcceptedTypes::PHSD => convert_files::<PHSDDataFile>(args, dict, to),
+        AcceptedTypes::AMPT => convert_files::<AMPTDataFile>(args, dict, to),
+        AcceptedTypes::LHE => convert_files::<LHEDataFile>(args, dict, to),
+        AcceptedTypes::UrQMDF13 | AcceptedTypes::UrQMDF14 => convert_files::<UrQMDDataFile>(args, dict, to),
+        AcceptedTypes::UrQMDF15 => convert_files::<UrQMDCollisionDataFile>(args, dict, to),
+        AcceptedTypes::Cache => convert_files::<CacheDataFile>(args, dict, to),
+    }
+    println!("CONVERSION DONE: {} s", start.elapsed().unwrap().as_secs_f64());
+}
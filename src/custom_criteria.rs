@@ -11,14 +11,14 @@ pub enum MyExampleCriterias {
     StupidCriteria2(i32) // if we need parameter
 }
 
-impl<'a, S, T> ScalarCriteria<'a, S, T> for MyExampleCriterias 
+impl<'a, S: ?Sized, T> ScalarCriteria<'a, S, T> for MyExampleCriterias 
 where T: Particle<Decoder = S> + 'static, {
 
     // Here 
     // p: &T is argument 'p' of type: reference to T where T implements Particle trait
     // from anlz::generic
     // dec is DECODER described im Particle implementation
-    // for example, EposDict for EPOS UrQMD or PHQMD
+    // for example, dyn ParticleDatabase (EPOS or PDG particle table) for all input formats
     // 
     fn get_criteria_value(&self, p: &T, dec: &S) -> f64 {
         match self {
@@ -0,0 +1,227 @@
+use std::error::Error;
+
+// AMPT (ampt.dat) format reader and interpreter
+
+use super::error::{columns, ErrorPolicy, ParseToken};
+use super::generic::*;
+
+#[derive(Debug)]
+pub struct AMPTBlock {
+    /// Block Header
+    pub header: AMPTBlockHeader,
+    /// Particles
+    pub event: Vec<AMPTParticle>
+}
+
+/// `event test nout b npart_proj npart_targ nel_proj ninel_proj nel_targ ninel_targ [psi]`
+#[derive(Debug, Default, Clone)]
+pub struct AMPTBlockHeader {
+    pub event_id: usize,
+    /// test (run) number
+    pub test_id: i32,
+    /// Count of particles in event
+    pub nout: usize,
+    /// impact parameter, fm
+    pub b: f64,
+    /// participant nucleons in projectile
+    pub npart_proj: i32,
+    /// participant nucleons in target
+    pub npart_targ: i32,
+    /// elastic and inelastic participants in projectile
+    pub npart_proj_el_inel: (i32, i32),
+    /// elastic and inelastic participants in target
+    pub npart_targ_el_inel: (i32, i32),
+    /// event plane angle (written by some AMPT versions)
+    pub event_plane_angle: Option<f64>,
+}
+
+impl<'a> TryFrom<&'a [&'a str]> for AMPTBlockHeader {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        Ok(Self {
+            event_id: value[0].token()?,
+            test_id: value[1].token()?,
+            nout: value[2].token()?,
+            b: value[3].token()?,
+            npart_proj: value[4].token()?,
+            npart_targ: value[5].token()?,
+            npart_proj_el_inel: (value[6].token()?, value[7].token()?),
+            npart_targ_el_inel: (value[8].token()?, value[9].token()?),
+            event_plane_angle: value.get(10).map(|x| x.token()).transpose()?,
+        })
+    }
+}
+
+impl AMPTBlockHeader {
+    /// Count of participants
+    pub fn npart(&self) -> i32 {
+        self.npart_proj + self.npart_targ
+    }
+}
+
+#[derive(Debug)]
+pub struct AMPTHeader {
+}
+
+#[derive(Debug)]
+pub struct AMPTDataFile;
+
+/// `pdg px py pz mass x y z t`
+#[derive(Debug)]
+pub struct AMPTParticle {
+    pub code: i32,
+    pub p: (f64, f64, f64),
+    pub mass: f64,
+    pub coords: (f64, f64, f64),
+    pub time: f64,
+}
+
+impl<'a> TryFrom<[&'a str; 9]> for AMPTParticle {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: [&'a str; 9]) -> Result<Self, Self::Error> {
+        let s = Self {
+            code:   value[0].token()?,
+            p:      (value[1].token()?, value[2].token()?, value[3].token()?),
+            mass:   value[4].token()?,
+            coords: (value[5].token()?, value[6].token()?, value[7].token()?),
+            time:   value[8].token()?,
+        };
+        Ok(s)
+    }
+}
+
+impl<'a> TryFrom<(AMPTBlockHeader, &'a Vec<String>)> for AMPTBlock {
+    type Error = Box<dyn Error>;
+    fn try_from(value: (AMPTBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let particles = parse_lines(
+            value.1,
+            |line| AMPTParticle::try_from( columns::<9>(line)? )
+        )?;
+        Ok(
+            Self {
+                header: value.0,
+                event: particles,
+            }
+        )
+    }
+}
+
+impl<'a> DataBlock<'a, AMPTBlockHeader> for AMPTBlock {
+    fn get_header(&self) -> &AMPTBlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for AMPTBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Line parser of AMPT output
+#[derive(Debug, Default)]
+pub struct AMPTParser {
+    buf: EventBuffer<AMPTBlockHeader>,
+}
+
+impl BlockParser for AMPTParser {
+    type Header = AMPTHeader;
+
+    type Block = AMPTBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let tr = line.trim();
+        let tokens: Vec<_> = tr.split_ascii_whitespace().collect();
+        match tokens.len() {
+            (10..) => {
+                // new event
+                self.buf.start(idx, AMPTBlockHeader::try_from(tokens.as_slice()))
+            },
+            0 => {
+                Ok(None)
+            },
+            _ => {
+                // line event, short lines are reported by block parsing
+                self.buf.push(idx, line);
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        line.split_ascii_whitespace().count() >= 10
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    /// AMPT output has no file header
+    fn take_header(&mut self) -> Option<Self::Header> {
+        Some(AMPTHeader {})
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Event is its header line and one line per particle
+impl EventLayout for AMPTParser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((1..lines.len()).filter(|i| {
+            let n = lines[*i].split_ascii_whitespace().count();
+            n != 0
+        }).collect())
+    }
+
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        lines[0] = replace_token(&lines[0], 2, &n.to_string());
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for AMPTDataFile {
+    type Header = AMPTHeader;
+
+    type BlockHeader = AMPTBlockHeader;
+
+    type Block = AMPTBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, AMPTParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+#[test]
+fn test_ampt_stream() {
+    let data = "     1      1     2   6.3150    80   79    0   80    0   79   0.25
+   2212   0.100   0.200   5.000   0.938   1.0   2.0   3.0   10.0
+   -211   0.010   0.020   0.100   0.140   1.0   2.0   3.0   11.0
+     2      1     1   2.1000   190  191    2  188    1  190
+    111   0.100   0.000   0.000   0.135   0.0   0.0   0.0   1.0
+";
+    let mut reader = AMPTDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let first = reader.next().unwrap().unwrap();
+    let second = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!(first.header.npart(), 159);
+    assert_eq!(first.header.event_plane_angle, Some(0.25));
+    assert_eq!(first.event.len(), 2);
+    assert_eq!(first.event[1].code, -211);
+    assert_eq!(second.header.event_id, 2);
+    assert_eq!(second.header.event_plane_angle, None);
+    assert_eq!((second.header.npart_proj_el_inel, second.header.npart_targ_el_inel), ((2, 188), (1, 190)));
+    assert_eq!(second.event[0].time, 1.0);
+}
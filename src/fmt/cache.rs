@@ -0,0 +1,893 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+
+use clap::ValueEnum;
+
+// Compact binary cache of parsed events for fast re-analysis.
+//
+// Only dictionary independent values are stored, so the cache is analysed
+// with the dictionary of its source format
+
+use super::error::ParseError;
+use super::generic::*;
+use super::{
+    ampt::AMPTBlock, hepmc::HepMCBlock, hepmc2::HepMC2Block, lhe::LHEBlock,
+    oscar::OSCEposBlock, oscar2013::OSCAR2013Block, phqmd::PHQMDBlock, phsd::PHSDBlock,
+    qgsm::QGSMBlock, urqmd::{UrQMDBlock, UrQMDCollisionBlock, UrQMDParticle},
+};
+use crate::cli::AcceptedTypes;
+
+/// Magic of cache files
+pub const CACHE_MAGIC: &[u8; 4] = b"HEGC";
+/// Current cache format version
+pub const CACHE_VERSION: u16 = 1;
+/// Suffix appended to input path by cache writer
+pub const CACHE_SUFFIX: &str = ".hegc";
+
+/// Value is read from the input file, not from dictionary
+const HAS_CHARGE: u8 = 1 << 0;
+const HAS_BARYON: u8 = 1 << 1;
+const HAS_LEPTON: u8 = 1 << 2;
+const HAS_MASS: u8 = 1 << 3;
+const HAS_POSITION: u8 = 1 << 4;
+/// Particle is in final state
+const IS_FINAL: u8 = 1 << 5;
+
+/// Declared count of events which format does not declare it
+const NO_COUNT: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheParticle {
+    pub code: i32,
+    /// status of source format, e.g. HepMC status or EPOS istat
+    pub status: i32,
+    pub is_final: bool,
+    /// electric charge, if it is written in the input
+    pub charge: Option<i32>,
+    /// baryon number, if it is written in the input
+    pub baryon: Option<i32>,
+    /// lepton number, if it is written in the input
+    pub lepton: Option<i32>,
+    pub p0: f64,
+    pub p: (f64, f64, f64),
+    /// mass, None if the format takes it from dictionary
+    pub mass: Option<f64>,
+    /// (x, y, z, t), if it is written in the input
+    pub position: Option<(f64, f64, f64, f64)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheBlockHeader {
+    pub event_id: usize,
+    /// Count of particles declared by source event header
+    pub nout: Option<usize>,
+    /// numeric values of source event header, names are stored in `CacheHeader::value_names`
+    pub values: Vec<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheBlock {
+    /// Block Header
+    pub header: CacheBlockHeader,
+    /// Particles
+    pub event: Vec<CacheParticle>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHeader {
+    /// cache format version
+    pub version: u16,
+    /// format of cached input, defines dictionary used for analysis
+    pub source: AcceptedTypes,
+    pub value_names: Vec<String>,
+}
+
+impl CacheHeader {
+    /// Event header value by name
+    pub fn value(&self, hd: &CacheBlockHeader, name: &str) -> Option<f64> {
+        self.value_names.iter().position(|x| x == name).and_then(|i| hd.values.get(i).cloned())
+    }
+}
+
+impl<'a> TryFrom<(CacheBlockHeader, &'a Vec<String>)> for CacheBlock {
+    type Error = Box<dyn Error>;
+
+    fn try_from(_value: (CacheBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        Err(ParseError::format("Cache blocks are binary and can not be read from text lines").into())
+    }
+}
+
+impl<'a> DataBlock<'a, CacheBlockHeader> for CacheBlock {
+    fn get_header(&self) -> &CacheBlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for CacheBlock {
+    fn declared_count(&self) -> Option<usize> {
+        self.header.nout
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Event which can be written to cache
+pub trait ToCache: ParticleCount {
+    /// Names of event header values
+    const VALUES: &'static [&'static str];
+
+    /// Event id written in the input, events without it are numbered by cache writer
+    fn event_id(&self) -> Option<usize> {
+        None
+    }
+
+    /// Event header values, in order of `VALUES`; None values are stored as NaN
+    fn values(&self) -> Vec<f64>;
+
+    fn particles(&self) -> Vec<CacheParticle>;
+
+    /// Event header value by name, None if it is not set
+    fn value(&self, name: &str) -> Option<f64> {
+        Self::VALUES.iter().position(|x| *x == name)
+            .and_then(|i| self.values().get(i).cloned())
+            .filter(|x| !x.is_nan())
+    }
+}
+
+/* --------------------------------- WRITER --------------------------------------- */
+
+/// Writer of cache files.
+///
+/// Layout (little endian):
+/// header `"HEGC" u16:version u16:len [len]u8:source_type u16:nvalues {u16:len [len]u8:name}*nvalues`,
+/// then events `'e' u64:event_id u64:declared_count u32:npart u16:nvalues [nvalues]f64:values [npart]particle`,
+/// particle `u8:flags i32:code i32:status f64:p0 f64:px f64:py f64:pz
+/// [i32:charge] [i32:baryon] [i32:lepton] [f64:mass] [f64:x f64:y f64:z f64:t]`,
+/// where optional values are written if their bit is set in flags.
+/// Header is written with the first event, as value names are known only then
+pub struct CacheWriter<W: Write> {
+    data: BufWriter<W>,
+    source: AcceptedTypes,
+    header_written: bool,
+    events: usize,
+}
+
+impl CacheWriter<File> {
+    /// Creates cache of input `path` at `path` + `CACHE_SUFFIX`
+    pub fn create(path: &str, source: &AcceptedTypes) -> Result<Self, std::io::Error> {
+        Ok(Self::new(File::create(format!("{}{}", path, CACHE_SUFFIX))?, source))
+    }
+}
+
+impl<W: Write> CacheWriter<W> {
+    pub fn new(data: W, source: &AcceptedTypes) -> Self {
+        Self {
+            data: BufWriter::new(data),
+            source: source.clone(),
+            header_written: false,
+            events: 0,
+        }
+    }
+
+    fn write_str(&mut self, s: &str) -> std::io::Result<()> {
+        self.data.write_all(&(s.len() as u16).to_le_bytes())?;
+        self.data.write_all(s.as_bytes())
+    }
+
+    fn write_header(&mut self, names: &[&str]) -> std::io::Result<()> {
+        self.header_written = true;
+        let source = self.source.to_possible_value().map(|x| x.get_name().to_owned()).unwrap_or_default();
+        self.data.write_all(CACHE_MAGIC)?;
+        self.data.write_all(&CACHE_VERSION.to_le_bytes())?;
+        self.write_str(&source)?;
+        self.data.write_all(&(names.len() as u16).to_le_bytes())?;
+        names.iter().try_for_each(|x| self.write_str(x))
+    }
+
+    fn write_particle(&mut self, p: &CacheParticle) -> std::io::Result<()> {
+        let flags = [
+            (p.charge.is_some(), HAS_CHARGE),
+            (p.baryon.is_some(), HAS_BARYON),
+            (p.lepton.is_some(), HAS_LEPTON),
+            (p.mass.is_some(), HAS_MASS),
+            (p.position.is_some(), HAS_POSITION),
+            (p.is_final, IS_FINAL),
+        ].iter().filter(|x| x.0).fold(0u8, |acc, x| acc | x.1);
+        self.data.write_all(&[flags])?;
+        self.data.write_all(&p.code.to_le_bytes())?;
+        self.data.write_all(&p.status.to_le_bytes())?;
+        for x in [p.p0, p.p.0, p.p.1, p.p.2] {
+            self.data.write_all(&x.to_le_bytes())?;
+        }
+        for x in [p.charge, p.baryon, p.lepton].iter().flatten() {
+            self.data.write_all(&x.to_le_bytes())?;
+        }
+        if let Some(m) = p.mass {
+            self.data.write_all(&m.to_le_bytes())?;
+        }
+        if let Some((x, y, z, t)) = p.position {
+            for v in [x, y, z, t] {
+                self.data.write_all(&v.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> EventWriter for CacheWriter<W> {
+    fn write_event<B: ToCache>(&mut self, block: &B) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header(B::VALUES)?;
+        }
+        let particles = block.particles();
+        let values = block.values();
+        self.data.write_all(b"e")?;
+        self.data.write_all(&(block.event_id().unwrap_or(self.events) as u64).to_le_bytes())?;
+        self.data.write_all(&block.declared_count().map(|x| x as u64).unwrap_or(NO_COUNT).to_le_bytes())?;
+        self.data.write_all(&(particles.len() as u32).to_le_bytes())?;
+        self.data.write_all(&(values.len() as u16).to_le_bytes())?;
+        for x in values.iter() {
+            self.data.write_all(&x.to_le_bytes())?;
+        }
+        particles.iter().try_for_each(|p| self.write_particle(p))?;
+        self.events += 1;
+        Ok(())
+    }
+
+    /// Writes header of empty cache and flushes data
+    fn finish(mut self) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header(&[])?;
+        }
+        self.data.flush()
+    }
+}
+
+/* --------------------------------- READER --------------------------------------- */
+
+/// Streaming reader of cache files, see `CacheWriter` for layout
+pub struct CacheReader<T: Read> {
+    data: std::io::BufReader<T>,
+    header: Option<CacheHeader>,
+    header_read: bool,
+    events_read: usize,
+    done: bool,
+    source: Option<String>,
+    integrity: IntegrityCheck,
+}
+
+impl<T: Read> CacheReader<T> {
+    pub fn new(data: std::io::BufReader<T>) -> Self {
+        Self {
+            data,
+            header: None,
+            header_read: false,
+            events_read: 0,
+            done: false,
+            source: None,
+            integrity: Default::default(),
+        }
+    }
+
+    /// Error of event being read, located in the input
+    fn locate(&self, e: std::io::Error) -> std::io::Error {
+        let mut pe = ParseError::format(e.to_string()).at_event(self.events_read);
+        if let Some(src) = &self.source {
+            pe = pe.in_file(src);
+        }
+        std::io::Error::new(e.kind(), pe)
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.data.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_i32(&mut self) -> std::io::Result<i32> {
+        Ok(i32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_f64(&mut self) -> std::io::Result<f64> {
+        Ok(f64::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_str(&mut self) -> std::io::Result<String> {
+        let len = self.read_u16()? as usize;
+        let mut buf = vec![0u8; len];
+        self.data.read_exact(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).to_string())
+    }
+
+    fn read_header(&mut self) -> std::io::Result<()> {
+        let magic = self.read_bytes::<4>()?;
+        if &magic != CACHE_MAGIC {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Not a hega-rs cache file"));
+        }
+        let version = self.read_u16()?;
+        if version > CACHE_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unsupported cache version {}, latest supported is {}", version, CACHE_VERSION)
+            ));
+        }
+        let name = self.read_str()?;
+        let source = AcceptedTypes::from_str(&name, true).map_err(
+            |_| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown cache source format \"{}\"", name))
+        )?;
+        let n = self.read_u16()?;
+        let value_names = (0..n).map(|_| self.read_str()).collect::<Result<Vec<_>, _>>()?;
+        self.header = Some(CacheHeader { version, source, value_names });
+        Ok(())
+    }
+
+    fn read_particle(&mut self) -> std::io::Result<CacheParticle> {
+        let flags = self.read_bytes::<1>()?[0];
+        let mut p = CacheParticle {
+            code: self.read_i32()?,
+            status: self.read_i32()?,
+            is_final: flags & IS_FINAL != 0,
+            p0: self.read_f64()?,
+            p: (self.read_f64()?, self.read_f64()?, self.read_f64()?),
+            ..Default::default()
+        };
+        if flags & HAS_CHARGE != 0 {
+            p.charge = Some(self.read_i32()?);
+        }
+        if flags & HAS_BARYON != 0 {
+            p.baryon = Some(self.read_i32()?);
+        }
+        if flags & HAS_LEPTON != 0 {
+            p.lepton = Some(self.read_i32()?);
+        }
+        if flags & HAS_MASS != 0 {
+            p.mass = Some(self.read_f64()?);
+        }
+        if flags & HAS_POSITION != 0 {
+            p.position = Some((self.read_f64()?, self.read_f64()?, self.read_f64()?, self.read_f64()?));
+        }
+        Ok(p)
+    }
+
+    /// Reads next event, the last one may be cut off by the end of file
+    fn read_block(&mut self) -> std::io::Result<Option<CacheBlock>> {
+        let mut tag = [0u8; 1];
+        if self.data.read(&mut tag)? == 0 {
+            self.done = true;
+            return Ok(None);
+        }
+        if tag[0] != b'e' {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown cache block '{}'", tag[0] as char)));
+        }
+        let event_id = self.read_u64()? as usize;
+        let declared = self.read_u64()?;
+        let npart = self.read_u32()? as usize;
+        let nvalues = self.read_u16()?;
+        let values = (0..nvalues).map(|_| self.read_f64()).collect::<Result<Vec<_>, _>>()?;
+        let mut event = Vec::with_capacity(npart);
+        for _ in 0..npart {
+            match self.read_particle() {
+                Ok(p) => event.push(p),
+                // file is cut off inside of event, it is reported as truncated
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    self.done = true;
+                    break;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        self.events_read += 1;
+        Ok(Some(CacheBlock {
+            header: CacheBlockHeader {
+                event_id,
+                nout: (declared != NO_COUNT).then_some(declared as usize),
+                values,
+            },
+            event,
+        }))
+    }
+}
+
+impl<T: Read> Iterator for CacheReader<T> {
+    type Item = Result<CacheBlock, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.header_read {
+            self.header_read = true;
+            if let Err(e) = self.read_header() {
+                self.done = true;
+                return Some(Err(self.locate(e)));
+            }
+        }
+        while !self.done {
+            match self.read_block() {
+                Ok(Some(block)) => {
+                    let event = self.events_read - 1;
+                    if let Some(block) = self.integrity.accept(block, self.done, event, self.source.as_deref()) {
+                        return Some(Ok(block));
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(self.locate(e)));
+                },
+            }
+        }
+        None
+    }
+}
+
+impl<T: Read> EventReader for CacheReader<T> {
+    type Header = CacheHeader;
+    type Block = CacheBlock;
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    /// Binary records can not be resynchronized after corruption,
+    /// so error policy is not applied and reading always stops on error
+    fn set_source(&mut self, source: &str) {
+        self.source = Some(source.to_owned());
+    }
+
+    fn set_exclude_bad(&mut self, exclude: bool) {
+        self.integrity.report.excluded = exclude;
+    }
+
+    fn integrity(&self) -> IntegrityReport {
+        self.integrity.report
+    }
+}
+
+/// Source format of cache files, all files have to be cached from the same format
+pub fn cache_source(filenames: &[String]) -> Result<AcceptedTypes, std::io::Error> {
+    let mut resolved: Option<(AcceptedTypes, &String)> = None;
+    for path in filenames.iter() {
+        let mut reader = CacheReader::new(std::io::BufReader::new(File::open(path)?));
+        reader.read_header().map_err(|e| { reader.source = Some(path.clone()); reader.locate(e) })?;
+        let t = reader.header.map(|x| x.source).unwrap();
+        match &resolved {
+            Some((first, first_path)) if *first != t => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Mixed cache sources: {} is {:?}, {} is {:?}", first_path, first, path, t)
+                ));
+            },
+            Some(_) => {},
+            None => resolved = Some((t, path)),
+        }
+    }
+    resolved.map(|x| x.0).ok_or_else(
+        || std::io::Error::new(std::io::ErrorKind::InvalidInput, "No input files")
+    )
+}
+
+#[derive(Debug)]
+pub struct CacheDataFile;
+
+impl<'a> GenericDataContainer<'a> for CacheDataFile {
+    type Header = CacheHeader;
+
+    type BlockHeader = CacheBlockHeader;
+
+    type Block = CacheBlock;
+
+    type Reader<T: std::io::Read> = CacheReader<T>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        CacheReader::new(data)
+    }
+}
+
+/* ------------------------------ SOURCE FORMATS ---------------------------------- */
+
+fn opt(x: Option<f64>) -> f64 {
+    x.unwrap_or(f64::NAN)
+}
+
+impl ToCache for OSCEposBlock {
+    const VALUES: &'static [&'static str] = &[];
+
+    fn values(&self) -> Vec<f64> {
+        vec![]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                status: x.state,
+                is_final: x.state == 0,
+                p0: x.p0,
+                p: x.p,
+                mass: Some(x.mass),
+                position: Some((x.coords.0, x.coords.1, x.coords.2, x.time)),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for PHQMDBlock {
+    const VALUES: &'static [&'static str] = &[];
+
+    fn values(&self) -> Vec<f64> {
+        vec![]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                is_final: true,
+                charge: Some(x.charge),
+                p0: x.E,
+                p: x.p,
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for QGSMBlock {
+    const VALUES: &'static [&'static str] = &["b", "bx", "by"];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        vec![self.header.b, self.header.bx, self.header.by]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                is_final: true,
+                charge: Some(x.charge as i32),
+                baryon: Some(x.baryon_number as i32),
+                lepton: Some(x.lepton_number as i32),
+                p0: (x.mass.powi(2) + x.p.0.powi(2) + x.p.1.powi(2) + x.p.2.powi(2)).sqrt(),
+                p: x.p,
+                mass: Some(x.mass),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for HepMCBlock {
+    const VALUES: &'static [&'static str] = &[
+        "weight", "b", "npart", "ncoll", "event_plane_angle", "cross_section", "cross_section_error"
+    ];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        let heavy_ion = self.header.heavy_ion.as_ref();
+        let cross_section = self.header.cross_section.as_ref();
+        vec![
+            self.header.weight,
+            opt(heavy_ion.map(|x| x.b)),
+            opt(heavy_ion.map(|x| x.npart() as f64)),
+            opt(heavy_ion.map(|x| x.ncoll as f64)),
+            opt(heavy_ion.map(|x| x.event_plane_angle)),
+            opt(cross_section.map(|x| x.cross_section)),
+            opt(cross_section.map(|x| x.cross_section_error)),
+        ]
+    }
+
+    /// Particles produced in a vertex get its position
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                status: x.status,
+                is_final: x.status == 1,
+                p0: x.energy,
+                p: x.p,
+                mass: Some(x.mass),
+                position: self.vertices.iter().find(|v| x.parent < 0 && v.id == x.parent).and_then(|v| v.position),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for HepMC2Block {
+    const VALUES: &'static [&'static str] = &[
+        "weight", "scale", "alpha_qcd", "alpha_qed", "signal_process_id", "n_mpi",
+        "b", "npart", "ncoll", "event_plane_angle", "cross_section", "cross_section_error"
+    ];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        let heavy_ion = self.header.heavy_ion.as_ref();
+        let cross_section = self.header.cross_section.as_ref();
+        vec![
+            self.header.weight,
+            self.header.scale,
+            self.header.alpha_qcd,
+            self.header.alpha_qed,
+            self.header.signal_process_id as f64,
+            self.header.n_mpi as f64,
+            opt(heavy_ion.map(|x| x.b)),
+            opt(heavy_ion.map(|x| x.npart() as f64)),
+            opt(heavy_ion.map(|x| x.ncoll as f64)),
+            opt(heavy_ion.map(|x| x.event_plane_angle)),
+            opt(cross_section.map(|x| x.cross_section)),
+            opt(cross_section.map(|x| x.cross_section_error)),
+        ]
+    }
+
+    /// Particles produced in a vertex get its position
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                status: x.status,
+                is_final: x.status == 1,
+                p0: x.energy,
+                p: x.p,
+                mass: Some(x.mass),
+                position: self.vertices.iter().find(|v| v.barcode == x.production_vertex).map(|v| v.position),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for OSCAR2013Block {
+    const VALUES: &'static [&'static str] = &["b", "empty", "ensemble"];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        vec![
+            opt(self.header.b),
+            opt(self.header.empty.map(|x| x as u8 as f64)),
+            opt(self.header.ensemble.map(|x| x as f64)),
+        ]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                is_final: true,
+                charge: Some(x.charge),
+                p0: x.p0,
+                p: x.p,
+                mass: Some(x.mass),
+                position: Some((x.coords.0, x.coords.1, x.coords.2, x.time)),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for PHSDBlock {
+    const VALUES: &'static [&'static str] = &["isub", "irun", "b", "b_weight", "npart"];
+
+    fn values(&self) -> Vec<f64> {
+        vec![
+            self.header.isub as f64,
+            self.header.irun as f64,
+            self.header.b,
+            self.header.b_weight,
+            opt(self.header.npart.map(|x| x as f64)),
+        ]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                is_final: true,
+                charge: Some(x.charge),
+                p0: x.e,
+                p: x.p,
+                mass: Some(x.offshell_mass()),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for AMPTBlock {
+    const VALUES: &'static [&'static str] = &["test_id", "b", "npart", "event_plane_angle"];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        vec![
+            self.header.test_id as f64,
+            self.header.b,
+            self.header.npart() as f64,
+            opt(self.header.event_plane_angle),
+        ]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                is_final: true,
+                p0: (x.mass.powi(2) + x.p.0.powi(2) + x.p.1.powi(2) + x.p.2.powi(2)).sqrt(),
+                p: x.p,
+                mass: Some(x.mass),
+                position: Some((x.coords.0, x.coords.1, x.coords.2, x.time)),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+impl ToCache for LHEBlock {
+    const VALUES: &'static [&'static str] = &["process_id", "weight", "scale", "alpha_qed", "alpha_qcd"];
+
+    fn values(&self) -> Vec<f64> {
+        vec![
+            self.header.process_id as f64,
+            self.header.weight,
+            self.header.scale,
+            self.header.alpha_qed,
+            self.header.alpha_qcd,
+        ]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(
+            |x| CacheParticle {
+                code: x.code,
+                status: x.status,
+                is_final: x.status == 1,
+                p0: x.energy,
+                p: x.p,
+                mass: Some(x.mass),
+                ..Default::default()
+            }
+        ).collect()
+    }
+}
+
+/// UrQMD particles carry charge and baryon number (|ityp| < 100), leptons are not propagated
+fn urqmd_particle(x: &UrQMDParticle) -> CacheParticle {
+    CacheParticle {
+        code: x.code,
+        is_final: true,
+        charge: Some(x.charge),
+        baryon: Some(if x.ityp.abs() < 100 { x.ityp.signum() } else { 0 }),
+        lepton: Some(0),
+        p0: x.p0,
+        p: x.p,
+        mass: Some(x.mass),
+        position: Some((x.coords.0, x.coords.1, x.coords.2, x.time)),
+        ..Default::default()
+    }
+}
+
+impl ToCache for UrQMDBlock {
+    const VALUES: &'static [&'static str] = &["b", "time"];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        vec![self.header.b, self.header.time]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(urqmd_particle).collect()
+    }
+}
+
+/// Only final state of collision history is cached
+impl ToCache for UrQMDCollisionBlock {
+    const VALUES: &'static [&'static str] = &["b"];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        vec![self.header.b]
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.iter().map(urqmd_particle).collect()
+    }
+}
+
+/// Caching of cache input: values are kept, but their names are not known to the block
+impl ToCache for CacheBlock {
+    const VALUES: &'static [&'static str] = &[];
+
+    fn event_id(&self) -> Option<usize> {
+        Some(self.header.event_id)
+    }
+
+    fn values(&self) -> Vec<f64> {
+        self.header.values.clone()
+    }
+
+    fn particles(&self) -> Vec<CacheParticle> {
+        self.event.clone()
+    }
+}
+
+#[test]
+fn test_cache_roundtrip() {
+    use super::oscar::OSCEposDataFile;
+    let data = "# (1,1)+(1,1) nncm 7000.0 1\n      0       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  1 2 3 4\n    2    120  1  0.3  0.2  1.0  1.1  0.139  0 0 0 0\n      1       1       0.000  0.000  1\n    1    1220  0  0.1  0.2  3.0  3.2  0.939  0 0 0 0\n";
+    let source: Vec<_> = OSCEposDataFile::reader(std::io::BufReader::new(data.as_bytes()))
+        .collect::<Result<_, _>>().unwrap();
+    let mut buf = vec![];
+    let mut writer = CacheWriter::new(&mut buf, &AcceptedTypes::EPOS);
+    source.iter().for_each(|x| writer.write_event(x).unwrap());
+    writer.finish().unwrap();
+
+    let mut reader = CacheDataFile::reader(std::io::BufReader::new(buf.as_slice()));
+    let events: Vec<_> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+    assert_eq!(reader.take_header().unwrap().source, AcceptedTypes::EPOS);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].header.event_id, 0);
+    assert_eq!(events[0].header.nout, Some(2));
+    assert_eq!(events[0].event, source[0].particles());
+    assert!(!events[0].event[1].is_final);
+    assert_eq!(events[1].event[0].mass, Some(0.939));
+    assert_eq!(reader.integrity().ok, 2);
+
+    // cut off inside of the last event
+    let mut reader = CacheDataFile::reader(std::io::BufReader::new(&buf[..buf.len() - 10]));
+    assert_eq!(reader.by_ref().filter_map(Result::ok).count(), 2);
+    assert_eq!(reader.integrity().truncated, 1);
+}
+
+#[test]
+fn test_cache_header_values() {
+    use super::ampt::AMPTDataFile;
+    let data = "     1      1     1   6.3150    80   79    0   80    0   79   0.25\n    111   0.100   0.000   0.000   0.135   0.0   0.0   0.0   1.0\n";
+    let source: Vec<_> = AMPTDataFile::reader(std::io::BufReader::new(data.as_bytes()))
+        .collect::<Result<_, _>>().unwrap();
+    let mut buf = vec![];
+    let mut writer = CacheWriter::new(&mut buf, &AcceptedTypes::AMPT);
+    source.iter().for_each(|x| writer.write_event(x).unwrap());
+    writer.finish().unwrap();
+
+    let mut reader = CacheDataFile::reader(std::io::BufReader::new(buf.as_slice()));
+    let event = reader.next().unwrap().unwrap();
+    let header = reader.take_header().unwrap();
+    assert_eq!(header.value_names, vec!["test_id", "b", "npart", "event_plane_angle"]);
+    assert_eq!(header.value(&event.header, "npart"), Some(159.0));
+    assert_eq!(header.value(&event.header, "event_plane_angle"), Some(0.25));
+    assert_eq!(header.value(&event.header, "ncoll"), None);
+}
@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use crate::anlz::Particle;
+
+use super::decoder::{DctCoding, EposDict, EposDictParticle};
+use super::istat::FinalState;
+use super::nuclei::NuclearCode;
+use super::pdg;
+use super::selector;
+
+/// Reaction on particles missing in particle database
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq
+)]
+pub enum UnknownParticlePolicy {
+    /// stop with census of unknown particles
+    #[default]
+    Abort,
+    /// drop unknown particles from analysis
+    Skip,
+    /// analyse unknown particles as neutral ones with zero mass
+    Zero,
+}
+
+/// Warns once per particle and column that the column is not defined in particle table
+pub fn warn_undefined(x: &EposDictParticle, column: &str) {
+    static WARNED: Mutex<Option<HashSet<(String, String)>>> = Mutex::new(None);
+    let mut warned = WARNED.lock().unwrap();
+    if warned.get_or_insert_with(HashSet::new).insert((x.name.clone(), column.to_string())) {
+        println!("[WARNING]: {} of {} is not defined in particle table, zero is used: {:?}", column, x.name, x);
+    }
+}
+
+/// Particle database: properties of particles by particle code of input format.
+///
+/// Tables provide records of the particles they list. Antiparticles missing in a table
+/// are decoded with record of their particle and opposite charges, nuclei missing in a table
+/// are decoded from their PDG code (`10LZZZAAAI`), so every table decodes codes the same way.
+/// Formats carrying their own charges use them and do not ask the database
+pub trait ParticleDatabase: Debug + Send + Sync {
+    /// Record of particle `k` as it is written in the table
+    fn get(&self, k: &i32) -> Option<&EposDictParticle>;
+
+    /// True for leptons listed in the table, antileptons have negative codes
+    fn is_lepton(&self, k: &i32) -> bool;
+
+    /// Code of particle named `name` in the table, `-name` is an antiparticle
+    fn get_particle_code(&self, name: &str) -> Option<i32>;
+
+    /// Codes of particles listed in the table
+    fn table_codes(&self) -> Vec<i32>;
+
+    /// Code of particle named `name` in the table or with common name (`p`, `pbar`, `K0S`, `Lambda`)
+    fn code_by_name(&self, name: &str) -> Option<i32> {
+        self.get_particle_code(name).or_else(
+            || selector::alias_pdg(name).and_then(|pdg| self.code_of_pdg(pdg).or_else(|| NuclearCode::decode(pdg).map(|_| pdg)))
+        )
+    }
+
+    /// Reaction on particles missing in database
+    fn on_unknown(&self) -> UnknownParticlePolicy {
+        UnknownParticlePolicy::Abort
+    }
+
+    /// Particles analysed as final state
+    fn final_state(&self) -> FinalState {
+        FinalState::Final
+    }
+
+    /// True for particles of table, their antiparticles and nuclei
+    fn knows(&self, code: i32) -> bool {
+        self.lookup(code).is_some() || NuclearCode::decode(code).is_some()
+    }
+
+    /// Value `what` of particle missing in database: zero, or stop of the run for `abort` policy
+    fn unknown(&self, code: i32, what: &str) -> f64 {
+        match self.on_unknown() {
+            UnknownParticlePolicy::Abort => panic!("Unable to get {} : Undefined Particle {}!", what, code),
+            UnknownParticlePolicy::Skip | UnknownParticlePolicy::Zero => 0.0,
+        }
+    }
+
+    /// Code of particle with table code `k` in numbering `to`.
+    /// Antiparticles missing in table get negated code of particle,
+    /// nuclei keep their codes in EPOS and PDG numbering and have no codes in the others
+    fn translate(&self, k: i32, to: DctCoding) -> Option<i32> {
+        if let Some(p) = self.get(&k) {
+            p.id(to)
+        } else if let Some(p) = self.get(&-k) {
+            p.id(to).map(|x| -x)
+        } else if k.abs() / 1000000000 > 0 && matches!(to, DctCoding::EPOS | DctCoding::PDG) {
+            Some(k)
+        } else {
+            None
+        }
+    }
+
+    /// PDG code of particle with table code `k`
+    fn pdg_code(&self, k: &i32) -> Option<i32> {
+        self.translate(*k, DctCoding::PDG)
+    }
+
+    /// Table code of particle with code `id` of numbering `from`, antiparticles missing in table get negated code of particle
+    fn code_of(&self, from: DctCoding, id: i32) -> Option<i32>;
+
+    /// Table code of particle with PDG code `pdg`
+    fn code_of_pdg(&self, pdg: i32) -> Option<i32> {
+        self.code_of(DctCoding::PDG, pdg)
+    }
+
+    /// Record of particle `code` and sign of its charges
+    fn lookup(&self, code: i32) -> Option<(&EposDictParticle, f64)> {
+        match self.get(&code) {
+            Some(x) => Some((x, 1.0)),
+            None => self.get(&-code).map(|x| (x, -1.0)),
+        }
+    }
+
+    /// Electric charge
+    fn charge(&self, code: i32) -> Option<f64> {
+        match self.lookup(code) {
+            Some((x, sign)) => Some(sign * x.charge.unwrap_or_else(|| { warn_undefined(x, "charge"); 0. })),
+            None => EposDict::get_nuclei_from_code(code, None, None).map(|x| x.e_charge(&())),
+        }
+    }
+
+    /// Baryon number
+    fn baryon(&self, code: i32) -> Option<f64> {
+        match self.lookup(code) {
+            Some((x, sign)) => Some(sign * x.b_charge(&())),
+            None => EposDict::get_nuclei_from_code(code, None, None).map(|x| x.b_charge(&())),
+        }
+    }
+
+    /// Lepton number, zero for particles missing in table
+    fn lepton(&self, code: i32) -> f64 {
+        if self.is_lepton(&code) {
+            1.0
+        } else if self.is_lepton(&-code) {
+            -1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Quantum number of record, given by `value` of particle; opposite for antiparticles missing in table
+    fn quantum_number(&self, code: i32, value: fn(&EposDictParticle) -> f64) -> Option<f64> {
+        match self.lookup(code) {
+            Some((x, sign)) => Some(sign * value(x)),
+            None => EposDict::get_nuclei_from_code(code, None, None).map(|x| value(&x)),
+        }
+    }
+
+    /// Strangeness, by quark flavours of record or by count of lambdas of nucleus
+    fn strangeness(&self, code: i32) -> Option<f64> {
+        self.quantum_number(code, |x| x.strangeness(&()))
+    }
+
+    /// Charm
+    fn charm(&self, code: i32) -> Option<f64> {
+        self.quantum_number(code, |x| x.charm(&()))
+    }
+
+    /// Beauty
+    fn beauty(&self, code: i32) -> Option<f64> {
+        self.quantum_number(code, |x| x.beauty(&()))
+    }
+
+    /// Isospin projection
+    fn isospin3(&self, code: i32) -> Option<f64> {
+        self.quantum_number(code, |x| x.isospin3(&()))
+    }
+
+    /// Spin degeneracy `2J + 1` of record, or of PDG code for particles missing in table
+    fn spin_degeneracy(&self, code: i32) -> Option<i32> {
+        self.lookup(code).and_then(|(x, _)| x.degeneracy).or_else(|| self.pdg_code(&code).and_then(pdg::spin_degeneracy))
+    }
+
+    /// Mass (GeV), records without mass give zero.
+    /// Nuclei missing in table get mass of Bethe-Weizsäcker model with lambda separation energy
+    fn mass(&self, code: i32) -> Option<f64> {
+        match self.lookup(code) {
+            Some((x, _)) => Some(x.mass.unwrap_or_else(|| { warn_undefined(x, "mass"); 0. })),
+            None => EposDict::get_nuclei_from_code(code, None, None).and_then(|x| x.mass),
+        }
+    }
+
+    /// Width (GeV)
+    fn width(&self, code: i32) -> Option<f64> {
+        self.lookup(code).and_then(|(x, _)| x.width)
+    }
+
+    /// Name in table, antiparticles missing in table are named `-name`, nuclei missing in table get generated names
+    fn name(&self, code: i32) -> Option<String> {
+        match self.lookup(code) {
+            Some((x, sign)) => Some(if sign < 0.0 { format!("-{}", x.name) } else { x.name.clone() }),
+            None => EposDict::get_nuclei_from_code(code, None, None).map(|x| x.name),
+        }
+    }
+}
+
+/// Unknown particles met in input files: code, count and files
+#[derive(Debug, Default)]
+pub struct ParticleCensus {
+    unknown: Mutex<BTreeMap<i32, (usize, BTreeSet<String>)>>,
+}
+
+impl ParticleCensus {
+    /// Adds counts of unknown codes met in `source`
+    pub fn add(&self, source: &str, counts: &HashMap<i32, usize>) {
+        let mut unknown = self.unknown.lock().unwrap();
+        counts.iter().for_each(|(code, n)| {
+            let x = unknown.entry(*code).or_default();
+            x.0 += n;
+            x.1.insert(source.to_string());
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unknown.lock().unwrap().is_empty()
+    }
+
+    /// Lines of summary: `unknown particle 777777: 12 times in run1.dat, run2.dat`
+    pub fn report(&self) -> Vec<String> {
+        self.unknown.lock().unwrap().iter().map(
+            |(code, (n, files))| format!("unknown particle {}: {} times in {}", code, n, files.iter().cloned().collect::<Vec<_>>().join(", "))
+        ).collect()
+    }
+}
+
+#[test]
+fn test_epos_database() {
+    use std::io::BufReader;
+    use super::decoder::{EPOS_LEPTONS, EPOS_PARTICLES, NUCLEI_LIST};
+    let leptons = EposDict::upload(BufReader::new(EPOS_LEPTONS.as_bytes()), DctCoding::EPOS, None);
+    let mut dict = EposDict::upload(
+        BufReader::new(EPOS_PARTICLES.as_bytes()),
+        DctCoding::EPOS,
+        Some(leptons.codes().cloned().collect())
+    );
+    dict.upload_nuclei(BufReader::new(NUCLEI_LIST.as_bytes()));
+    let dict: &dyn ParticleDatabase = &dict;
+    assert_eq!((dict.charge(-1120), dict.baryon(-1120)), (Some(-1.0), Some(-1.0)));
+    assert_eq!((dict.strangeness(130), dict.strangeness(-130), dict.strangeness(2130)), (Some(1.0), Some(-1.0), Some(-1.0)));
+    assert_eq!(dict.name(-1120), Some("-Proton".to_string()));
+    assert_eq!((dict.charm(140), dict.beauty(150), dict.strangeness(-2130)), (Some(-1.0), Some(1.0), Some(1.0)));
+    assert_eq!((dict.isospin3(120), dict.isospin3(1220), dict.isospin3(-1120)), (Some(1.0), Some(-0.5), Some(-0.5)));
+    assert_eq!((dict.spin_degeneracy(1120), dict.spin_degeneracy(120)), (Some(2), Some(1)));
+    // Lambda hypertriton: p n Lambda
+    assert_eq!((dict.strangeness(1010010030), dict.isospin3(1010010030)), (Some(-1.0), Some(0.0)));
+    assert_eq!((dict.charge(-1000020040), dict.name(1010010030)), (Some(-2.0), Some("3LH".to_string())));
+    assert_eq!((dict.mass(1000010020), dict.charge(1000020040)), (Some(1.87656), Some(2.0)));
+    assert!(dict.mass(1000060120).is_some_and(|m| (m - 11.175).abs() < 0.01));
+    assert_eq!((dict.lepton(-12), dict.lepton(120)), (-1.0, 0.0));
+    // deuteron is decoded from its PDG code
+    assert_eq!((dict.charge(1000010020), dict.baryon(1000010020)), (Some(1.0), Some(2.0)));
+    assert_eq!(dict.charge(99999), None);
+    assert_eq!((dict.translate(1120, DctCoding::PDG), dict.translate(-1120, DctCoding::QGSJET)), (Some(2212), Some(-2)));
+    assert_eq!((dict.translate(1220, DctCoding::SIBYLL), dict.translate(1000010020, DctCoding::GHEISHA)), (Some(14), None));
+    assert_eq!((dict.code_of(DctCoding::QGSJET, 4), dict.code_of(DctCoding::PDG, -2212)), (Some(130), Some(-1120)));
+    assert!(dict.knows(-1120) && dict.knows(1000060120) && !dict.knows(99999));
+    let census = ParticleCensus::default();
+    census.add("a.dat", &HashMap::from([(99999, 2), (88888, 1)]));
+    census.add("b.dat", &HashMap::from([(99999, 1)]));
+    assert_eq!(census.report(), vec!["unknown particle 88888: 1 times in a.dat", "unknown particle 99999: 3 times in a.dat, b.dat"]);
+}
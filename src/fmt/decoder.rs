@@ -2,6 +2,19 @@ use std::{collections::{HashMap, HashSet}, io::BufRead, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
+use super::database::{ParticleDatabase, UnknownParticlePolicy};
+use super::istat::FinalState;
+use super::nuclei::NuclearCode;
+use super::error::{columns, ParseError, ParseToken};
+
+/// Default dictionary tables, compiled into the binary
+pub const EPOS_PARTICLES: &str = include_str!("../../dicts/EPOS.particles.txt");
+pub const EPOS_LEPTONS: &str = include_str!("../../dicts/EPOS_LEPTONS.particles.txt");
+pub const NUCLEI_LIST: &str = include_str!("../../dicts/NUCLEI_LIST.txt");
+
+/// Environment variable with directory of dictionary tables, `--dict-dir` takes precedence
+pub const DICT_DIR_ENV: &str = "HEGA_DICT_DIR";
+
 
 /// column 1 : id_EPOS     : Option<i32>
 /// column 2 : id_PDG      : Option<i32>                                                                                         
@@ -50,53 +63,98 @@ impl EposDictParticle {
         Self { id_EPOS, id_PDG, id_QGSJET, id_GHEISHA, id_SIBYLL, name, ifl1, ifl2, ifl3, counter, mass, charge, width, multiplicity, degeneracy, status, lepton_charge}
     }
 
-    fn cleared<'a, T: FromStr>(c: &'a str) -> Option<T>
-    where <T as FromStr>::Err: std::fmt::Debug {
+    /// Signed quark flavours `ifl1, ifl2, ifl3` (1 = u, 2 = d, 3 = s, 4 = c, 5 = b), antiquarks are negative.
+    /// Records without flavours get them from PDG code
+    pub fn flavours(&self) -> [i32; 3] {
+        match (self.ifl1, self.ifl2, self.ifl3) {
+            (None, None, None) => match self.id_PDG {
+                Some(code) if code.abs() < 1000000000 => {
+                    let (a, b, c) = super::pdg::flavours(code);
+                    [a, b, c]
+                },
+                _ => [0; 3],
+            },
+            (a, b, c) => [a.unwrap_or(0), b.unwrap_or(0), c.unwrap_or(0)],
+        }
+    }
+
+    /// Count of quarks `q` minus count of antiquarks `q`
+    pub fn quark_number(&self, q: i32) -> i32 {
+        self.flavours().iter().map(|&x| if x == q { 1 } else if x == -q { -1 } else { 0 }).sum()
+    }
+
+    /// Nucleus of PDG code `10LZZZAAAI`
+    pub fn nucleus(&self) -> Option<NuclearCode> {
+        self.id_PDG.and_then(NuclearCode::decode)
+    }
+
+    /// Code of particle in numbering `coding`
+    pub fn id(&self, coding: DctCoding) -> Option<i32> {
+        match coding {
+            DctCoding::EPOS => self.id_EPOS,
+            DctCoding::PDG => self.id_PDG,
+            DctCoding::QGSJET => self.id_QGSJET,
+            DctCoding::GHEISHA => self.id_GHEISHA,
+            DctCoding::SIBYLL => self.id_SIBYLL,
+        }
+    }
+
+    /// "99" marks absent value
+    fn cleared<'a, T: FromStr>(c: &'a str) -> Result<Option<T>, ParseError> {
         if c.eq("99") {
-            None
+            Ok(None)
         } else {
             // println!(">> \"{}\"", c);
-            Some(c.parse::<T>().unwrap())
+            c.token().map(Some)
         }
     }
 
-    pub fn from_str(s: String) -> Self {
-        let mut tokens = s.split_ascii_whitespace().filter(|x| {x.trim().len() > 0});
-        Self {
-            id_EPOS: Self::cleared(tokens.next().unwrap()),
-            id_PDG: Self::cleared(tokens.next().unwrap()),
-            id_QGSJET: Self::cleared(tokens.next().unwrap()),
-            id_GHEISHA: Self::cleared(tokens.next().unwrap()),
-            id_SIBYLL: Self::cleared(tokens.next().unwrap()),
-            name: tokens.next().unwrap().to_string(),
-            ifl1: Self::cleared(tokens.next().unwrap()),
-            ifl2: Self::cleared(tokens.next().unwrap()),
-            ifl3: Self::cleared(tokens.next().unwrap()),
-            counter: Self::cleared(tokens.next().unwrap()),
-            mass: Self::cleared(tokens.next().unwrap()),
-            charge: Self::cleared(tokens.next().unwrap()),
-            width: Self::cleared(tokens.next().unwrap()),
-            multiplicity: Self::cleared(tokens.next().unwrap()),
-            degeneracy: Self::cleared(tokens.next().unwrap()),
-            status: tokens.next().unwrap().to_string(),
+    pub fn from_str(s: String) -> Result<Self, ParseError> {
+        let t = columns::<16>(&s)?;
+        Ok(Self {
+            id_EPOS: Self::cleared(t[0])?,
+            id_PDG: Self::cleared(t[1])?,
+            id_QGSJET: Self::cleared(t[2])?,
+            id_GHEISHA: Self::cleared(t[3])?,
+            id_SIBYLL: Self::cleared(t[4])?,
+            name: t[5].to_string(),
+            ifl1: Self::cleared(t[6])?,
+            ifl2: Self::cleared(t[7])?,
+            ifl3: Self::cleared(t[8])?,
+            counter: Self::cleared(t[9])?,
+            mass: Self::cleared(t[10])?,
+            charge: Self::cleared(t[11])?,
+            width: Self::cleared(t[12])?,
+            multiplicity: Self::cleared(t[13])?,
+            degeneracy: Self::cleared(t[14])?,
+            status: t[15].to_string(),
             lepton_charge: 0.0
-        }
+        })
     }
 
 }
 
 
-#[derive(Clone)]
+/// Particle numbering scheme, i.e. id column of dictionary used as particle code
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq
+)]
 pub enum DctCoding {
     EPOS,
     PDG,
-    
+    QGSJET,
+    GHEISHA,
+    SIBYLL,
 }
 
 #[derive(Debug)]
 pub struct EposDict {
     dct: HashMap<i32, EposDictParticle>,
-    leptons: HashSet<i32>
+    leptons: HashSet<i32>,
+    /// codes of particle names, the first listed particle for repeated names
+    names: HashMap<String, i32>,
+    on_unknown: UnknownParticlePolicy,
+    final_state: FinalState,
 }
 
 impl EposDict {
@@ -105,6 +163,7 @@ impl EposDict {
         if self.dct.contains_key(&code) {
             println!("TRYING TO ADD EXSITING PARTICLE TO DICTIONARY !");
         }
+        self.names.entry(particle.name.clone()).or_insert(code);
         self.dct.insert(code, particle);
         println!(" Particle registered in dict: [{}] {:?}", code, self.dct.get(&code).unwrap());
         if is_lepto {
@@ -112,45 +171,40 @@ impl EposDict {
         }
     }
 
-    pub fn get_nuclei_from_code(code: i32, mass: f64, name: Option<String>) -> Option<EposDictParticle> {
-        if code.abs() / 1000000000 > 0 {
-            let name = name.unwrap_or(format!("New Nuclei {}", code));
-            let charge = (code.signum() * (code % 10000000 / 10000) ) as f64;
-            Some(EposDictParticle {
-                id_EPOS: Some(code),
-                id_PDG:  Some(code),
-                id_QGSJET: None,
-                id_GHEISHA: None,
-                id_SIBYLL: None,
-                name: name,
-                ifl1: None,
-                ifl2: None,
-                ifl3: None,
-                counter: None,
-                mass: Some(mass),
-                charge: Some(charge),
-                width: None,
-                multiplicity: None,
-                degeneracy: None,
-                status: "Nuclei".to_string(),
-                lepton_charge: 0.
-            })
-        } else {
-            None
-        }
+    /// Nucleus of PDG code `10LZZZAAAI`, generated name and mass of mass model are used if not given
+    pub fn get_nuclei_from_code(code: i32, mass: Option<f64>, name: Option<String>) -> Option<EposDictParticle> {
+        let nucleus = NuclearCode::decode(code)?;
+        Some(EposDictParticle {
+            id_EPOS: Some(code),
+            id_PDG:  Some(code),
+            id_QGSJET: None,
+            id_GHEISHA: None,
+            id_SIBYLL: None,
+            name: name.unwrap_or_else(|| nucleus.name()),
+            ifl1: None,
+            ifl2: None,
+            ifl3: None,
+            counter: None,
+            mass: Some(mass.unwrap_or_else(|| nucleus.mass())),
+            charge: Some(nucleus.charge()),
+            width: None,
+            multiplicity: None,
+            degeneracy: None,
+            status: "Nuclei".to_string(),
+            lepton_charge: 0.
+        })
     }
 
     pub fn upload_nuclei<T: Sized + std::io::Read>(&mut self, data: std::io::BufReader<T>) {
-        data.lines().for_each(
-            |s| {
+        data.lines().enumerate().for_each(
+            |(i, s)| {
                 if let Ok(s) = s {
                     let s = s.trim();
                     if s.starts_with("!") { /* skip */}
                     else {
-                        let s: Vec<String> = s.trim().split_ascii_whitespace().map(|x| {x.to_string()}).collect();
-                        let code: i32 = s[0].parse().unwrap();
-                        let name = s[1].clone();
-                        let mass = s[2].parse().unwrap();
+                        let (code, name, mass) = columns::<3>(s)
+                            .and_then(|s| Ok((s[0].token::<i32>()?, s[1].to_string(), s[2].token::<f64>()?)))
+                            .unwrap_or_else(|e| panic!("ERROR READING NUCLEI LIST: {}", e.at_line(i)));
                         // let charge = (code.signum() * (code % 10000000 / 10000) ) as f64;
                         /*let v = EposDictParticle {
                             id_EPOS: Some(code),
@@ -171,7 +225,8 @@ impl EposDict {
                             status: "Nuclei".to_string(),
                             lepton_charge: 0.
                         };*/
-                        let v = Self::get_nuclei_from_code(code, mass, Some(name)).unwrap();
+                        let v = Self::get_nuclei_from_code(code, Some(mass), Some(name))
+                            .unwrap_or_else(|| panic!("ERROR READING NUCLEI LIST: line {}: bad nucleus code {}", i + 1, code));
                         println!("> Added Nuclei: {:?}", v);
                         self.insert_code(code, v, false);
                     }
@@ -186,23 +241,23 @@ impl EposDict {
     pub fn upload<T: Sized + std::io::Read>(data: std::io::BufReader<T>, as_code: DctCoding, leptons: Option<HashSet<i32>>) -> Self {
 
         let mut mp = HashMap::new();
+        let mut names = HashMap::new();
 
-        data.lines().for_each(
-            |s| {
+        data.lines().enumerate().for_each(
+            |(i, s)| {
                 if let Ok(s) = s {
                     let s = s.trim();
                     if s.starts_with("!") || s.len() < 10 { /* skip */}
                     else {
-                        let mut v = EposDictParticle::from_str(s.to_string());
-                        let code = match as_code {
-                            DctCoding::EPOS => v.id_EPOS.unwrap_or(99),
-                            DctCoding::PDG => v.id_PDG.unwrap_or(99),
-                        };
+                        let mut v = EposDictParticle::from_str(s.to_string())
+                            .unwrap_or_else(|e| panic!("ERROR READING DICT WITH EPOS INTERPRETER: {}", e.at_line(i)));
+                        let code = v.id(as_code).unwrap_or(99);
                         if let Some(lp) = &leptons {
                             if lp.contains(&code) {
                                 v.lepton_charge = if (code > 0) {1.0} else {-1.0};
                             }
                         }
+                        names.entry(v.name.clone()).or_insert(code);
                         mp.insert(code, v);
                     }
                 } else {
@@ -210,32 +265,73 @@ impl EposDict {
                 }
             }
         );
-        Self { dct: mp, leptons: leptons.unwrap_or(HashSet::new())}
+        Self { dct: mp, leptons: leptons.unwrap_or(HashSet::new()), names, on_unknown: UnknownParticlePolicy::default(), final_state: FinalState::default() }
     }
 
-    pub fn get(&self, k: &i32) -> Option<&EposDictParticle> {
-        self.dct.get(k)
+    /// Dictionary of `(code, particle)` pairs
+    pub fn from_particles(particles: impl IntoIterator<Item = (i32, EposDictParticle)>, leptons: HashSet<i32>) -> Self {
+        let particles: Vec<_> = particles.into_iter().collect();
+        let mut names = HashMap::new();
+        particles.iter().for_each(|(code, x)| { names.entry(x.name.clone()).or_insert(*code); });
+        Self { dct: particles.into_iter().collect(), leptons, names, on_unknown: UnknownParticlePolicy::default(), final_state: FinalState::default() }
     }
 
-    pub fn is_lepton(&self, k: &i32) -> bool {
-        self.leptons.contains(k)
+    /// Adds particles of `other`, replacing particles with the same codes
+    pub fn extend(&mut self, other: EposDict) {
+        self.names.extend(other.names);
+        self.dct.extend(other.dct);
+        self.leptons.extend(other.leptons);
+    }
+
+    /// Sets reaction on particles missing in dictionary
+    pub fn set_on_unknown(&mut self, policy: UnknownParticlePolicy) {
+        self.on_unknown = policy;
+    }
+
+    /// Sets particles analysed as final state
+    pub fn set_final_state(&mut self, final_state: FinalState) {
+        self.final_state = final_state;
     }
 
     pub fn codes(&self) -> std::collections::hash_map::Keys<'_, i32, EposDictParticle> {
         self.dct.keys()
     }
+}
 
-    pub fn get_particle_code(&self, name: &str) -> Option<i32> {
-        let antip = name.trim_start_matches("-");
-        let isantip = name.starts_with('-');
-        for (index, part) in self.dct.iter() {
-            if part.name.eq(name) {
-                return Some(*index);
-            } else if isantip && part.name.eq( &antip ) {
-                return Some(- *index);
-            }
-        }
-        None
+impl ParticleDatabase for EposDict {
+    fn get(&self, k: &i32) -> Option<&EposDictParticle> {
+        self.dct.get(k)
+    }
+
+    fn is_lepton(&self, k: &i32) -> bool {
+        self.leptons.contains(k)
+    }
+
+    fn on_unknown(&self) -> UnknownParticlePolicy {
+        self.on_unknown
+    }
+
+    fn final_state(&self) -> FinalState {
+        self.final_state
+    }
+
+    fn get_particle_code(&self, name: &str) -> Option<i32> {
+        // names of replaced particles are not valid
+        let find = |name: &str| self.names.get(name).filter(|k| self.dct.get(k).is_some_and(|x| x.name == name)).copied();
+        find(name).or_else(|| name.strip_prefix('-').and_then(find).map(|k| -k))
     }
 
-}
\ No newline at end of file
+    fn table_codes(&self) -> Vec<i32> {
+        self.dct.keys().copied().collect()
+    }
+
+    fn code_of(&self, from: DctCoding, id: i32) -> Option<i32> {
+        let find = |id: i32| {
+            match self.dct.get(&id) {
+                Some(p) if p.id(from) == Some(id) => Some(id),
+                _ => self.dct.iter().filter(|(_, p)| p.id(from) == Some(id)).map(|(k, _)| *k).min(),
+            }
+        };
+        find(id).or_else(|| find(-id).map(|x| -x))
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+
+// Input format detection by the first lines of a file
+
+use crate::cli::AcceptedTypes;
+use super::cache::CACHE_MAGIC;
+
+/// Count of lines inspected while sniffing
+const SNIFF_LINES: usize = 32;
+
+/// Magic of OSCAR2013 binary files
+const OSCAR2013_BIN_MAGIC: &[u8; 4] = b"SMSH";
+
+/// Format of text file by its first lines.
+/// Returns None, if format is not recognized
+pub fn sniff_lines(lines: &[String]) -> Option<AcceptedTypes> {
+    for line in lines.iter() {
+        let tr = line.trim();
+        if tr.starts_with("#!OSCAR2013") {
+            return Some(AcceptedTypes::Oscar2013);
+        }
+        if tr.starts_with("HepMC::Version 2") || tr.starts_with("HepMC::IO_GenEvent-START_EVENT_LISTING") {
+            return Some(AcceptedTypes::HepMC2);
+        }
+        if tr.starts_with("HepMC::Version 3") || tr.starts_with("HepMC::Asciiv3-START_EVENT_LISTING") {
+            return Some(AcceptedTypes::HepMC);
+        }
+        if tr.contains("<LesHouchesEvents") {
+            return Some(AcceptedTypes::LHE);
+        }
+        if tr.contains("Results of QGSM") {
+            return Some(AcceptedTypes::QGSM);
+        }
+        if tr.starts_with("UQMD") {
+            // UQMD   version:  30400   1000  30400  output_file  14
+            let toks: Vec<_> = tr.split_ascii_whitespace().collect();
+            let file = toks.iter().position(|x| *x == "output_file").and_then(|i| toks.get(i + 1));
+            return match file {
+                Some(&"13") => Some(AcceptedTypes::UrQMDF13),
+                Some(&"15") => Some(AcceptedTypes::UrQMDF15),
+                _ => Some(AcceptedTypes::UrQMDF14),
+            };
+        }
+        if tr.contains("OSC1997A") {
+            return Some(AcceptedTypes::UrQmdF19);
+        }
+        if tr.starts_with('#') && (tr.contains("OSC1999A") || tr.contains("nncm")) {
+            return Some(AcceptedTypes::EPOS);
+        }
+    }
+    // headerless formats, distinguished by columns count
+    let tokens: Vec<usize> = lines.iter()
+        .map(|x| x.split_ascii_whitespace().count())
+        .filter(|x| *x > 0)
+        .collect();
+    match tokens.as_slice() {
+        // `N ISUB IRUN BIMP IBweight`, second header line, particles
+        [5, _, 8, ..] => Some(AcceptedTypes::PHSD),
+        [5, _, n, ..] if *n >= 9 => Some(AcceptedTypes::PHQMD),
+        [n, 9, ..] if *n >= 10 => Some(AcceptedTypes::AMPT),
+        _ => None,
+    }
+}
+
+/// Format of file at `path`
+pub fn detect_type(path: &str) -> Result<AcceptedTypes, io::Error> {
+    let mut data = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    let n = data.fill_buf()?.read(&mut magic)?;
+    if n == 4 && &magic == OSCAR2013_BIN_MAGIC {
+        return Ok(AcceptedTypes::Oscar2013Bin);
+    }
+    if n == 4 && &magic == CACHE_MAGIC {
+        return Ok(AcceptedTypes::Cache);
+    }
+    let mut lines = vec![];
+    let mut buf = vec![];
+    while lines.len() < SNIFF_LINES {
+        buf.clear();
+        if data.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        lines.push(String::from_utf8_lossy(&buf).into_owned());
+    }
+    sniff_lines(&lines).ok_or_else(
+        || io::Error::new(io::ErrorKind::InvalidData, format!("Unable to detect format of file {}, set file type explicitly", path))
+    )
+}
+
+/// Format of all inputs.
+/// `ftype` overrides detection, files of different formats are an error
+pub fn resolve_type(ftype: Option<&AcceptedTypes>, filenames: &[String]) -> Result<AcceptedTypes, io::Error> {
+    if let Some(t) = ftype {
+        return Ok(t.clone());
+    }
+    let mut resolved: Option<(AcceptedTypes, &String)> = None;
+    for path in filenames.iter() {
+        let t = detect_type(path)?;
+        match &resolved {
+            Some((first, first_path)) if *first != t => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Mixed input formats: {} is {:?}, {} is {:?}", first_path, first, path, t)
+                ));
+            },
+            Some(_) => {},
+            None => resolved = Some((t, path)),
+        }
+    }
+    resolved.map(|x| x.0).ok_or_else(
+        || io::Error::new(io::ErrorKind::InvalidInput, "No input files")
+    )
+}
+
+#[test]
+fn test_sniff_formats() {
+    let sniff = |s: &str| sniff_lines(&s.lines().map(|x| x.to_owned()).collect::<Vec<_>>());
+    assert_eq!(sniff("# OSC1999A\n# final_id_p_x\n# EPOS 3.4  (197,79)+(197,79)  eqsp  0.2000E+03  nncm\n"), Some(AcceptedTypes::EPOS));
+    assert_eq!(sniff("OSC1997A\nfinal_id_p_x\n UrQMD 3.4 (197,79)+(197,79) eqsp 200.0\n"), Some(AcceptedTypes::UrQmdF19));
+    assert_eq!(sniff("\nHepMC::Version 3.02.05\nHepMC::Asciiv3-START_EVENT_LISTING\n"), Some(AcceptedTypes::HepMC));
+    assert_eq!(sniff("\nHepMC::Version 2.06.09\nHepMC::IO_GenEvent-START_EVENT_LISTING\n"), Some(AcceptedTypes::HepMC2));
+    assert_eq!(sniff("<LesHouchesEvents version=\"3.0\">\n"), Some(AcceptedTypes::LHE));
+    assert_eq!(sniff("#!OSCAR2013 particle_lists t x y z mass p0 px py pz pdg ID charge\n"), Some(AcceptedTypes::Oscar2013));
+    assert_eq!(sniff("UQMD   version:       30400   1000  30400  output_file  15\n"), Some(AcceptedTypes::UrQMDF15));
+    assert_eq!(sniff("2 1 1 5.5 1.0\n310 0.1 0.05 0.2 0.01 0.0 0.0 0.0 0.0\n2212 1 0.1 0.2 1.0 1.39 0 1\n"), Some(AcceptedTypes::PHSD));
+    assert_eq!(sniff("2 1 1 5.5 1.0\n310 0.1\n2212 1 0.1 0.2 1.0 1.39 0 1 1\n"), Some(AcceptedTypes::PHQMD));
+    assert_eq!(sniff("1 1 2 6.3 80 79 0 80 0 79\n2212 0.1 0.2 5.0 0.938 1.0 2.0 3.0 10.0\n"), Some(AcceptedTypes::AMPT));
+    assert_eq!(sniff("garbage\n"), None);
+}
@@ -0,0 +1,155 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+// Typed errors of input readers
+
+/// What is wrong with the input
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// token is not a valid value of expected type
+    BadToken { token: String, expected: &'static str },
+    /// line has less columns than format requires
+    MissingColumns { expected: usize, found: usize },
+    /// any other violation of format structure
+    Format(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadToken { token, expected } => write!(f, "bad token \"{}\", expected {}", token, expected),
+            Self::MissingColumns { expected, found } => write!(f, "too few columns: expected {}, found {}", expected, found),
+            Self::Format(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Parse error located in the input.
+/// Location fields are filled as the error goes up from token to reader
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// input path
+    pub file: Option<String>,
+    /// zero-based line number in the input
+    pub line: Option<usize>,
+    /// zero-based line number inside of event lines, before `line` is known
+    pub event_line: Option<usize>,
+    /// zero-based event index in the input
+    pub event: Option<usize>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind) -> Self {
+        Self { kind, file: None, line: None, event_line: None, event: None }
+    }
+
+    pub fn format(msg: impl Into<String>) -> Self {
+        Self::new(ParseErrorKind::Format(msg.into()))
+    }
+
+    /// Keeps typed errors, other errors become `ParseErrorKind::Format`
+    pub fn from_boxed(e: Box<dyn Error>) -> Self {
+        match e.downcast::<ParseError>() {
+            Ok(pe) => *pe,
+            Err(e) => Self::format(e.to_string()),
+        }
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line.get_or_insert(line);
+        self
+    }
+
+    pub fn at_event_line(mut self, line: usize) -> Self {
+        self.event_line.get_or_insert(line);
+        self
+    }
+
+    pub fn at_event(mut self, event: usize) -> Self {
+        self.event.get_or_insert(event);
+        self
+    }
+
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file.get_or_insert_with(|| file.to_owned());
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        match (self.line, self.event_line) {
+            (Some(l), _) => write!(f, "{}: ", l + 1)?,
+            (None, Some(l)) => write!(f, " line {} of event: ", l + 1)?,
+            _ if self.file.is_some() => write!(f, " ")?,
+            _ => {},
+        }
+        if let Some(ev) = self.event {
+            write!(f, "event {}: ", ev)?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Typed parsing of a single token
+pub trait ParseToken {
+    fn token<T: FromStr>(&self) -> Result<T, ParseError>;
+}
+
+impl ParseToken for str {
+    fn token<T: FromStr>(&self) -> Result<T, ParseError> {
+        self.parse().map_err(
+            |_| ParseError::new(ParseErrorKind::BadToken {
+                token: self.to_owned(),
+                expected: std::any::type_name::<T>().rsplit("::").next().unwrap_or_default(),
+            })
+        )
+    }
+}
+
+/// First `N` whitespace separated columns of line
+pub fn columns<const N: usize>(line: &str) -> Result<[&str; N], ParseError> {
+    let mut args = [""; N];
+    let mut iter = line.split_ascii_whitespace();
+    for (i, a) in args.iter_mut().enumerate() {
+        *a = iter.next().ok_or(ParseError::new(ParseErrorKind::MissingColumns { expected: N, found: i }))?;
+    }
+    Ok(args)
+}
+
+/// Reaction of readers on corrupted input
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq
+)]
+pub enum ErrorPolicy {
+    /// stop reading on the first error
+    #[default]
+    Fail,
+    /// drop event containing the error
+    SkipEvent,
+    /// drop the bad line only, events with bad headers are dropped
+    SkipLine,
+}
+
+/// Count of input parts dropped by `ErrorPolicy`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SkipReport {
+    pub events: usize,
+    pub lines: usize,
+}
+
+#[test]
+fn test_parse_error_context() {
+    let e = "1.5x".token::<f64>().unwrap_err();
+    assert_eq!(e.kind, ParseErrorKind::BadToken { token: "1.5x".to_owned(), expected: "f64" });
+    let e = e.at_event_line(3).at_line(41).at_event(2).in_file("a.dat");
+    assert_eq!(e.to_string(), "a.dat:42: event 2: bad token \"1.5x\", expected f64");
+    let e = columns::<3>("1 2").unwrap_err();
+    assert_eq!(e.kind, ParseErrorKind::MissingColumns { expected: 3, found: 2 });
+    assert_eq!(columns::<2>("1 2 3").unwrap(), ["1", "2"]);
+}
@@ -1,28 +1,715 @@
-use std::{fs::File, io::BufReader};
+use std::{collections::{HashSet, VecDeque}, error::Error, fs::File, io::{BufRead, BufReader, Read}};
 
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use super::cache::{CacheParticle, ToCache};
+use super::{database::ParticleDatabase, decoder::{EposDict, EposDictParticle}};
+use crate::anlz::Particle;
+use super::error::{ErrorPolicy, ParseError, SkipReport};
 
 
 pub trait DataBlock<'a, Header>: TryFrom<(Header, &'a Vec<String>)> {
     fn get_header(&self) -> &Header;
 }
 
-pub trait GenericDataContainer<'a, 'b>: Sized {
+/// Particle count of event, checked against its header
+pub trait ParticleCount {
+    /// Count of particles declared by event header, None if format does not declare it
+    fn declared_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Count of particles read
+    fn particle_count(&self) -> usize;
+}
+
+/// Integrity of read event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+    Ok,
+    /// particle count differs from the declared one
+    Short,
+    /// last event of input has less particles than declared: input is cut off
+    Truncated,
+}
+
+impl EventStatus {
+    pub fn of<B: ParticleCount>(block: &B, last: bool) -> Self {
+        match block.declared_count() {
+            Some(n) if last && block.particle_count() < n => Self::Truncated,
+            Some(n) if block.particle_count() != n => Self::Short,
+            _ => Self::Ok,
+        }
+    }
+}
+
+/// Count of events of input by `EventStatus`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IntegrityReport {
+    pub ok: usize,
+    pub short: usize,
+    pub truncated: usize,
+    /// bad events are not passed to analysis
+    pub excluded: bool,
+}
+
+impl IntegrityReport {
+    pub fn bad(&self) -> usize {
+        self.short + self.truncated
+    }
+}
+
+/// Checks events of one input, drops bad ones if they are excluded
+#[derive(Debug, Default)]
+pub struct IntegrityCheck {
+    pub report: IntegrityReport,
+}
+
+impl IntegrityCheck {
+    /// Records status of `event`-th event of input, returns block if it is passed to analysis
+    pub fn accept<B: ParticleCount>(&mut self, block: B, last: bool, event: usize, source: Option<&str>) -> Option<B> {
+        let status = EventStatus::of(&block, last);
+        let msg = match status {
+            EventStatus::Ok => {
+                self.report.ok += 1;
+                return Some(block);
+            },
+            EventStatus::Short => {
+                self.report.short += 1;
+                "particle count differs from header"
+            },
+            EventStatus::Truncated => {
+                self.report.truncated += 1;
+                "input is cut off inside of event"
+            },
+        };
+        let mut e = ParseError::format(
+            format!("{}: {} particles, {} declared", msg, block.particle_count(), block.declared_count().unwrap_or(0))
+        ).at_event(event);
+        if let Some(src) = source {
+            e = e.in_file(src);
+        }
+        if self.report.excluded {
+            eprintln!("[WARNING]: {}, event excluded", e);
+            None
+        } else {
+            eprintln!("[WARNING]: {}", e);
+            Some(block)
+        }
+    }
+}
+
+/// Line-driven state machine of one text format.
+///
+/// Parser gets lines one by one and returns a block as soon as it is complete
+/// (usually when the header line of the next event is met).
+/// `finish` is called at the end of input to flush the last collected event.
+pub trait BlockParser: Default {
+    type Header: Send;
+    type Block: ParticleCount + Send;
+
+    /// `idx` - zero-based line number in the input
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn std::error::Error>>;
+
+    /// True if line `idx` (following line `prev`) starts an event, and a new parser
+    /// started at it reads the rest of input the same way as the parser which read the whole input
+    /// (except of file header). Input is split at such lines to be parsed in parallel
+    fn is_event_start(idx: usize, prev: &str, line: &str) -> bool;
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn std::error::Error>>;
+
+    fn take_header(&mut self) -> Option<Self::Header>;
+
+    /// Policy for bad lines inside of events.
+    /// Parsers not supporting it drop the whole event
+    fn set_policy(&mut self, _policy: ErrorPolicy) {}
+
+    /// Count of lines dropped by `ErrorPolicy::SkipLine`
+    fn skipped_lines(&self) -> usize {
+        0
+    }
+}
+
+/// Raw text layout of events of a line format.
+/// Used to write selected events and particles back in the input format
+pub trait EventLayout {
+    /// Positions of particle lines in `lines` of one event (the first line starts the event),
+    /// in order of particles of the parsed block.
+    /// None if particles can not be dropped from events of the format
+    fn particle_lines(_lines: &[String]) -> Option<Vec<usize>> {
+        None
+    }
+
+    /// Writes count `n` of kept particles into the event header lines
+    fn set_particle_count(_lines: &mut [String], _n: usize) {}
+
+    /// True for a line closing the whole input, it is written after the last kept event
+    fn is_footer(_line: &str) -> bool {
+        false
+    }
+}
+
+/// Replaces whitespace separated token `k` of `line`, keeping the columns aligned when possible
+pub fn replace_token(line: &str, k: usize, value: &str) -> String {
+    let mut start = None;
+    let mut n = 0;
+    let mut prev_space = true;
+    for (i, c) in line.char_indices() {
+        let space = c.is_ascii_whitespace();
+        if prev_space && !space {
+            if n == k {
+                start = Some(i);
+                break;
+            }
+            n += 1;
+        }
+        prev_space = space;
+    }
+    let Some(a) = start else {
+        return line.to_owned();
+    };
+    let b = line[a..].find(|c: char| c.is_ascii_whitespace()).map(|x| a + x).unwrap_or(line.len());
+    // value is right-aligned in the token and spaces before it, one space is kept after the previous token
+    let field = if k > 0 { line[..a].trim_end().len() + 1 } else { 0 };
+    format!("{}{:>w$}{}", &line[..field], value, &line[b..], w = b - field)
+}
+
+/// Parses lines of an event one by one.
+/// Errors are located by index of the offending line in `lines`
+pub fn parse_lines<P>(lines: &[String], mut f: impl FnMut(&str) -> Result<P, Box<dyn Error>>) -> Result<Vec<P>, Box<dyn Error>> {
+    lines.iter().enumerate().map(
+        |(i, line)| f(line).map_err(|e| ParseError::from_boxed(e).at_event_line(i).into())
+    ).collect()
+}
+
+/// Header and lines of the event being collected by a line parser.
+///
+/// Each started event gives exactly one block or error on flush;
+/// broken header line is reported when its event is flushed.
+#[derive(Debug)]
+pub struct EventBuffer<H> {
+    header: Option<(usize, Result<H, ParseError>)>,
+    lines: Vec<String>,
+    idx: Vec<usize>,
+    pub policy: ErrorPolicy,
+    pub skipped_lines: usize,
+}
+
+impl<H> Default for EventBuffer<H> {
+    fn default() -> Self {
+        Self { header: None, lines: vec![], idx: vec![], policy: Default::default(), skipped_lines: 0 }
+    }
+}
+
+impl<H: Clone> EventBuffer<H> {
+    /// Starts event with header at line `idx`, returns the previous event
+    pub fn start<B>(&mut self, idx: usize, header: Result<H, Box<dyn Error>>) -> Result<Option<B>, Box<dyn Error>>
+    where B: for<'a> TryFrom<(H, &'a Vec<String>), Error = Box<dyn Error>> {
+        let block = self.flush();
+        self.header = Some((idx, header.map_err(ParseError::from_boxed)));
+        block
+    }
+
+    /// Adds line of the current event, lines out of events are ignored
+    pub fn push(&mut self, idx: usize, line: String) {
+        if self.header.is_some() {
+            self.lines.push(line);
+            self.idx.push(idx);
+        }
+    }
+
+    /// Applies header record at line `idx` to the current event.
+    /// Failed record breaks the event, it is reported on flush
+    pub fn update_header(&mut self, idx: usize, f: impl FnOnce(&mut H) -> Result<(), Box<dyn Error>>) {
+        if let Some((_, h)) = &mut self.header
+            && let Some(e) = h.as_mut().ok().and_then(|hd| f(hd).err()) {
+            *h = Err(ParseError::from_boxed(e).at_line(idx));
+        }
+    }
+
+    /// Header of the current event, if it is parsed
+    pub fn header(&self) -> Option<&H> {
+        self.header.as_ref().and_then(|(_, h)| h.as_ref().ok())
+    }
+
+    /// Count of lines in the current event
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.header.is_some()
+    }
+
+    /// Builds block of the current event
+    pub fn flush<B>(&mut self) -> Result<Option<B>, Box<dyn Error>>
+    where B: for<'a> TryFrom<(H, &'a Vec<String>), Error = Box<dyn Error>> {
+        let Some((hidx, header)) = self.header.take() else {
+            return Ok(None);
+        };
+        let mut lines = std::mem::take(&mut self.lines);
+        let mut idx = std::mem::take(&mut self.idx);
+        let mut header = Some(header.map_err(|e| e.at_line(hidx))?);
+        loop {
+            let hd = if self.policy == ErrorPolicy::SkipLine { header.clone() } else { header.take() };
+            match B::try_from((hd.expect("event header"), &lines)) {
+                Ok(block) => return Ok(Some(block)),
+                Err(e) => {
+                    let e = ParseError::from_boxed(e);
+                    match e.event_line.filter(|i| *i < idx.len()) {
+                        Some(i) if self.policy == ErrorPolicy::SkipLine => {
+                            eprintln!("[WARNING]: {}, line skipped", e.at_line(idx[i]));
+                            lines.remove(i);
+                            idx.remove(i);
+                            self.skipped_lines += 1;
+                        },
+                        Some(i) => return Err(e.at_line(idx[i]).into()),
+                        None => return Err(e.at_line(hidx).into()),
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Streaming source of events (blocks) of one input.
+///
+/// Iterates over events without keeping them in memory;
+/// header of the input is available after first event is read.
+pub trait EventReader: Iterator<Item = Result<Self::Block, std::io::Error>> {
+    type Header;
+    type Block;
+
+    fn take_header(&mut self) -> Option<Self::Header>;
+
+    /// Input name used in error messages
+    fn set_source(&mut self, _source: &str) {}
+
+    fn set_policy(&mut self, _policy: ErrorPolicy) {}
+
+    /// Input parts dropped by error policy
+    fn skipped(&self) -> SkipReport {
+        SkipReport::default()
+    }
+
+    /// Drop events which particle count differs from their header
+    fn set_exclude_bad(&mut self, _exclude: bool) {}
+
+    /// Parse events in parallel; events are read in the same order
+    fn set_parallel(&mut self, _parallel: bool) {}
+
+    fn integrity(&self) -> IntegrityReport {
+        IntegrityReport::default()
+    }
+}
+
+/// Bytes read at once by parallel `LineReader`
+const PARALLEL_BATCH_BYTES: usize = 64 << 20;
+
+/// Result of parsing lines, in order of input
+enum Outcome<B> {
+    /// complete event, `true` if it is finished by the end of input
+    Block(B, bool),
+    /// parse error at line
+    Error(ParseError, usize),
+    Io(std::io::Error),
+}
+
+/// `EventReader` for text formats described by `BlockParser`.
+///
+/// In parallel mode input is read by large batches; every batch is split
+/// at event starts (`BlockParser::is_event_start`) into ranges which are
+/// parsed by separate parsers in parallel. Error policy and integrity
+/// checks are applied to events in input order, so results are identical to the sequential mode
+pub struct LineReader<T: std::io::Read, P: BlockParser> {
+    data: BufReader<T>,
+    idx: usize,
+    parser: P,
+    done: bool,
+    source: Option<String>,
+    policy: ErrorPolicy,
+    events: usize,
+    skipped_events: usize,
+    integrity: IntegrityCheck,
+    parallel: bool,
+    batch_bytes: usize,
+    /// unparsed tail of the last batch, starts at event start
+    carry: Vec<u8>,
+    /// whole input is parsed in parallel mode
+    input_done: bool,
+    outcomes: VecDeque<Outcome<P::Block>>,
+    /// header found by parallel parsers
+    header: Option<P::Header>,
+    parallel_skipped_lines: usize,
+}
+
+impl<T: std::io::Read, P: BlockParser> LineReader<T, P> {
+    pub fn new(data: BufReader<T>) -> Self {
+        Self {
+            data,
+            idx: 0,
+            parser: P::default(),
+            done: false,
+            source: None,
+            policy: Default::default(),
+            events: 0,
+            skipped_events: 0,
+            integrity: Default::default(),
+            parallel: false,
+            batch_bytes: PARALLEL_BATCH_BYTES,
+            carry: vec![],
+            input_done: false,
+            outcomes: VecDeque::new(),
+            header: None,
+            parallel_skipped_lines: 0,
+        }
+    }
+
+    /// Checks complete event, returns it if it is passed to analysis
+    fn accept(&mut self, block: P::Block, last: bool) -> Option<P::Block> {
+        self.events += 1;
+        self.integrity.accept(block, last, self.events + self.skipped_events - 1, self.source.as_deref())
+    }
+
+    /// Error of event being read, located in the input
+    fn locate(&self, e: ParseError, idx: usize) -> ParseError {
+        let e = e.at_line(idx).at_event(self.events + self.skipped_events);
+        match &self.source {
+            Some(src) => e.in_file(src),
+            None => e,
+        }
+    }
+
+    /// Applies error policy; returns error if reading has to stop
+    fn on_error(&mut self, e: ParseError, idx: usize) -> Option<std::io::Error> {
+        let e = self.locate(e, idx);
+        if self.policy == ErrorPolicy::Fail {
+            self.done = true;
+            self.outcomes.clear();
+            Some(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        } else {
+            eprintln!("[WARNING]: {}, event skipped", e);
+            self.skipped_events += 1;
+            None
+        }
+    }
+
+    /// Same as `BufRead::lines`
+    fn read_line(&mut self) -> Option<std::io::Result<String>> {
+        let mut line = String::new();
+        match self.data.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            },
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// Reads next batch of input and parses its complete events in parallel
+    fn fill(&mut self) {
+        let mut buf = std::mem::take(&mut self.carry);
+        let mut stop = None;
+        let eof = match (&mut self.data).take(self.batch_bytes as u64).read_to_end(&mut buf) {
+            Ok(n) => n == 0,
+            Err(e) => {
+                stop = Some(e);
+                true
+            },
+        };
+        let line_end = |buf: &[u8]| buf.iter().rposition(|c| *c == b'\n').map(|x| x + 1).unwrap_or(0);
+        let mut complete = if eof && stop.is_none() { buf.len() } else { line_end(&buf) };
+        if let Err(e) = std::str::from_utf8(&buf[..complete]) {
+            complete = line_end(&buf[..e.valid_up_to()]);
+            stop = Some(std::io::Error::new(std::io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"));
+        }
+        let text = std::str::from_utf8(&buf[..complete]).expect("validated utf-8");
+        let lines: Vec<&str> = text.lines().collect();
+        let base = self.idx;
+        let is_start = |i: usize| i > 0 && P::is_event_start(base + i, lines[i - 1], lines[i]);
+        // the last event of batch may be incomplete, it is parsed with the next batch
+        let end = if eof || stop.is_some() {
+            lines.len()
+        } else {
+            (1..lines.len()).rev().find(|i| is_start(*i)).unwrap_or(0)
+        };
+        if end == 0 && !eof && stop.is_none() {
+            self.carry = buf;
+            return;
+        }
+
+        let target = (end / (rayon::current_num_threads() * 4)).max(1);
+        let mut starts = vec![0];
+        for i in 1..end {
+            if i - starts[starts.len() - 1] >= target && is_start(i) {
+                starts.push(i);
+            }
+        }
+        let ranges: Vec<_> = starts.iter().enumerate().map(
+            |(k, a)| match starts.get(k + 1) {
+                Some(b) => (*a, *b, Some(false)),
+                // input broken by error is not finished, as in sequential reading
+                None => (*a, end, if stop.is_some() { None } else { Some(eof) }),
+            }
+        ).collect();
+
+        let policy = self.policy;
+        let results: Vec<_> = ranges.par_iter().map(
+            |(a, b, finish)| {
+                let mut parser = P::default();
+                parser.set_policy(policy);
+                let mut out = vec![];
+                for (i, line) in lines[*a..*b].iter().enumerate() {
+                    match parser.parse_line(base + a + i, line.to_string()) {
+                        Ok(Some(block)) => out.push(Outcome::Block(block, false)),
+                        Ok(None) => {},
+                        Err(e) => out.push(Outcome::Error(ParseError::from_boxed(e), base + a + i)),
+                    }
+                }
+                if let Some(last) = finish {
+                    match parser.finish() {
+                        Ok(Some(block)) => out.push(Outcome::Block(block, *last)),
+                        Ok(None) => {},
+                        Err(e) => out.push(Outcome::Error(ParseError::from_boxed(e), base + b)),
+                    }
+                }
+                (out, parser.take_header(), parser.skipped_lines())
+            }
+        ).collect();
+        for (out, header, skipped) in results {
+            self.outcomes.extend(out);
+            if header.is_some() {
+                self.header = header;
+            }
+            self.parallel_skipped_lines += skipped;
+        }
+
+        self.idx = base + end;
+        self.carry = match lines.get(end) {
+            Some(line) => buf[(line.as_ptr() as usize - text.as_ptr() as usize)..].to_vec(),
+            None => buf[complete..].to_vec(),
+        };
+        self.input_done = eof;
+        if let Some(e) = stop {
+            self.outcomes.push_back(Outcome::Io(e));
+        }
+    }
+
+    fn next_parallel(&mut self) -> Option<Result<P::Block, std::io::Error>> {
+        while !self.done {
+            match self.outcomes.pop_front() {
+                Some(Outcome::Block(block, last)) => if let Some(block) = self.accept(block, last) {
+                    return Some(Ok(block));
+                },
+                Some(Outcome::Error(e, idx)) => if let Some(e) = self.on_error(e, idx) {
+                    return Some(Err(e));
+                },
+                Some(Outcome::Io(e)) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+                None if self.input_done => self.done = true,
+                None => self.fill(),
+            }
+        }
+        None
+    }
+}
+
+impl<T: std::io::Read, P: BlockParser> Iterator for LineReader<T, P> {
+    type Item = Result<P::Block, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.parallel {
+            return self.next_parallel();
+        }
+        while !self.done {
+            match self.read_line() {
+                Some(Ok(line)) => {
+                    let idx = self.idx;
+                    self.idx += 1;
+                    match self.parser.parse_line(idx, line) {
+                        Ok(Some(block)) => if let Some(block) = self.accept(block, false) {
+                            return Some(Ok(block));
+                        },
+                        Ok(None) => {},
+                        Err(e) => if let Some(e) = self.on_error(ParseError::from_boxed(e), idx) {
+                            return Some(Err(e));
+                        },
+                    }
+                },
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+                None => {
+                    self.done = true;
+                    match self.parser.finish() {
+                        Ok(Some(block)) => if let Some(block) = self.accept(block, true) {
+                            return Some(Ok(block));
+                        },
+                        Ok(None) => {},
+                        Err(e) => return self.on_error(ParseError::from_boxed(e), self.idx).map(Err),
+                    }
+                },
+            }
+        }
+        None
+    }
+}
+
+impl<T: std::io::Read, P: BlockParser> EventReader for LineReader<T, P> {
+    type Header = P::Header;
+    type Block = P::Block;
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take().or_else(|| self.parser.take_header())
+    }
+
+    fn set_source(&mut self, source: &str) {
+        self.source = Some(source.to_owned());
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.policy = policy;
+        self.parser.set_policy(policy);
+    }
+
+    fn skipped(&self) -> SkipReport {
+        SkipReport { events: self.skipped_events, lines: self.parser.skipped_lines() + self.parallel_skipped_lines }
+    }
+
+    fn set_exclude_bad(&mut self, exclude: bool) {
+        self.integrity.report.excluded = exclude;
+    }
+
+    fn integrity(&self) -> IntegrityReport {
+        self.integrity.report
+    }
+
+    fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+}
+
+pub trait GenericDataContainer<'a>: Sized {
     type Header;
     type BlockHeader;
     type Block: DataBlock<'a, Self::BlockHeader>;
-    type Decoder;
-    fn get_header(&self) -> &Self::Header;
+    type Reader<T: std::io::Read>: EventReader<Header = Self::Header, Block = Self::Block>;
+
+    /// Streaming reader of the input, events are parsed on demand
+    fn reader<T: Sized + std::io::Read>(data: BufReader<T>) -> Self::Reader<T>;
+
+}
+
+/// Streaming writer of events in an output format.
+/// Events of any input are written through their format independent view `ToCache`
+pub trait EventWriter {
+    fn write_event<B: ToCache>(&mut self, block: &B) -> std::io::Result<()>;
+
+    /// Writes end of output and flushes it
+    fn finish(self) -> std::io::Result<()>;
+}
 
-    fn get_blocks(&self) -> &Vec<Self::Block>;
+/// Dictionary values of particles for event writers.
+/// Values written in the input are used as is; particles missing in dictionary
+/// keep their code and get zero mass and charges, they are reported once per code
+pub struct CodeMapper<'a> {
+    pub dict: &'a dyn ParticleDatabase,
+    unknown: HashSet<i32>,
+}
 
-    fn len(&self) -> usize {
-        self.get_blocks().len()
+impl<'a> CodeMapper<'a> {
+    pub fn new(dict: &'a dyn ParticleDatabase) -> Self {
+        Self { dict, unknown: HashSet::new() }
     }
 
-    fn borrow_blocks(self)  -> Vec<Self::Block>;
+    /// Dictionary particle and sign of its charges
+    fn lookup(&mut self, code: i32, mass: Option<f64>) -> Option<(EposDictParticle, f64)> {
+        let found = match self.dict.get(&code) {
+            Some(x) => Some((x.clone(), 1.0)),
+            None => match self.dict.get(&-code) {
+                Some(x) => Some((x.clone(), -1.0)),
+                None => EposDict::get_nuclei_from_code(code, mass, None).map(|x| (x, 1.0)),
+            },
+        };
+        if found.is_none() && self.unknown.insert(code) {
+            eprintln!("[WARNING]: particle {} is not found in dictionary, it is written with its own code", code);
+        }
+        found
+    }
 
-    fn upload<T: Sized + std::io::Read>(data: BufReader<T>, decoder: &'b Self::Decoder) -> Result<Self, std::io::Error>;
+    pub fn pdg(&mut self, p: &CacheParticle) -> i32 {
+        match self.dict.pdg_code(&p.code) {
+            Some(x) => x,
+            None => {
+                self.lookup(p.code, p.mass);
+                p.code
+            },
+        }
+    }
 
-    fn push_back(&mut self, t: Self);
+    pub fn mass(&mut self, p: &CacheParticle) -> f64 {
+        p.mass.or_else(|| self.lookup(p.code, None).and_then(|x| x.0.mass)).unwrap_or(0.0)
+    }
 
-}
\ No newline at end of file
+    pub fn charge(&mut self, p: &CacheParticle) -> i32 {
+        p.charge.unwrap_or_else(
+            || self.lookup(p.code, p.mass).map(|(x, sign)| (sign * x.charge.unwrap_or(0.0)).round() as i32).unwrap_or(0)
+        )
+    }
+
+    pub fn baryon(&mut self, p: &CacheParticle) -> i32 {
+        p.baryon.unwrap_or_else(
+            || self.lookup(p.code, p.mass).map(|(x, sign)| (sign * x.b_charge(&())).round() as i32).unwrap_or(0)
+        )
+    }
+
+    pub fn strangeness(&mut self, p: &CacheParticle) -> i32 {
+        self.lookup(p.code, p.mass).map(|(x, sign)| (sign * x.strangeness(&())).round() as i32).unwrap_or(0)
+    }
+
+    pub fn lepton(&mut self, p: &CacheParticle) -> i32 {
+        p.lepton.unwrap_or_else(
+            || if self.dict.is_lepton(&p.code) { 1 } else if self.dict.is_lepton(&-p.code) { -1 } else { 0 }
+        )
+    }
+}
+
+/// Opens file as streaming event reader of container `C`
+pub fn open_reader<'a, C: GenericDataContainer<'a>>(path: &str, policy: ErrorPolicy, exclude_bad: bool, parallel: bool) -> Result<C::Reader<File>, std::io::Error> {
+    let mut reader = C::reader(BufReader::new(File::open(path)?));
+    reader.set_source(path);
+    reader.set_policy(policy);
+    reader.set_exclude_bad(exclude_bad);
+    reader.set_parallel(parallel);
+    Ok(reader)
+}
+
+#[test]
+fn test_epos_parallel() {
+    let event = "      0       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n    2    120  1  0.3  0.2  1.0  1.1  0.139  0 0 0 0\n";
+    let bad = "      1       1       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.x  3.2  0.938  0 0 0 0\n";
+    let truncated = "      2       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0";
+    let data = format!("# (1,1)+(1,1) nncm 7000.0 1\n{}{}{}{}", event.repeat(5), bad, event.repeat(5), truncated);
+    let read = |parallel: bool, batch: usize| {
+        let mut reader = super::oscar::OSCEposDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+        reader.set_parallel(parallel);
+        reader.batch_bytes = batch;
+        reader.set_policy(ErrorPolicy::SkipEvent);
+        let events: Vec<_> = reader.by_ref().map(|x| x.unwrap().event.iter().map(|p| p.p.2).collect::<Vec<_>>()).collect();
+        (events, reader.skipped(), reader.integrity(), reader.take_header().map(|x| x.snn))
+    };
+    let sequential = read(false, 1);
+    assert_eq!(sequential.0.len(), 11);
+    for batch in [1, 7, 64, 200, 1 << 20] {
+        assert_eq!(read(true, batch), sequential);
+    }
+}
@@ -1,10 +1,13 @@
+use std::collections::HashMap;
 use std::error::Error;
-use std::io::prelude::*;
-use with_position::{WithPosition, Position};
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
 
-use super::decoder::EposDict;
-/// OSCAR1999 format reader and interpreter
+use super::cache::ToCache;
+use super::database::ParticleDatabase;
+// HepMC3 ASCII format reader and interpreter
 
+use super::error::{columns, ErrorPolicy, ParseError, ParseErrorKind, ParseToken};
 use super::generic::*;
 
 #[derive(Debug)]
@@ -12,29 +15,228 @@ pub struct HepMCBlock {
     /// Block Header
     pub header: HepMCBlockHeader,
     /// Particles
-    pub event: Vec<HepMCParticle>
+    pub event: Vec<HepMCParticle>,
+    /// Vertices
+    pub vertices: Vec<HepMCVertex>,
 }
 
-#[derive(Debug)]
+/// Momentum unit of HepMC event (`U` line), momenta are normalised to GeV while reading
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HepMCMomentumUnit {
+    MeV,
+    #[default]
+    GeV,
+}
+
+impl HepMCMomentumUnit {
+    /// multiplier to convert value to GeV
+    pub fn to_gev(self) -> f64 {
+        match self {
+            HepMCMomentumUnit::MeV => 1e-3,
+            HepMCMomentumUnit::GeV => 1.0,
+        }
+    }
+}
+
+impl FromStr for HepMCMomentumUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "MEV" => Ok(Self::MeV),
+            "GEV" => Ok(Self::GeV),
+            _ => Err(format!("Unknown HepMC momentum unit: {}", s)),
+        }
+    }
+}
+
+/// Length unit of HepMC event (`U` line), positions are normalised to mm while reading
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HepMCLengthUnit {
+    #[default]
+    MM,
+    CM,
+}
+
+impl HepMCLengthUnit {
+    /// multiplier to convert value to mm
+    pub fn to_mm(self) -> f64 {
+        match self {
+            HepMCLengthUnit::MM => 1.0,
+            HepMCLengthUnit::CM => 10.0,
+        }
+    }
+}
+
+impl FromStr for HepMCLengthUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "MM" => Ok(Self::MM),
+            "CM" => Ok(Self::CM),
+            _ => Err(format!("Unknown HepMC length unit: {}", s)),
+        }
+    }
+}
+
+/// `GenCrossSection` event attribute
+#[derive(Debug, Clone, Default)]
+pub struct HepMCCrossSection {
+    /// cross section, pb
+    pub cross_section: f64,
+    pub cross_section_error: f64,
+    pub accepted_events: i64,
+    pub attempted_events: i64,
+}
+
+impl FromStr for HepMCCrossSection {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let toks: Vec<_> = s.split_ascii_whitespace().collect();
+        if toks.len() < 4 {
+            return Err(ParseError::format(format!("Bad GenCrossSection attribute: {}", s)).into());
+        }
+        Ok(Self {
+            cross_section: toks[0].token()?,
+            cross_section_error: toks[1].token()?,
+            accepted_events: toks[2].token()?,
+            attempted_events: toks[3].token()?,
+        })
+    }
+}
+
+/// `GenHeavyIon` event attribute
+#[derive(Debug, Clone, Default)]
+pub struct HepMCHeavyIon {
+    pub ncoll_hard: i32,
+    pub npart_proj: i32,
+    pub npart_targ: i32,
+    pub ncoll: i32,
+    /// impact parameter, fm
+    pub b: f64,
+    pub event_plane_angle: f64,
+    pub eccentricity: f64,
+    pub sigma_inel_nn: f64,
+    pub centrality: f64,
+}
+
+impl HepMCHeavyIon {
+    /// Count of participants
+    pub fn npart(&self) -> i32 {
+        self.npart_proj + self.npart_targ
+    }
+}
+
+impl FromStr for HepMCHeavyIon {
+    type Err = Box<dyn Error>;
+
+    /// Supports both "v0 ..." (HepMC 3.2+) and legacy attribute layouts
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let toks: Vec<_> = s.split_ascii_whitespace().collect();
+        let (toks, b_idx) = if toks.first() == Some(&"v0") {
+            // Ncoll_hard Npart_proj Npart_targ Ncoll N_Nwounded Nwounded_N Nwounded_Nwounded b phi ecc sigma cent
+            (&toks[1..], 7)
+        } else {
+            // Ncoll_hard Npart_proj Npart_targ Ncoll spec_n spec_p N_Nwounded Nwounded_N Nwounded_Nwounded b phi ecc sigma cent
+            (&toks[..], 9)
+        };
+        if toks.len() < b_idx + 5 {
+            return Err(ParseError::format(format!("Bad GenHeavyIon attribute: {}", s)).into());
+        }
+        Ok(Self {
+            ncoll_hard: toks[0].token()?,
+            npart_proj: toks[1].token()?,
+            npart_targ: toks[2].token()?,
+            ncoll: toks[3].token()?,
+            b: toks[b_idx].token()?,
+            event_plane_angle: toks[b_idx + 1].token()?,
+            eccentricity: toks[b_idx + 2].token()?,
+            sigma_inel_nn: toks[b_idx + 3].token()?,
+            centrality: toks[b_idx + 4].token()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct HepMCBlockHeader {
     pub event_id: usize,
-    /// Count of particles in OSCAR event description
+    /// Count of vertices in event
+    pub nvertices: usize,
+    /// Count of particles in event
     pub nout: usize,
 
-    /// weight
+    /// weight (first of event weights)
     pub weight: f64,
-    
+    /// all event weights, names are stored in `HepMCHeader::weight_names`
+    pub weights: Vec<f64>,
+
+    pub momentum_unit: HepMCMomentumUnit,
+    pub length_unit: HepMCLengthUnit,
+
+    pub cross_section: Option<HepMCCrossSection>,
+    pub heavy_ion: Option<HepMCHeavyIon>,
+    /// other event attributes: (id, name) -> value
+    pub attributes: HashMap<(i32, String), String>,
 }
 
-#[derive(Debug)]
+impl<'a> TryFrom<&'a [&'a str]> for HepMCBlockHeader {
+    type Error = Box<dyn Error>;
+
+    /// `E event_id nvertices nparticles`
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        if value.len() < 4 {
+            return Err(ParseError::new(ParseErrorKind::MissingColumns { expected: 4, found: value.len() }).into());
+        }
+        Ok(Self {
+            weight: 0.0,
+            weights: vec![],
+            event_id: value[1].token()?,
+            nvertices: value[2].token()?,
+            nout: value[3].token()?,
+            momentum_unit: Default::default(),
+            length_unit: Default::default(),
+            cross_section: None,
+            heavy_ion: None,
+            attributes: HashMap::new(),
+        })
+    }
+}
+
+/// Tool description of run info (`T` line)
+#[derive(Debug, Clone, Default)]
+pub struct HepMCTool {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+#[derive(Debug, Default)]
 pub struct HepMCHeader {
-    // Collision Energy
-    //pub snn: f64,
-    // OSCAR event signature. 
-    // (1, 1) + (1, 1) or Au(..) + Au(..)
-    // pub event_signature: String,
+    /// HepMC version of the file
+    pub version: String,
+    pub tools: Vec<HepMCTool>,
+    pub weight_names: Vec<String>,
+    /// run attributes
+    pub attributes: HashMap<String, String>,
 }
 
+impl HepMCHeader {
+    /// Event weights paired with their names
+    pub fn named_weights<'a>(&'a self, hd: &'a HepMCBlockHeader) -> Vec<(&'a str, f64)> {
+        hd.weights.iter().enumerate().map(
+            |(i, w)| {
+                (self.weight_names.get(i).map(|x| x.as_str()).unwrap_or(""), *w)
+            }
+        ).collect()
+    }
+
+    /// Event weight by name
+    pub fn weight(&self, hd: &HepMCBlockHeader, name: &str) -> Option<f64> {
+        self.weight_names.iter().position(|x| x == name).and_then(|i| hd.weights.get(i).cloned())
+    }
+}
 
 /*
     Based on HepMC \\
@@ -49,6 +251,9 @@ pub struct HepMCHeader {
 */
 #[derive(Debug)]
 pub struct HepMCParticle {
+    pub id: i32,
+    /// production vertex id (negative) or parent particle id (positive)
+    pub parent: i32,
     pub code: i32,
     pub status: i32,
     pub mass: f64,
@@ -56,30 +261,82 @@ pub struct HepMCParticle {
     pub p: (f64, f64, f64)
 }
 
+/// Vertex (`V` line), position is normalised to mm
+#[derive(Debug)]
+pub struct HepMCVertex {
+    pub id: i32,
+    pub status: i32,
+    /// ids of incoming particles
+    pub incoming: Vec<i32>,
+    /// (x, y, z, t)
+    pub position: Option<(f64, f64, f64, f64)>,
+}
+
+impl TryFrom<&str> for HepMCVertex {
+    type Error = Box<dyn Error>;
+
+    /// V id status [in1,in2,...] @ x y z t
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let (body, pos) = match line.split_once('@') {
+            Some((a, b)) => (a, Some(b)),
+            None => (line, None),
+        };
+        let toks: Vec<_> = body.split_ascii_whitespace().collect();
+        if toks.len() < 3 {
+            return Err(ParseError::format(format!("Bad HepMC vertex line: {}", line)).into());
+        }
+        let incoming = toks[3..].join("").trim_matches(|c| c == '[' || c == ']').split(',')
+            .filter(|x| !x.is_empty())
+            .map(|x| x.trim().token::<i32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let position = match pos {
+            Some(pos) => {
+                let v = pos.split_ascii_whitespace().map(|x| x.token::<f64>()).collect::<Result<Vec<_>, _>>()?;
+                if v.len() < 4 {
+                    return Err(ParseError::format(format!("Bad HepMC vertex position: {}", line)).into());
+                }
+                Some((v[0], v[1], v[2], v[3]))
+            },
+            None => None,
+        };
+        Ok(Self {
+            id: toks[1].token()?,
+            status: toks[2].token()?,
+            incoming,
+            position,
+        })
+    }
+}
+
 impl<'a> TryFrom<(HepMCBlockHeader, &'a Vec<String>)> for HepMCBlock {
     type Error = Box<dyn Error>;
     fn try_from(value: (HepMCBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
-        let particles = value.1.iter().try_fold(
-            vec![],
-            |mut v, line| -> Result<Vec<_>, Box<dyn Error>> {
-                let mut iter = line.split_ascii_whitespace();
-                let mut args = [""; 10];
-                [0usize, 1, 2, 3, 4, 5 ,6 ,7 ,8 ,9].iter()
-                    .try_fold(&mut args, |a, x| {
-                        let v = iter.next();
-                        a[*x] = v?;
-                        Some(a)
-                });
-                v.push(
-                    HepMCParticle::try_from( args )?
-                );
-                Ok(v)
+        let to_gev = value.0.momentum_unit.to_gev();
+        let to_mm = value.0.length_unit.to_mm();
+        let mut vertices = vec![];
+        let particles = parse_lines(
+            value.1,
+            |line| -> Result<Option<HepMCParticle>, Box<dyn Error>> {
+                if line.starts_with("V") {
+                    let mut vertex = HepMCVertex::try_from(line)?;
+                    vertex.position = vertex.position.map(|(x, y, z, t)| (x * to_mm, y * to_mm, z * to_mm, t * to_mm));
+                    vertices.push(vertex);
+                    return Ok(None);
+                }
+                let mut particle = HepMCParticle::try_from( columns::<10>(line)? )?;
+                if to_gev != 1.0 {
+                    particle.p = (particle.p.0 * to_gev, particle.p.1 * to_gev, particle.p.2 * to_gev);
+                    particle.energy *= to_gev;
+                    particle.mass *= to_gev;
+                }
+                Ok(Some(particle))
             }
-        )?;
+        )?.into_iter().flatten().collect();
         Ok(
             Self {
                 header: value.0,
                 event: particles,
+                vertices,
             }
         )
     }
@@ -91,11 +348,13 @@ impl<'a> TryFrom<[&'a str; 10]> for HepMCParticle {
     fn try_from(value: [&'a str; 10]) -> Result<Self, Self::Error> {
         // value[0] is P literal
         let s = Self {
-            code: value[3].parse().unwrap(),
-            p: (value[4].parse().unwrap(), value[5].parse().unwrap(), value[6].parse().unwrap()),
-            energy: value[7].parse().unwrap(),
-            mass: value[8].parse().unwrap(),
-            status: value[9].parse().unwrap(),
+            id: value[1].token()?,
+            parent: value[2].token()?,
+            code: value[3].token()?,
+            p: (value[4].token()?, value[5].token()?, value[6].token()?),
+            energy: value[7].token()?,
+            mass: value[8].token()?,
+            status: value[9].token()?,
         };
         Ok(s)
     }
@@ -107,135 +366,293 @@ impl<'a> DataBlock<'a, HepMCBlockHeader> for HepMCBlock {
     }
 }
 
+impl ParticleCount for HepMCBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
 
 #[derive(Debug)]
-pub struct HepMCDataFile<'a> {
-    header: HepMCHeader,
-    events: Vec<HepMCBlock>,
-    pub decoder: &'a EposDict
+pub struct HepMCDataFile;
+
+
+/// Line parser of HepMC3 ASCII output
+#[derive(Debug, Default)]
+pub struct HepMCParser {
+    header: Option<HepMCHeader>,
+    buf: EventBuffer<HepMCBlockHeader>,
 }
 
+impl BlockParser for HepMCParser {
+    type Header = HepMCHeader;
 
-impl<'a, 'b> GenericDataContainer<'a, 'b> for HepMCDataFile<'b> {
+    type Block = HepMCBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let line = line.trim().to_string();
+        if line.starts_with("HepMC") {
+            let header = self.header.get_or_insert_with(HepMCHeader::default);
+            if let Some(version) = line.strip_prefix("HepMC::Version") {
+                header.version = version.trim().to_owned();
+            }
+            Ok(None)
+        } else if line.starts_with("E") {
+            // new event
+            let toks: Vec<_> = line.split_ascii_whitespace().filter(|x| x.len() > 0).collect();
+            self.buf.start(idx, HepMCBlockHeader::try_from(toks.as_slice()))
+        } else if line.starts_with("U") {
+            self.buf.update_header(idx, |hd| {
+                let tr = line.split_ascii_whitespace().collect::<Vec<&str>>();
+                if tr.len() < 3 {
+                    return Err(ParseError::format(format!("Bad HepMC units line: {}", line)).into());
+                }
+                hd.momentum_unit = tr[1].token()?;
+                hd.length_unit = tr[2].token()?;
+                Ok(())
+            });
+            Ok(None)
+        } else if line.starts_with("W") {
+            if self.buf.is_open() {
+                self.buf.update_header(idx, |hd| {
+                    hd.weights = line.split_ascii_whitespace().skip(1).map(|x| x.token::<f64>()).collect::<Result<Vec<_>, _>>()?;
+                    hd.weight = hd.weights.first().cloned().unwrap_or(0.0);
+                    Ok(())
+                });
+            } else {
+                // run info: weight names, separated with escaped new line
+                let header = self.header.get_or_insert_with(HepMCHeader::default);
+                header.weight_names = line.strip_prefix('W').unwrap_or_default().trim().split("\\n")
+                    .flat_map(|x| x.split_ascii_whitespace())
+                    .map(|x| x.to_owned())
+                    .collect();
+            }
+            Ok(None)
+        } else if line.starts_with("N") {
+            // legacy weight names line: N count "name1" "name2" ...
+            let header = self.header.get_or_insert_with(HepMCHeader::default);
+            header.weight_names = line.split_ascii_whitespace().skip(1)
+                .filter(|x| x.parse::<usize>().is_err())
+                .map(|x| x.trim_matches('"').to_owned())
+                .collect();
+            Ok(None)
+        } else if line.starts_with("T") {
+            let header = self.header.get_or_insert_with(HepMCHeader::default);
+            let mut tool = line.strip_prefix('T').unwrap_or_default().trim().split("\\|");
+            header.tools.push(HepMCTool {
+                name: tool.next().unwrap_or("").to_owned(),
+                version: tool.next().unwrap_or("").to_owned(),
+                description: tool.next().unwrap_or("").to_owned(),
+            });
+            Ok(None)
+        } else if line.starts_with("A") {
+            let mut tr = line.strip_prefix('A').unwrap_or_default().trim().splitn(2, char::is_whitespace);
+            let first = tr.next().unwrap_or("");
+            let rest = tr.next().unwrap_or("").trim();
+            match first.parse::<i32>() {
+                Ok(id) if self.buf.is_open() => {
+                    // event attribute: A id name value
+                    let (name, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                    let value = value.trim();
+                    self.buf.update_header(idx, |hd| {
+                        match name {
+                            "GenCrossSection" => hd.cross_section = Some(value.parse()?),
+                            "GenHeavyIon" => hd.heavy_ion = Some(value.parse()?),
+                            _ => { hd.attributes.insert((id, name.to_owned()), value.to_owned()); },
+                        }
+                        Ok(())
+                    });
+                },
+                _ => {
+                    // run attribute: A name value
+                    let header = self.header.get_or_insert_with(HepMCHeader::default);
+                    header.attributes.insert(first.to_owned(), rest.to_owned());
+                }
+            }
+            Ok(None)
+        } else if line.starts_with("P") || line.starts_with("V") {
+            // particle or vertex
+            self.buf.push(idx, line);
+            Ok(None)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        line.trim_start().starts_with('E')
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Particles are linked by vertices, so only whole events are selected
+impl EventLayout for HepMCParser {
+    fn is_footer(line: &str) -> bool {
+        line.starts_with("HepMC::") && line.contains("END_EVENT_LISTING")
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for HepMCDataFile {
     type Header = HepMCHeader;
 
     type BlockHeader = HepMCBlockHeader;
 
     type Block = HepMCBlock;
 
-    type Decoder = EposDict;
+    type Reader<T: std::io::Read> = LineReader<T, HepMCParser>;
 
-    fn get_header(&self) -> &Self::Header {
-        &self.header
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
     }
+}
 
-    fn get_blocks(&self) -> &Vec<Self::Block> {
-        &self.events
-    }
-
-    fn borrow_blocks(self)  -> Vec<Self::Block> {
-        self.events
-    }
-
-    fn upload<T: Sized + std::io::Read>(data: std::io::BufReader<T>, decoder: &'b Self::Decoder) -> Result<Self, std::io::Error> {
-        match data.lines().with_position().enumerate().try_fold(
-            (
-                None,
-                None,
-                Vec::<String>::new(),
-                vec![]
-            ),
-            |
-                (mut header, mut bufheader, mut buf, mut events)
-                , (idx, (position, _line))
-            | -> Result<_, Box<dyn Error> > {
-                match _line {
-                    Ok(line) => {
-                        let line = line.trim().to_string();
-                        if line.starts_with("HepMC") {
-                            if let Position::Last = position {
-                                if let Some(hd) = bufheader {
-                                    let block = Self::Block::try_from(
-                                        (hd, &buf)
-                                    )?;
-                                    events.push(block);
-                                    buf.clear();
-                                }
-                                bufheader = None;
-                            }
-                            Ok((Some(HepMCHeader {}), bufheader, buf, events))
-                        } else if line.starts_with("E") {
-                            // new event
-                            if let Some(hd) = bufheader {
-                                let block = Self::Block::try_from(
-                                    (hd, &buf)
-                                )?;
-                                events.push(block);
-                                buf.clear();
-                            }
-                            let toks: Vec<_> = line.split_ascii_whitespace().filter(|x| x.len() > 0).collect();
-                            bufheader = Some(Self::BlockHeader {                                        
-                                weight: 0.0,
-                                event_id: toks[1].parse()?,
-                                nout: toks[3].parse()?,
-                            });
-                            Ok((header, bufheader, buf, events))
-                        } else if line.starts_with("W") {
-                            if let Some(hd) = &mut bufheader {
-                                let tr = line.split_ascii_whitespace().filter(|x| x.len() > 0).collect::<Vec<&str>>();
-                                hd.weight = tr[1].parse()?;
-                            }
-                            Ok((header, bufheader, buf, events))
-                        } else if line.starts_with("P") {
-                            // particle!
-                            buf.push(line);
-                            if let Position::Last = position {
-                                if let Some(hd) = bufheader {
-                                    let block = Self::Block::try_from(
-                                        (hd, &buf)
-                                    )?;
-                                    events.push(block);
-                                    buf.clear();
-                                }
-                                bufheader = None;
-                            }
-                            Ok((header, bufheader, buf, events))
-                        } else {
-                            if let Position::Last = position {
-                                if let Some(hd) = bufheader {
-                                    let block = Self::Block::try_from(
-                                        (hd, &buf)
-                                    )?;
-                                    events.push(block);
-                                    buf.clear();
-                                }
-                                bufheader = None;
-                            }
-                            Ok((header, bufheader, buf, events))
-                        }
-                    },
-                    Err(e) => {
-                        Err(Box::new(e))
-                    },
-                }
-            }
-        ) {
-            Ok((a, b ,c, events)) => {
-                Ok(
-                    Self {
-                        header: a.unwrap(),
-                        events: events,
-                        decoder: decoder
-                    }
-                )
-            },
-            Err(e) => {
-                Err(std::io::Error::new::<String>(std::io::ErrorKind::InvalidData, e.as_ref().to_string().into()))
-            },
+/// Writer of HepMC3 ASCII (Asciiv3) output.
+/// Particles are attached to the root vertex: vertices are not known for most inputs.
+/// Final particles get status 1, the other ones status 2
+pub struct HepMCWriter<'a, W: Write> {
+    data: BufWriter<W>,
+    codes: CodeMapper<'a>,
+    header_written: bool,
+    events: usize,
+}
+
+impl<'a, W: Write> HepMCWriter<'a, W> {
+    pub fn new(data: W, dict: &'a dyn ParticleDatabase) -> Self {
+        Self {
+            data: BufWriter::new(data),
+            codes: CodeMapper::new(dict),
+            header_written: false,
+            events: 0,
+        }
+    }
+
+    fn write_header(&mut self) -> std::io::Result<()> {
+        self.header_written = true;
+        writeln!(self.data, "HepMC::Version 3.02.05")?;
+        writeln!(self.data, "HepMC::Asciiv3-START_EVENT_LISTING")?;
+        writeln!(self.data, "T hega-rs\\|{}\\|converted", env!("CARGO_PKG_VERSION"))
+    }
+}
+
+impl<W: Write> EventWriter for HepMCWriter<'_, W> {
+    fn write_event<B: ToCache>(&mut self, block: &B) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header()?;
         }
+        let particles = block.particles();
+        writeln!(self.data, "E {} 0 {}", block.event_id().unwrap_or(self.events), particles.len())?;
+        writeln!(self.data, "U GEV MM")?;
+        writeln!(self.data, "W {}", block.value("weight").unwrap_or(1.0))?;
+        for (i, p) in particles.iter().enumerate() {
+            let (pdg, mass) = (self.codes.pdg(p), self.codes.mass(p));
+            writeln!(
+                self.data, "P {} 0 {} {} {} {} {} {} {}",
+                i + 1, pdg, p.p.0, p.p.1, p.p.2, p.p0, mass, if p.is_final { 1 } else { 2 }
+            )?;
+        }
+        self.events += 1;
+        Ok(())
     }
 
-    fn push_back(&mut self, mut t: Self) {
-        self.events.append(&mut t.events);
+    fn finish(mut self) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        writeln!(self.data, "HepMC::Asciiv3-END_EVENT_LISTING")?;
+        self.data.flush()
     }
 }
+
+#[test]
+fn test_hepmc3_records() {
+    let data = r#"HepMC::Version 3.02.05
+HepMC::Asciiv3-START_EVENT_LISTING
+W default\nscale_up
+T EPOS\|4.0.0\|heavy ion generator
+A sqrt_s 200
+E 7 1 3
+U MEV CM
+W 1.5 2.5
+A 0 GenCrossSection 4.2e+01 1.0e-01 1000 1200
+A 0 GenHeavyIon v0 10 50 60 300 0 0 0 7.5 0.3 0.1 42.0 20.0 -1 0 0 0 0
+P 1 0 2212 0 0 100000 100004.4 938.27 4
+P 2 0 2212 0 0 -100000 100004.4 938.27 4
+V -1 0 [1,2] @ 1 2 3 4
+P 3 -1 211 1000 0 0 1009.7 139.57 1
+HepMC::Asciiv3-END_EVENT_LISTING
+"#;
+    let mut reader = HepMCDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let header = reader.take_header().unwrap();
+
+    assert_eq!(header.version, "3.02.05");
+    let tool = &header.tools[0];
+    assert_eq!((tool.name.as_str(), tool.version.as_str(), tool.description.as_str()), ("EPOS", "4.0.0", "heavy ion generator"));
+    assert_eq!(header.weight(&event.header, "scale_up"), Some(2.5));
+    assert_eq!(header.weight(&event.header, "scale_down"), None);
+    assert_eq!(header.named_weights(&event.header), vec![("default", 1.5), ("scale_up", 2.5)]);
+    assert_eq!(event.header.weight, 1.5);
+    assert_eq!((event.header.nvertices, event.header.nout), (1, 3));
+    let xs = event.header.cross_section.as_ref().unwrap();
+    assert_eq!((xs.cross_section, xs.accepted_events, xs.attempted_events), (42.0, 1000, 1200));
+    let hi = event.header.heavy_ion.as_ref().unwrap();
+    assert_eq!(hi.npart(), 110);
+    assert_eq!((hi.ncoll_hard, hi.b), (10, 7.5));
+    assert_eq!((hi.eccentricity, hi.sigma_inel_nn, hi.centrality), (0.1, 42.0, 20.0));
+    assert_eq!(event.event.len(), 3);
+    assert_eq!((event.event[2].id, event.event[2].parent), (3, -1));
+    assert!((event.event[2].p.0 - 1.0).abs() < 1e-12);
+    assert_eq!((event.vertices[0].id, event.vertices[0].status), (-1, 0));
+    assert_eq!(event.vertices[0].incoming, vec![1, 2]);
+    assert_eq!(event.vertices[0].position, Some((10.0, 20.0, 30.0, 40.0)));
+    let cached = event.particles();
+    assert_eq!((cached[0].position, cached[2].position), (None, Some((10.0, 20.0, 30.0, 40.0))));
+    assert_eq!(event.value("ncoll"), Some(300.0));
+    assert_eq!(event.value("cross_section"), Some(42.0));
+}
+
+#[test]
+fn test_hepmc3_writer() {
+    use super::decoder::{DctCoding, EposDict};
+    use super::oscar::OSCEposDataFile;
+    let dict = EposDict::upload(
+        std::io::BufReader::new("       1120       2212        2    14   13    Proton                1  1   2   0  0.93827    1.00000   0.0000E+00  2  2  R\n".as_bytes()),
+        DctCoding::EPOS,
+        None,
+    );
+    let data = "# (1,1)+(1,1) nncm 7000.0 1\n      0       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n    2    -1120  1  0.3  0.2  1.0  1.1  0.938  0 0 0 0\n";
+    let mut out = vec![];
+    let mut writer = HepMCWriter::new(&mut out, &dict);
+    OSCEposDataFile::reader(std::io::BufReader::new(data.as_bytes()))
+        .for_each(|x| writer.write_event(&x.unwrap()).unwrap());
+    writer.finish().unwrap();
+
+    let mut reader = HepMCDataFile::reader(std::io::BufReader::new(out.as_slice()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!(event.header.nout, 2);
+    assert_eq!((event.event[0].code, event.event[0].status), (2212, 1));
+    assert_eq!((event.event[1].code, event.event[1].status), (-2212, 2));
+    assert_eq!(event.event[1].p, (0.3, 0.2, 1.0));
+    assert_eq!(event.event[1].energy, 1.1);
+}
@@ -0,0 +1,411 @@
+use std::error::Error;
+
+use super::hepmc::{HepMCCrossSection, HepMCHeavyIon, HepMCLengthUnit, HepMCMomentumUnit};
+// HepMC2 (IO_GenEvent) format reader and interpreter
+
+use super::error::{columns, ErrorPolicy, ParseError, ParseToken};
+use super::generic::*;
+
+#[derive(Debug)]
+pub struct HepMC2Block {
+    /// Block Header
+    pub header: HepMC2BlockHeader,
+    /// Particles
+    pub event: Vec<HepMC2Particle>,
+    /// Vertices
+    pub vertices: Vec<HepMC2Vertex>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HepMC2BlockHeader {
+    pub event_id: usize,
+    /// Count of multi parton interactions
+    pub n_mpi: i32,
+    pub scale: f64,
+    pub alpha_qcd: f64,
+    pub alpha_qed: f64,
+    pub signal_process_id: i32,
+    /// Count of vertices in event
+    pub nvertices: usize,
+    /// Count of particles in event (not written in `E` line, counted while reading)
+    pub nout: usize,
+
+    /// weight (first of event weights)
+    pub weight: f64,
+    /// all event weights
+    pub weights: Vec<f64>,
+    /// names of weights (`N` line)
+    pub weight_names: Vec<String>,
+
+    pub momentum_unit: HepMCMomentumUnit,
+    pub length_unit: HepMCLengthUnit,
+
+    pub cross_section: Option<HepMCCrossSection>,
+    pub heavy_ion: Option<HepMCHeavyIon>,
+}
+
+impl HepMC2BlockHeader {
+    /// Event weight by name
+    pub fn named_weight(&self, name: &str) -> Option<f64> {
+        self.weight_names.iter().position(|x| x == name).and_then(|i| self.weights.get(i).cloned())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HepMC2Header {
+    /// HepMC version of the file
+    pub version: String,
+}
+
+/// P barcode pdg px py pz e m status theta phi end_vertex n_flow [flows]
+#[derive(Debug)]
+pub struct HepMC2Particle {
+    pub barcode: i32,
+    pub code: i32,
+    pub status: i32,
+    pub mass: f64,
+    pub energy: f64,
+    pub p: (f64, f64, f64),
+    /// polarization (theta, phi)
+    pub polarization: (f64, f64),
+    /// barcode of production vertex, 0 for incoming particles without it (e.g. beams)
+    pub production_vertex: i32,
+    /// barcode of end vertex, 0 if particle has no decay vertex
+    pub end_vertex: i32,
+}
+
+impl<'a> TryFrom<[&'a str; 12]> for HepMC2Particle {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: [&'a str; 12]) -> Result<Self, Self::Error> {
+        // value[0] is P literal
+        let s = Self {
+            barcode: value[1].token()?,
+            code: value[2].token()?,
+            p: (value[3].token()?, value[4].token()?, value[5].token()?),
+            energy: value[6].token()?,
+            mass: value[7].token()?,
+            status: value[8].token()?,
+            polarization: (value[9].token()?, value[10].token()?),
+            production_vertex: 0,
+            end_vertex: value[11].token()?,
+        };
+        Ok(s)
+    }
+}
+
+/// V barcode id x y z t n_orphan n_out n_weights [weights]
+#[derive(Debug)]
+pub struct HepMC2Vertex {
+    pub barcode: i32,
+    pub id: i32,
+    /// (x, y, z, t), normalised to mm
+    pub position: (f64, f64, f64, f64),
+    /// count of incoming particles listed after the vertex, they have no production vertex
+    pub n_orphan: usize,
+    /// count of outgoing particles listed after the orphans
+    pub n_out: usize,
+}
+
+impl<'a> TryFrom<[&'a str; 9]> for HepMC2Vertex {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: [&'a str; 9]) -> Result<Self, Self::Error> {
+        // value[0] is V literal
+        let s = Self {
+            barcode: value[1].token()?,
+            id: value[2].token()?,
+            position: (value[3].token()?, value[4].token()?, value[5].token()?, value[6].token()?),
+            n_orphan: value[7].token()?,
+            n_out: value[8].token()?,
+        };
+        Ok(s)
+    }
+}
+
+fn heavy_ion_from_h_line(toks: &[&str]) -> Result<HepMCHeavyIon, Box<dyn Error>> {
+    // H Ncoll_hard Npart_proj Npart_targ Ncoll spec_n spec_p N_Nwounded Nwounded_N Nwounded_Nwounded b phi ecc sigma
+    if toks.len() < 14 {
+        return Err(ParseError::format(format!("Bad HepMC2 heavy ion line: {}", toks.join(" "))).into());
+    }
+    Ok(HepMCHeavyIon {
+        ncoll_hard: toks[1].token()?,
+        npart_proj: toks[2].token()?,
+        npart_targ: toks[3].token()?,
+        ncoll: toks[4].token()?,
+        b: toks[10].token()?,
+        event_plane_angle: toks[11].token()?,
+        eccentricity: toks[12].token()?,
+        sigma_inel_nn: toks[13].token()?,
+        centrality: toks.get(14).map(|x| x.token()).transpose()?.unwrap_or(-1.0),
+    })
+}
+
+impl<'a> TryFrom<(HepMC2BlockHeader, &'a Vec<String>)> for HepMC2Block {
+    type Error = Box<dyn Error>;
+    fn try_from(value: (HepMC2BlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let to_gev = value.0.momentum_unit.to_gev();
+        let to_mm = value.0.length_unit.to_mm();
+        let mut vertices: Vec<HepMC2Vertex> = vec![];
+        // orphans of the last vertex not read yet
+        let mut orphans = 0;
+        let particles = parse_lines(
+            value.1,
+            |line| -> Result<Option<HepMC2Particle>, Box<dyn Error>> {
+                if line.starts_with("V") {
+                    let mut vertex = HepMC2Vertex::try_from( columns::<9>(line)? )?;
+                    let (x, y, z, t) = vertex.position;
+                    vertex.position = (x * to_mm, y * to_mm, z * to_mm, t * to_mm);
+                    orphans = vertex.n_orphan;
+                    vertices.push(vertex);
+                    return Ok(None);
+                }
+                let mut particle = HepMC2Particle::try_from( columns::<12>(line)? )?;
+                // incoming orphans of a vertex precede its outgoing particles
+                if orphans > 0 {
+                    orphans -= 1;
+                } else {
+                    particle.production_vertex = vertices.last().map(|x| x.barcode).unwrap_or(0);
+                }
+                if to_gev != 1.0 {
+                    particle.p = (particle.p.0 * to_gev, particle.p.1 * to_gev, particle.p.2 * to_gev);
+                    particle.energy *= to_gev;
+                    particle.mass *= to_gev;
+                }
+                Ok(Some(particle))
+            }
+        )?.into_iter().flatten().collect::<Vec<_>>();
+        let mut header = value.0;
+        header.nout = particles.len();
+        Ok(
+            Self {
+                header,
+                event: particles,
+                vertices,
+            }
+        )
+    }
+}
+
+impl<'a> DataBlock<'a, HepMC2BlockHeader> for HepMC2Block {
+    fn get_header(&self) -> &HepMC2BlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for HepMC2Block {
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+
+#[derive(Debug)]
+pub struct HepMC2DataFile;
+
+/// Line parser of HepMC2 IO_GenEvent output
+#[derive(Debug, Default)]
+pub struct HepMC2Parser {
+    header: Option<HepMC2Header>,
+    buf: EventBuffer<HepMC2BlockHeader>,
+}
+
+impl HepMC2Parser {
+    /// E evnum n_mpi scale aQCD aQED signal_id signal_vertex n_vertices beam1 beam2 n_rand [rand] n_weights [weights]
+    fn parse_event_line(toks: &[&str]) -> Result<HepMC2BlockHeader, Box<dyn Error>> {
+        if toks.len() < 12 {
+            return Err(ParseError::format(format!("Bad HepMC2 event line: {}", toks.join(" "))).into());
+        }
+        let n_rand: usize = toks[11].token()?;
+        let weights = match toks.get(12 + n_rand) {
+            Some(n_weights) => {
+                let n_weights: usize = n_weights.token()?;
+                toks.iter().skip(13 + n_rand).take(n_weights).map(|x| x.token::<f64>()).collect::<Result<Vec<_>, _>>()?
+            },
+            None => vec![],
+        };
+        Ok(HepMC2BlockHeader {
+            event_id: toks[1].token()?,
+            n_mpi: toks[2].token()?,
+            scale: toks[3].token()?,
+            alpha_qcd: toks[4].token()?,
+            alpha_qed: toks[5].token()?,
+            signal_process_id: toks[6].token()?,
+            nvertices: toks[8].token()?,
+            nout: 0,
+            weight: weights.first().cloned().unwrap_or(0.0),
+            weights,
+            weight_names: vec![],
+            momentum_unit: Default::default(),
+            length_unit: Default::default(),
+            cross_section: None,
+            heavy_ion: None,
+        })
+    }
+}
+
+impl BlockParser for HepMC2Parser {
+    type Header = HepMC2Header;
+
+    type Block = HepMC2Block;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let line = line.trim().to_string();
+        if line.starts_with("HepMC") {
+            let header = self.header.get_or_insert_with(HepMC2Header::default);
+            if let Some(version) = line.strip_prefix("HepMC::Version") {
+                header.version = version.trim().to_owned();
+            }
+            return Ok(None);
+        }
+        let toks: Vec<_> = line.split_ascii_whitespace().collect();
+        match toks.first() {
+            Some(&"E") => {
+                // new event
+                self.buf.start(idx, Self::parse_event_line(&toks))
+            },
+            Some(&"N") => {
+                self.buf.update_header(idx, |hd| {
+                    hd.weight_names = line[1..].split('"')
+                        .skip(1).step_by(2)
+                        .map(|x| x.to_owned())
+                        .collect();
+                    Ok(())
+                });
+                Ok(None)
+            },
+            Some(&"U") => {
+                self.buf.update_header(idx, |hd| {
+                    if toks.len() < 3 {
+                        return Err(ParseError::format(format!("Bad HepMC2 units line: {}", line)).into());
+                    }
+                    hd.momentum_unit = toks[1].token()?;
+                    hd.length_unit = toks[2].token()?;
+                    Ok(())
+                });
+                Ok(None)
+            },
+            Some(&"C") => {
+                self.buf.update_header(idx, |hd| {
+                    if toks.len() < 3 {
+                        return Err(ParseError::format(format!("Bad HepMC2 cross section line: {}", line)).into());
+                    }
+                    hd.cross_section = Some(HepMCCrossSection {
+                        cross_section: toks[1].token()?,
+                        cross_section_error: toks[2].token()?,
+                        accepted_events: -1,
+                        attempted_events: -1,
+                    });
+                    Ok(())
+                });
+                Ok(None)
+            },
+            Some(&"H") => {
+                self.buf.update_header(idx, |hd| {
+                    hd.heavy_ion = Some(heavy_ion_from_h_line(&toks)?);
+                    Ok(())
+                });
+                Ok(None)
+            },
+            Some(&"V") | Some(&"P") => {
+                // vertex or particle
+                self.buf.push(idx, line);
+                Ok(None)
+            },
+            _ => {
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        line.split_ascii_whitespace().next() == Some("E")
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Particles are linked by vertices, so only whole events are selected
+impl EventLayout for HepMC2Parser {
+    fn is_footer(line: &str) -> bool {
+        line.starts_with("HepMC::") && line.contains("END_EVENT_LISTING")
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for HepMC2DataFile {
+    type Header = HepMC2Header;
+
+    type BlockHeader = HepMC2BlockHeader;
+
+    type Block = HepMC2Block;
+
+    type Reader<T: std::io::Read> = LineReader<T, HepMC2Parser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+#[test]
+fn test_hepmc2_records() {
+    use super::cache::ToCache;
+    let data = r#"HepMC::Version 2.06.09
+HepMC::IO_GenEvent-START_EVENT_LISTING
+E 1 -1 -1.0000000000000000e+00 -1.0000000000000000e+00 -1.0000000000000000e+00 20 0 4 1 2 0 2 1.0000000000000000e+00 5.0000000000000000e-01
+N 2 "0" "alt"
+U GEV CM
+C 4.2000000000000000e+01 1.0000000000000000e-01
+H 10 50 60 300 0 0 0 0 0 7.5 0.3 0.1 42.0
+V -1 0 0 0 0 0 1 1 0
+P 1 2212 0 0 7.0000000000000000e+03 7.0000000000000000e+03 9.3827000000000005e-01 4 0 0 -1 0
+P 3 1 7.5000000000000000e-01 -1.5690000000000000e+00 3.2191000000000003e+01 3.2238000000000000e+01 0 3 0 0 -3 0
+V -2 0 0 0 0 0 1 1 0
+P 2 2212 0 0 -7.0000000000000000e+03 7.0000000000000000e+03 9.3827000000000005e-01 4 0 0 -2 0
+P 4 -2 -3.0470000000000002e+00 -1.9000000000000000e+01 -5.4628999999999998e+01 5.7920000000000002e+01 0 3 0 0 -3 0
+V -3 0 0 0 0 0 0 2 0
+P 5 -24 1.5169999999999999e+00 -2.0680000000000000e+01 -2.0605000000000000e+01 8.5924999999999997e+01 8.0799999999999997e+01 3 0 0 -4 0
+P 6 22 -3.8130000000000002e+00 1.1300000000000001e-01 -1.8330000000000000e+00 4.2329999999999997e+00 0 1 0 0 0 0
+V -4 0 1.2000000000000000e-01 -3.0000000000000000e-01 5.0000000000000003e-02 4.0000000000000001e-03 0 2 0
+P 7 1 -2.4449999999999998e+00 2.8815999999999999e+01 6.0819999999999999e+00 2.9552000000000000e+01 1.0000000000000000e-02 1 0 0 0 0
+P 8 -2 3.9620000000000002e+00 -4.9497999999999998e+01 -2.6687000000000001e+01 5.6372999999999998e+01 6.0000000000000001e-03 1 0 0 0 0
+HepMC::IO_GenEvent-END_EVENT_LISTING
+"#;
+    let mut reader = HepMC2DataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!(reader.take_header().unwrap().version, "2.06.09");
+    assert_eq!((event.header.event_id, event.header.nvertices, event.header.nout), (1, 4, 8));
+    assert_eq!(event.header.weights, vec![1.0, 0.5]);
+    assert_eq!(event.header.named_weight("alt"), Some(0.5));
+    let hi = event.header.heavy_ion.as_ref().unwrap();
+    assert_eq!((hi.ncoll_hard, hi.b, hi.sigma_inel_nn, hi.centrality), (10, 7.5, 42.0, -1.0));
+    assert_eq!(event.value("ncoll"), Some(300.0));
+    // beams are orphans entering their vertices
+    assert_eq!((event.event[0].barcode, event.event[0].production_vertex, event.event[0].end_vertex), (1, 0, -1));
+    assert_eq!((event.event[2].barcode, event.event[2].production_vertex, event.event[2].end_vertex), (2, 0, -2));
+    assert_eq!((event.event[1].barcode, event.event[1].production_vertex, event.event[1].end_vertex), (3, -1, -3));
+    assert_eq!((event.event[6].barcode, event.event[6].production_vertex), (7, -4));
+    assert_eq!((event.vertices[0].n_orphan, event.vertices[3].n_out, event.vertices[3].id), (1, 2, 0));
+    let particles = event.particles();
+    assert_eq!(particles[0].position, None);
+    assert_eq!(particles[1].position, Some((0.0, 0.0, 0.0, 0.0)));
+    // positions are normalised to mm
+    let (x, y, z, t) = particles[6].position.unwrap();
+    assert!((x - 1.2).abs() < 1e-12 && (y + 3.0).abs() < 1e-12 && (z - 0.5).abs() < 1e-12 && (t - 0.04).abs() < 1e-12);
+    assert_eq!(event.event.iter().filter(|x| x.status == 1).count(), 3);
+    assert_eq!(event.event[5].polarization, (0.0, 0.0));
+}
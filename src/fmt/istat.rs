@@ -0,0 +1,128 @@
+use std::io::BufRead;
+
+use super::error::{ParseError, ParseToken};
+
+// EPOS particle status `ist` and sets of particles selected by it
+
+/// EPOS status list, compiled into the binary
+pub const ISTAT: &str = include_str!("../../dicts/istat.dt");
+
+/// Status `ist` of EPOS particle, see `istat.dt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EposStatus {
+    /// 0: particle last generation
+    LastGeneration,
+    /// 1: particle not last generation
+    NotLastGeneration,
+    /// 2: invalid particle
+    Invalid,
+    /// 3: particle going into hadronic cascade
+    HadronicCascade,
+    /// 5: particle going into core (intermediate)
+    CoreIntermediate,
+    /// 7: particle going into core (final)
+    CoreFinal,
+    /// 8: particle last generation without hadronic cascade
+    LastGenerationNoHacas,
+    /// 6: particle not last generation without hadronic cascade
+    NotLastGenerationNoHacas,
+    /// 20: parton last generation
+    PartonLastGeneration,
+    /// 21: parton not last generation
+    PartonNotLastGeneration,
+    /// 25: intermediate out-Born parton
+    OutBornParton,
+    /// 29: string
+    String,
+    /// status missing in `istat.dt`
+    Other(i32),
+}
+
+impl EposStatus {
+    pub fn from_code(ist: i32) -> Self {
+        match ist {
+            0 => Self::LastGeneration,
+            1 => Self::NotLastGeneration,
+            2 => Self::Invalid,
+            3 => Self::HadronicCascade,
+            5 => Self::CoreIntermediate,
+            7 => Self::CoreFinal,
+            8 => Self::LastGenerationNoHacas,
+            6 => Self::NotLastGenerationNoHacas,
+            20 => Self::PartonLastGeneration,
+            21 => Self::PartonNotLastGeneration,
+            25 => Self::OutBornParton,
+            29 => Self::String,
+            x => Self::Other(x),
+        }
+    }
+
+    /// Description of status in embedded `istat.dt`
+    pub fn description(ist: i32) -> Option<String> {
+        parse_status_table(ISTAT.as_bytes()).ok()?.into_iter().find(|(x, _)| *x == ist).map(|(_, x)| x)
+    }
+}
+
+/// Lines `<ist> <description>` of `istat.dt`, lines starting with `!` are comments
+pub fn parse_status_table(data: impl BufRead) -> Result<Vec<(i32, String)>, ParseError> {
+    let mut entries = vec![];
+    for (i, s) in data.lines().enumerate() {
+        let s = s.map_err(|e| ParseError::format(e.to_string()).at_line(i))?;
+        let s = s.trim();
+        if s.is_empty() || s.starts_with('!') {
+            continue;
+        }
+        let (ist, description) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        entries.push((ist.token::<i32>().map_err(|e| e.at_line(i))?, description.trim().to_string()));
+    }
+    Ok(entries)
+}
+
+/// Particles analysed as final state of event
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FinalState {
+    /// final particles of the format: EPOS last generation after hadronic cascade (`ist` 0)
+    #[default]
+    Final,
+    /// EPOS last generation of hydro without hadronic cascade (`ist` 8)
+    NoHacas,
+    /// EPOS particles going into core (`ist` 5 and 7)
+    Core,
+    /// all particles of event
+    All,
+}
+
+impl FinalState {
+    /// True for EPOS particle of status `ist` in the set
+    pub fn contains(&self, ist: i32) -> bool {
+        matches!(
+            (self, EposStatus::from_code(ist)),
+            (Self::Final, EposStatus::LastGeneration)
+                | (Self::NoHacas, EposStatus::LastGenerationNoHacas)
+                | (Self::Core, EposStatus::CoreIntermediate | EposStatus::CoreFinal)
+                | (Self::All, _)
+        )
+    }
+
+    /// Set is defined by EPOS statuses and is not available for other formats
+    pub fn is_epos_only(&self) -> bool {
+        matches!(self, Self::NoHacas | Self::Core)
+    }
+}
+
+#[test]
+fn test_epos_status() {
+    let table = parse_status_table(ISTAT.as_bytes()).unwrap();
+    assert_eq!(table.len(), 12);
+    // every status of istat.dt is known
+    assert!(table.iter().all(|(ist, _)| !matches!(EposStatus::from_code(*ist), EposStatus::Other(_))));
+    assert_eq!(EposStatus::description(8), Some("particle last generation without hadronic cascade".to_string()));
+    assert!(parse_status_table("x invalid\n".as_bytes()).is_err());
+    assert_eq!(EposStatus::from_code(8), EposStatus::LastGenerationNoHacas);
+    assert_eq!(EposStatus::from_code(29), EposStatus::String);
+    assert_eq!(EposStatus::from_code(4), EposStatus::Other(4));
+    assert!(FinalState::Final.contains(0) && !FinalState::Final.contains(8));
+    assert!(FinalState::NoHacas.contains(8) && !FinalState::NoHacas.contains(0));
+    assert!(FinalState::Core.contains(5) && FinalState::Core.contains(7) && !FinalState::Core.contains(1));
+    assert!(FinalState::All.contains(29));
+}
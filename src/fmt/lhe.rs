@@ -0,0 +1,358 @@
+use std::error::Error;
+
+// Les Houches Event (LHE) format reader and interpreter
+
+use super::error::{columns, ErrorPolicy, ParseError, ParseErrorKind, ParseToken};
+use super::generic::*;
+
+#[derive(Debug)]
+pub struct LHEBlock {
+    /// Block Header
+    pub header: LHEBlockHeader,
+    /// Particles
+    pub event: Vec<LHEParticle>
+}
+
+/// `NUP IDPRUP XWGTUP SCALUP AQEDUP AQCDUP` and optional named weights
+#[derive(Debug, Default, Clone)]
+pub struct LHEBlockHeader {
+    /// Count of particles in event (NUP)
+    pub nout: usize,
+    /// process id (IDPRUP)
+    pub process_id: i32,
+    /// event weight (XWGTUP)
+    pub weight: f64,
+    /// scale, GeV (SCALUP)
+    pub scale: f64,
+    pub alpha_qed: f64,
+    pub alpha_qcd: f64,
+    /// additional weights: `<wgt id='..'>` or `<weights>` entries
+    pub weights: Vec<(String, f64)>,
+}
+
+/// Process description of `<init>` block: `XSECUP XERRUP XMAXUP LPRUP`
+#[derive(Debug, Clone)]
+pub struct LHEProcess {
+    /// cross section, pb
+    pub xsec: f64,
+    pub xsec_error: f64,
+    pub max_weight: f64,
+    pub process_id: i32,
+}
+
+/// `<init>` block:
+/// `IDBMUP1 IDBMUP2 EBMUP1 EBMUP2 PDFGUP1 PDFGUP2 PDFSUP1 PDFSUP2 IDWTUP NPRUP`
+#[derive(Debug, Default)]
+pub struct LHEHeader {
+    pub version: String,
+    /// PDG codes of beams
+    pub beam_ids: (i32, i32),
+    /// beam energies, GeV
+    pub beam_energies: (f64, f64),
+    pub pdf_groups: (i32, i32),
+    pub pdf_sets: (i32, i32),
+    /// weighting strategy (IDWTUP)
+    pub weighting: i32,
+    pub processes: Vec<LHEProcess>,
+}
+
+impl LHEHeader {
+    /// Centre of mass energy of beams, massless approximation
+    pub fn sqrt_s(&self) -> f64 {
+        2.0 * (self.beam_energies.0 * self.beam_energies.1).sqrt()
+    }
+}
+
+/// `IDUP ISTUP MOTHUP1 MOTHUP2 ICOLUP1 ICOLUP2 PX PY PZ E M VTIMUP SPINUP`
+#[derive(Debug)]
+pub struct LHEParticle {
+    pub code: i32,
+    /// ISTUP: -1 incoming, 1 outgoing, 2 intermediate resonance
+    pub status: i32,
+    pub mothers: (i32, i32),
+    pub colors: (i32, i32),
+    pub p: (f64, f64, f64),
+    pub energy: f64,
+    pub mass: f64,
+    /// proper lifetime
+    pub lifetime: f64,
+    /// cosine of spin angle
+    pub spin: f64,
+}
+
+impl<'a> TryFrom<[&'a str; 13]> for LHEParticle {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: [&'a str; 13]) -> Result<Self, Self::Error> {
+        let s = Self {
+            code:     value[0].token()?,
+            status:   value[1].token()?,
+            mothers:  (value[2].token()?, value[3].token()?),
+            colors:   (value[4].token()?, value[5].token()?),
+            p:        (value[6].token()?, value[7].token()?, value[8].token()?),
+            energy:   value[9].token()?,
+            mass:     value[10].token()?,
+            lifetime: value[11].token()?,
+            spin:     value[12].token()?,
+        };
+        Ok(s)
+    }
+}
+
+impl<'a> TryFrom<(LHEBlockHeader, &'a Vec<String>)> for LHEBlock {
+    type Error = Box<dyn Error>;
+    fn try_from(value: (LHEBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let particles = parse_lines(
+            value.1,
+            |line| LHEParticle::try_from( columns::<13>(line)? )
+        )?;
+        Ok(
+            Self {
+                header: value.0,
+                event: particles,
+            }
+        )
+    }
+}
+
+impl<'a> TryFrom<&'a str> for LHEBlockHeader {
+    type Error = Box<dyn Error>;
+
+    /// `NUP IDPRUP XWGTUP SCALUP AQEDUP AQCDUP`
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let toks = columns::<6>(value)?;
+        Ok(Self {
+            nout: toks[0].token()?,
+            process_id: toks[1].token()?,
+            weight: toks[2].token()?,
+            scale: toks[3].token()?,
+            alpha_qed: toks[4].token()?,
+            alpha_qcd: toks[5].token()?,
+            weights: vec![],
+        })
+    }
+}
+
+impl<'a> DataBlock<'a, LHEBlockHeader> for LHEBlock {
+    fn get_header(&self) -> &LHEBlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for LHEBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+
+#[derive(Debug)]
+pub struct LHEDataFile;
+
+#[derive(Debug, Default, PartialEq)]
+enum LHESection {
+    #[default]
+    Outside,
+    /// `<init>`, count of lines read inside
+    Init(usize),
+    /// `<event>`, count of lines read inside
+    Event(usize),
+}
+
+/// Line parser of LHE output
+#[derive(Debug, Default)]
+pub struct LHEParser {
+    header: Option<LHEHeader>,
+    section: LHESection,
+    buf: EventBuffer<LHEBlockHeader>,
+}
+
+impl LHEParser {
+    /// value of xml attribute `name='value'` or `name="value"`
+    fn attribute<'s>(tag: &'s str, name: &str) -> Option<&'s str> {
+        let (_, rest) = tag.split_once(&format!("{}=", name))?;
+        let quote = rest.chars().next()?;
+        rest[1..].split(quote).next()
+    }
+
+    fn parse_weights(hd: &mut LHEBlockHeader, line: &str) -> Result<(), Box<dyn Error>> {
+        if line.starts_with("<wgt") {
+            // <wgt id='1001'> 1.0 </wgt>
+            let id = Self::attribute(line, "id").unwrap_or("").to_owned();
+            let value = line.split_once('>').map(|x| x.1).unwrap_or("")
+                .split('<').next().unwrap_or("").trim();
+            hd.weights.push((id, value.token()?));
+        } else if let Some(rest) = line.strip_prefix("<weights>") {
+            // <weights> 1.0 2.0 </weights>
+            let values = rest.split('<').next().unwrap_or("");
+            for (i, v) in values.split_ascii_whitespace().enumerate() {
+                hd.weights.push((i.to_string(), v.token()?));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BlockParser for LHEParser {
+    type Header = LHEHeader;
+
+    type Block = LHEBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let tr = line.trim();
+        if tr.starts_with("<LesHouchesEvents") {
+            self.header.get_or_insert_with(LHEHeader::default).version =
+                Self::attribute(tr, "version").unwrap_or("").to_owned();
+            return Ok(None);
+        }
+        match self.section {
+            LHESection::Outside => {
+                if tr.starts_with("<init") {
+                    self.header.get_or_insert_with(LHEHeader::default);
+                    self.section = LHESection::Init(0);
+                } else if tr.starts_with("<event") {
+                    self.section = LHESection::Event(0);
+                }
+                Ok(None)
+            },
+            LHESection::Init(n) => {
+                if tr.starts_with("</init") {
+                    self.section = LHESection::Outside;
+                    return Ok(None);
+                }
+                if tr.starts_with("<") || tr.starts_with("#") || tr.is_empty() {
+                    return Ok(None);
+                }
+                let toks: Vec<_> = tr.split_ascii_whitespace().collect();
+                let header = self.header.get_or_insert_with(LHEHeader::default);
+                if n == 0 {
+                    if toks.len() < 10 {
+                        return Err(ParseError::new(ParseErrorKind::MissingColumns { expected: 10, found: toks.len() }).into());
+                    }
+                    header.beam_ids = (toks[0].token()?, toks[1].token()?);
+                    header.beam_energies = (toks[2].token()?, toks[3].token()?);
+                    header.pdf_groups = (toks[4].token()?, toks[5].token()?);
+                    header.pdf_sets = (toks[6].token()?, toks[7].token()?);
+                    header.weighting = toks[8].token()?;
+                } else if toks.len() >= 4 {
+                    header.processes.push(LHEProcess {
+                        xsec: toks[0].token()?,
+                        xsec_error: toks[1].token()?,
+                        max_weight: toks[2].token()?,
+                        process_id: toks[3].token()?,
+                    });
+                }
+                self.section = LHESection::Init(n + 1);
+                Ok(None)
+            },
+            LHESection::Event(n) => {
+                if tr.starts_with("</event") {
+                    self.section = LHESection::Outside;
+                    return self.buf.flush();
+                }
+                if tr.starts_with("<") {
+                    self.buf.update_header(idx, |hd| Self::parse_weights(hd, tr));
+                    return Ok(None);
+                }
+                if tr.starts_with("#") || tr.is_empty() {
+                    return Ok(None);
+                }
+                self.section = LHESection::Event(n + 1);
+                if n == 0 {
+                    return self.buf.start(idx, LHEBlockHeader::try_from(tr));
+                } else if self.buf.len() < self.buf.header().map(|x| x.nout).unwrap_or(0) {
+                    // particle line, extra lines after NUP particles are generator comments
+                    self.buf.push(idx, line);
+                }
+                Ok(None)
+            },
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        line.trim_start().starts_with("<event")
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Particles refer to their mothers by position, so only whole events are selected
+impl EventLayout for LHEParser {
+    fn is_footer(line: &str) -> bool {
+        line.trim_start().starts_with("</LesHouchesEvents")
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for LHEDataFile {
+    type Header = LHEHeader;
+
+    type BlockHeader = LHEBlockHeader;
+
+    type Block = LHEBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, LHEParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+#[test]
+fn test_lhe_stream() {
+    let data = r#"<LesHouchesEvents version="3.0">
+<header>
+<!-- generator info -->
+</header>
+<init>
+  2212  2212  6.500000e+03  6.500000e+03  0  0  247000  247000  -4  1
+  5.0e+01  1.0e-01  5.0e+01  1
+</init>
+<event>
+ 4  1  +2.5e+01  9.1e+01  7.5e-03  1.2e-01
+       21 -1    0    0  501  502 +0.0 +0.0 +1.0e+02 1.0e+02 0.0 0.0 9.0
+       21 -1    0    0  502  503 -0.0 -0.0 -1.0e+02 1.0e+02 0.0 0.0 9.0
+       23  2    1    2    0    0 +0.0 +0.0 +0.0 2.0e+02 9.1e+01 0.0 9.0
+      211  1    3    3    0    0 +1.0 +0.0 +0.0 1.0e+02 0.139 0.0 9.0
+#aMCatNLO 1 2 3
+<rwgt>
+<wgt id='1001'> +2.5e+01 </wgt>
+<wgt id='1002'> +3.0e+01 </wgt>
+</rwgt>
+</event>
+</LesHouchesEvents>
+"#;
+    let mut reader = LHEDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let header = reader.take_header().unwrap();
+    assert_eq!(header.version, "3.0");
+    assert_eq!(header.beam_energies, (6500.0, 6500.0));
+    assert_eq!(header.sqrt_s(), 13000.0);
+    assert_eq!(header.processes[0].xsec, 50.0);
+    let process = &header.processes[0];
+    assert_eq!((process.xsec_error, process.max_weight, process.process_id), (0.1, 50.0, 1));
+    assert_eq!(event.header.weight, 25.0);
+    assert_eq!(event.header.weights[1], ("1002".to_owned(), 30.0));
+    assert_eq!(event.event.len(), 4);
+    assert_eq!(event.event.iter().filter(|x| x.status == 1).count(), 1);
+    assert_eq!((event.event[0].colors, event.event[3].mothers), ((501, 502), (3, 3)));
+    assert_eq!((event.event[3].lifetime, event.event[3].spin), (0.0, 9.0));
+}
@@ -7,6 +7,36 @@ pub mod phqmd;
 
 pub mod decoder;
 
+pub mod database;
+
+pub mod nuclei;
+
+pub mod remap;
+
+pub mod istat;
+
+pub mod selector;
+
+pub mod pdg;
+
 pub mod qgsm;
 
-pub mod hepmc;
\ No newline at end of file
+pub mod hepmc;
+
+pub mod hepmc2;
+
+pub mod oscar2013;
+
+pub mod phsd;
+
+pub mod ampt;
+
+pub mod lhe;
+
+pub mod urqmd;
+
+pub mod detect;
+
+pub mod error;
+
+pub mod cache;
\ No newline at end of file
@@ -0,0 +1,154 @@
+// Nuclei and hypernuclei of PDG codes `10LZZZAAAI` without entries in nuclei list
+
+/// Proton, neutron and Lambda masses (GeV)
+const PROTON_MASS: f64 = 0.938272;
+const NEUTRON_MASS: f64 = 0.939565;
+const LAMBDA_MASS: f64 = 1.115683;
+
+/// Bethe-Weizsäcker coefficients (MeV): volume, surface, Coulomb, asymmetry, pairing
+const A_VOLUME: f64 = 15.75;
+const A_SURFACE: f64 = 17.8;
+const A_COULOMB: f64 = 0.711;
+const A_ASYMMETRY: f64 = 23.7;
+const A_PAIRING: f64 = 11.18;
+
+/// Lambda separation energy `B_inf - C / A^(2/3)` (MeV), fitted to 13C, 89Y and 208Pb hypernuclei
+const LAMBDA_B_INF: f64 = 29.0;
+const LAMBDA_B_SURFACE: f64 = 96.0;
+
+const ELEMENTS: [&str; 118] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl", "Ar",
+    "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As", "Se", "Br", "Kr",
+    "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In", "Sn", "Sb", "Te", "I", "Xe",
+    "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm", "Yb", "Lu",
+    "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl", "Pb", "Bi", "Po", "At", "Rn",
+    "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk", "Cf", "Es", "Fm", "Md", "No", "Lr",
+    "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Nucleus of PDG code `10LZZZAAAI`: charge `Z`, mass number `A` (lambdas included),
+/// count of lambdas `L` and isomer level `I`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NuclearCode {
+    pub anti: bool,
+    pub z: i32,
+    pub a: i32,
+    pub lambdas: i32,
+    pub isomer: i32,
+}
+
+impl NuclearCode {
+    /// Decodes nucleus code, `None` for other codes and for nuclei with more protons and lambdas than nucleons
+    pub fn decode(code: i32) -> Option<Self> {
+        let c = code.abs();
+        if c / 1000000000 != 1 {
+            return None;
+        }
+        let x = Self {
+            anti: code < 0,
+            z: c % 10000000 / 10000,
+            a: c % 10000 / 10,
+            lambdas: c % 100000000 / 10000000,
+            isomer: c % 10,
+        };
+        if x.a == 0 || x.z + x.lambdas > x.a {
+            None
+        } else {
+            Some(x)
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        let c = 1000000000 + self.lambdas * 10000000 + self.z * 10000 + self.a * 10 + self.isomer;
+        if self.anti { -c } else { c }
+    }
+
+    fn sign(&self) -> f64 {
+        if self.anti { -1.0 } else { 1.0 }
+    }
+
+    pub fn charge(&self) -> f64 {
+        self.sign() * self.z as f64
+    }
+
+    pub fn baryon(&self) -> f64 {
+        self.sign() * self.a as f64
+    }
+
+    pub fn strangeness(&self) -> f64 {
+        -self.sign() * self.lambdas as f64
+    }
+
+    /// Isospin projection, `(protons - neutrons) / 2`
+    pub fn isospin3(&self) -> f64 {
+        self.sign() * (2 * self.z - self.a + self.lambdas) as f64 / 2.0
+    }
+
+    /// Name as `3LH`, `4He`, `180m1Ta`, `anti-4He`: mass number, isomer level, one `L` for each lambda, element
+    pub fn name(&self) -> String {
+        let element = match self.z {
+            0 => "n",
+            z => ELEMENTS.get(z as usize - 1).copied().unwrap_or("X"),
+        };
+        format!(
+            "{}{}{}{}{}",
+            if self.anti { "anti-" } else { "" },
+            self.a,
+            if self.isomer > 0 { format!("m{}", self.isomer) } else { String::new() },
+            "L".repeat(self.lambdas as usize),
+            element
+        )
+    }
+
+    /// Bethe-Weizsäcker binding energy of nucleus without lambdas (MeV), not negative
+    fn core_binding(z: i32, a: i32) -> f64 {
+        if a < 2 {
+            return 0.0;
+        }
+        let (zf, af, n) = (z as f64, a as f64, a - z);
+        let pairing = match (z % 2, n % 2) {
+            (0, 0) => A_PAIRING / af.sqrt(),
+            (1, 1) => -A_PAIRING / af.sqrt(),
+            _ => 0.0,
+        };
+        let binding = A_VOLUME * af - A_SURFACE * af.powf(2.0 / 3.0) - A_COULOMB * zf * (zf - 1.0) / af.cbrt()
+            - A_ASYMMETRY * (n - z).pow(2) as f64 / af + pairing;
+        binding.max(0.0)
+    }
+
+    /// Separation energy of lambda from hypernucleus of mass number `a` (MeV), not negative
+    fn lambda_separation(a: i32) -> f64 {
+        (LAMBDA_B_INF - LAMBDA_B_SURFACE / (a as f64).powf(2.0 / 3.0)).max(0.0)
+    }
+
+    /// Mass (GeV) of Bethe-Weizsäcker nucleus of non-strange nucleons and lambdas bound with separation energy.
+    /// Isomer excitation is not known and is ignored
+    pub fn mass(&self) -> f64 {
+        let core = self.a - self.lambdas;
+        let n = core - self.z;
+        let binding = Self::core_binding(self.z, core) + self.lambdas as f64 * Self::lambda_separation(self.a);
+        self.z as f64 * PROTON_MASS + n as f64 * NEUTRON_MASS + self.lambdas as f64 * LAMBDA_MASS - binding / 1000.0
+    }
+}
+
+#[test]
+fn test_nuclear_code() {
+    let h3l = NuclearCode::decode(1010010030).unwrap();
+    assert_eq!((h3l.z, h3l.a, h3l.lambdas, h3l.isomer), (1, 3, 1, 0));
+    assert_eq!((h3l.charge(), h3l.baryon(), h3l.strangeness()), (1.0, 3.0, -1.0));
+    assert_eq!(h3l.name(), "3LH");
+    let anti = NuclearCode::decode(-1000020040).unwrap();
+    assert_eq!((anti.charge(), anti.baryon(), anti.isospin3(), anti.name()), (-2.0, -4.0, 0.0, "anti-4He".to_string()));
+    assert_eq!(NuclearCode::decode(1000731801).unwrap().name(), "180m1Ta");
+    assert_eq!(NuclearCode::decode(1000731801).unwrap().code(), 1000731801);
+    assert_eq!(anti.code(), -1000020040);
+    assert_eq!(NuclearCode::decode(1000030020), None);
+    assert_eq!(NuclearCode::decode(2212), None);
+    // 56Fe is 55.9349 u = 52.103 GeV, 208Pb is 193.729 GeV
+    assert!((NuclearCode::decode(1000260560).unwrap().mass() - 52.103).abs() < 0.02);
+    assert!((NuclearCode::decode(1000822080).unwrap().mass() - 193.729).abs() < 0.05);
+    // 13C(Lambda) is heavier than 12C by Lambda mass without 11.7 MeV separation energy
+    let c13l = NuclearCode::decode(1010060130).unwrap().mass();
+    let c12 = NuclearCode::decode(1000060120).unwrap().mass();
+    assert!((c13l - c12 - LAMBDA_MASS + 0.0117).abs() < 0.002);
+}
@@ -1,10 +1,8 @@
 use std::error::Error;
-use std::io::prelude::*;
-use with_position::{WithPosition, Position};
 
-use super::decoder::EposDict;
 /// OSCAR1999 format reader and interpreter
 
+use super::error::{columns, ErrorPolicy, ParseToken};
 use super::generic::*;
 
 #[derive(Debug)]
@@ -15,12 +13,21 @@ pub struct OSCEposBlock {
     pub event: Vec<OscarParticle>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OSCEposBlockHeader {
     /// Count of particles in OSCAR event description
     pub nout: usize
 }
 
+impl<'a> TryFrom<&'a [&'a str]> for OSCEposBlockHeader {
+    type Error = Box<dyn Error>;
+
+    /// `event nout ...`
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        Ok(Self { nout: value[1].token()? })
+    }
+}
+
 #[derive(Debug)]
 pub struct OSCEposHeader {
     /// Collision Energy
@@ -31,11 +38,7 @@ pub struct OSCEposHeader {
 }
 
 #[derive(Debug)]
-pub struct OSCEposDataFile<'a> {
-    header: OSCEposHeader,
-    events: Vec<OSCEposBlock>,
-    pub decoder: &'a EposDict
-}
+pub struct OSCEposDataFile;
 
 #[derive(Debug)]
 pub struct OscarParticle {
@@ -59,14 +62,14 @@ impl<'a> TryFrom<[&'a str; 12]> for OscarParticle {
 
     fn try_from(value: [&'a str; 12]) -> Result<Self, Self::Error> {
         let s = Self {
-            id:     value[0].parse()?,
-            code:   value[1].parse()?,
-            state:  value[2].parse()?,
-            p:      (value[3].parse()?, value[4].parse()?, value[5].parse()?),
-            p0:     value[6].parse()?,
-            mass:   value[7].parse()?,
-            coords: (value[8].parse()?, value[9].parse()?, value[10].parse()?),
-            time:   value[11].parse()?,
+            id:     value[0].token()?,
+            code:   value[1].token()?,
+            state:  value[2].token()?,
+            p:      (value[3].token()?, value[4].token()?, value[5].token()?),
+            p0:     value[6].token()?,
+            mass:   value[7].token()?,
+            coords: (value[8].token()?, value[9].token()?, value[10].token()?),
+            time:   value[11].token()?,
             
             //echarge: 0.,
             //contains: None,
@@ -78,22 +81,9 @@ impl<'a> TryFrom<[&'a str; 12]> for OscarParticle {
 impl<'a> TryFrom<(OSCEposBlockHeader, &'a Vec<String>)> for OSCEposBlock {
     type Error = Box<dyn Error>;
     fn try_from(value: (OSCEposBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
-        let particles = value.1.iter().try_fold(
-            vec![],
-            |mut v, line| -> Result<Vec<_>, Box<dyn Error>> {
-                let mut iter = line.split_ascii_whitespace();
-                let mut args = [""; 12];
-                [0usize, 1, 2, 3, 4, 5 ,6 ,7 ,8 ,9 ,10, 11].iter()
-                    .try_fold(&mut args, |a, x| {
-                        let v = iter.next();
-                        a[*x] = v?;
-                        Some(a)
-                });
-                v.push(
-                    OscarParticle::try_from( args )?
-                );
-                Ok(v)
-            }
+        let particles = parse_lines(
+            value.1,
+            |line| OscarParticle::try_from( columns::<12>(line)? )
         )?;
         Ok(
             Self {
@@ -110,120 +100,110 @@ impl<'a> DataBlock<'a, OSCEposBlockHeader> for OSCEposBlock {
     }
 }
 
-impl<'a, 'b> GenericDataContainer<'a, 'b> for OSCEposDataFile<'b> {
-    type Header = OSCEposHeader;
+impl ParticleCount for OSCEposBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
 
-    type BlockHeader = OSCEposBlockHeader;
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Line parser of OSC1999A (EPOS) output
+#[derive(Debug, Default)]
+pub struct OSCEposParser {
+    header: Option<OSCEposHeader>,
+    buf: EventBuffer<OSCEposBlockHeader>,
+}
+
+impl BlockParser for OSCEposParser {
+    type Header = OSCEposHeader;
 
     type Block = OSCEposBlock;
 
-    type Decoder = EposDict;
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        if line.starts_with("#") {
+            // COMMENT
+            let cmt = line.strip_prefix("#").unwrap();
+            let s: Vec<_> = cmt.split("nncm").collect();
+            if s.len() >= 2 {
+                self.header = Some (
+                    OSCEposHeader {
+                        snn: s[1].split_ascii_whitespace().next().unwrap_or("").token()?,
+                        event_signature: s[0].trim().to_owned(),
+                    }
+                );
+            }
+            Ok(None)
+            // END COMMENT
+        } else {
+            // DATA OR EMPTY
+            let tr = line.trim();
+            let tokens: Vec<_> = tr.split_ascii_whitespace().collect();
+            match tokens.len() {
+                5 => {
+                    // new event
+                    self.buf.start(idx, OSCEposBlockHeader::try_from(tokens.as_slice()))
+                },
+                0 => {
+                    Ok(None)
+                },
+                _ => {
+                    // line event, short lines are reported by block parsing
+                    self.buf.push(idx, line);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        !line.starts_with('#') && line.split_ascii_whitespace().count() == 5
+    }
 
-    fn get_header(&self) -> &Self::Header {
-        &self.header
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
     }
 
-    fn get_blocks(&self) -> &Vec<Self::Block> {
-        &self.events
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
     }
 
-    fn borrow_blocks(self) -> Vec<Self::Block> {
-        self.events
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
     }
 
-    fn upload<T: Sized + std::io::Read>(data: std::io::BufReader<T>, decoder: &'b Self::Decoder) -> Result<Self, std::io::Error> {
-        match data.lines().with_position().try_fold(
-            (
-                None,
-                None,
-                Vec::<String>::new(),
-                vec![]
-            ),
-            |
-                (mut header, mut bufheader, mut buf, mut events)
-                , (position, _line)| -> Result<_, Box<dyn Error> > {
-                match _line {
-                    Ok(line) => {
-                        if line.starts_with("#") {
-                            // COMMENT
-                            let cmt = line.strip_prefix("#").unwrap();
-                            let s: Vec<_> = cmt.split("nncm").collect();
-                            if s.len() < 2 {
-                                // SKIP
-                                Ok((header, bufheader, buf, events))
-                            } else {
-                                header = Some (
-                                    OSCEposHeader {
-                                        snn: s[1].trim().split_ascii_whitespace().collect::<Vec<_>>().first().unwrap().parse()?,
-                                        event_signature: s[0].trim().to_owned(),
-                                    }
-                                );
-                                Ok((header, bufheader, buf, events))
-                            }
-                            // END COMMENT
-                        } else {
-                            // DATA OR EMPTY
-                            let tr = line.trim();
-                            let tokens: Vec<_> = tr.split_ascii_whitespace().collect();
-                            match tokens.len() {
-                                5 => {
-                                    // new event
-                                    if let Some(hd) = bufheader {
-                                        let block = OSCEposBlock::try_from(
-                                            (hd, &buf)
-                                        )?;
-                                        events.push(block);
-                                        buf.clear();
-                                    }
-                                    bufheader = Some(OSCEposBlockHeader {
-                                        nout: tokens.first().unwrap().parse().unwrap(),
-                                    });
-                                },
-                                (6..) => {
-                                    // line event
-                                    buf.push(line);
-                                    if let Position::Last = position {
-                                        if let Some(hd) = bufheader {
-                                            let block = OSCEposBlock::try_from(
-                                                (hd, &buf)
-                                            )?;
-                                            events.push(block);
-                                            buf.clear();
-                                            bufheader = None;
-                                        }
-                                    }
-                                },
-                                _ => {
-                                    // warn!("Bad line!");
-
-                                }
-                            }
-                            Ok((header, bufheader, buf, events))
-                        }
-                    },
-                    Err(e) => {
-                        Err(Box::new(e))
-                    },
-                }
-            }
-        ) {
-            Ok((a, b, c, events)) => {
-                Ok(
-                    Self {
-                        header: a.unwrap(),
-                        events: events,
-                        decoder: decoder
-                    }
-                )
-            },
-            Err(e) => {
-                Err(std::io::Error::new::<String>(std::io::ErrorKind::InvalidData, e.as_ref().to_string().into()))
-            },
-        }
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Event is its header line and one line per particle
+impl EventLayout for OSCEposParser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((1..lines.len()).filter(|i| {
+            let n = lines[*i].split_ascii_whitespace().count();
+            !lines[*i].starts_with('#') && n != 0 && n != 5
+        }).collect())
     }
 
-    fn push_back(&mut self, mut t: Self) {
-        self.events.append(&mut t.events);
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        lines[0] = replace_token(&lines[0], 1, &n.to_string());
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for OSCEposDataFile {
+    type Header = OSCEposHeader;
+
+    type BlockHeader = OSCEposBlockHeader;
+
+    type Block = OSCEposBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, OSCEposParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
     }
 }
 
@@ -239,128 +219,156 @@ impl<'a, 'b> GenericDataContainer<'a, 'b> for OSCEposDataFile<'b> {
 /// sets STATUS code to =0 to all particles
 /// 
 #[derive(Debug)]
-pub struct OSC97UrQMDDataFile<'a> {
-    header: OSCEposHeader,
-    events: Vec<OSCEposBlock>,
-    pub decoder: &'a EposDict
-}
+pub struct OSC97UrQMDDataFile;
 
 
-impl<'a, 'b> GenericDataContainer<'a, 'b> for OSC97UrQMDDataFile<'b> {
-    type Header = OSCEposHeader;
+/// Line parser of OSC1997A (UrQMD .f19) output
+#[derive(Debug, Default)]
+pub struct OSC97UrQMDParser {
+    header: Option<OSCEposHeader>,
+    buf: EventBuffer<OSCEposBlockHeader>,
+}
 
-    type BlockHeader = OSCEposBlockHeader;
+impl BlockParser for OSC97UrQMDParser {
+    type Header = OSCEposHeader;
 
     type Block = OSCEposBlock;
 
-    type Decoder = EposDict;
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        if idx <= 2 && line.contains("UrQMD") {
+            // COMMENT
+            let cmt = line.trim();
+            let s: Vec<_> = line.split_ascii_whitespace().map(|x| x.trim()).filter(|x| x.len()>0).collect();
+            if s.len() >= 2 {
+                self.header = Some (
+                    OSCEposHeader {
+                        snn: s[s.len() - 1].token()?,
+                        event_signature: cmt.to_owned(),
+                    }
+                );
+            }
+            Ok(None)
+            // END COMMENT
+        } else {
+            // DATA OR EMPTY
+            let tr = line.trim();
+            let mut tokens: Vec<_> = tr.split_ascii_whitespace().filter(|x| x.len() > 0).collect();
+            match tokens.len() {
+                4 => {
+                    // new event
+                    self.buf.start(idx, OSCEposBlockHeader::try_from(tokens.as_slice()))
+                },
+                0 => {
+                    Ok(None)
+                },
+                _ => {
+                    // line event, short lines are reported by block parsing
+                    self.buf.push(idx, {
+                        tokens.insert(2.min(tokens.len()), &"0"); // add STATUS code, bcs .f19 OSCAR1997A UrQMD output files dont do this
+                        tokens.join(" ")
+                    });
+                    Ok(None)
+                }
+            }
+        }
+    }
 
-    fn get_header(&self) -> &Self::Header {
-        &self.header
+    fn is_event_start(idx: usize, _prev: &str, line: &str) -> bool {
+        !(idx <= 2 && line.contains("UrQMD")) && line.split_ascii_whitespace().count() == 4
     }
 
-    fn get_blocks(&self) -> &Vec<Self::Block> {
-        &self.events
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
     }
 
-    fn borrow_blocks(self)  -> Vec<Self::Block> {
-        self.events
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
     }
 
-    fn upload<T: Sized + std::io::Read>(data: std::io::BufReader<T>, decoder: &'b Self::Decoder) -> Result<Self, std::io::Error> {
-        match data.lines().with_position().enumerate().try_fold(
-            (
-                None,
-                None,
-                Vec::<String>::new(),
-                vec![]
-            ),
-            |
-                (mut header, mut bufheader, mut buf, mut events)
-                , (idx, (position, _line))| -> Result<_, Box<dyn Error> > {
-                match _line {
-                    Ok(line) => {
-                        if idx <= 2 && line.contains("UrQMD") {
-                            // COMMENT
-                            let cmt = line.trim();
-                            let s: Vec<_> = line.split_ascii_whitespace().map(|x| x.trim()).filter(|x| x.len()>0).collect();
-                            if s.len() < 2 {
-                                // SKIP
-                                Ok((header, bufheader, buf, events))
-                            } else {
-                                header = Some (
-                                    Self::Header {
-                                        snn: s.last().unwrap().parse()?,
-                                        event_signature: cmt.to_owned(),
-                                    }
-                                );
-                                Ok((header, bufheader, buf, events))
-                            }
-                            // END COMMENT
-                        } else {
-                            // DATA OR EMPTY
-                            let tr = line.trim();
-                            let mut tokens: Vec<_> = tr.split_ascii_whitespace().filter(|x| x.len() > 0).collect();
-                            match tokens.len() {
-                                4 => {
-                                    // new event
-                                    if let Some(hd) = bufheader {
-                                        let block = Self::Block::try_from(
-                                            (hd, &buf)
-                                        )?;
-                                        events.push(block);
-                                        buf.clear();
-                                    }
-                                    bufheader = Some(Self::BlockHeader {
-                                        nout: tokens[1].parse().unwrap(),
-                                    });
-                                },
-                                (5..) => {
-                                    // line event
-                                    buf.push({
-                                        tokens.insert(2, &"0"); // add STATUS code, bcs .f19 OSCAR1997A UrQMD output files dont do this
-                                        tokens.join(" ")
-                                    });
-                                    if let Position::Last = position {
-                                        if let Some(hd) = bufheader {
-                                            let block = Self::Block::try_from(
-                                                (hd, &buf)
-                                            )?;
-                                            events.push(block);
-                                            buf.clear();
-                                        }
-                                        bufheader = None
-                                    }
-                                },
-                                _ => {
-                                    // warn!("Bad line!");
-                                }
-                            }
-                            Ok((header, bufheader, buf, events))
-                        }
-                    },
-                    Err(e) => {
-                        Err(Box::new(e))
-                    },
-                }
-            }
-        ) {
-            Ok((a, b ,c, events)) => {
-                Ok(
-                    Self {
-                        header: a.unwrap(),
-                        events: events,
-                        decoder: decoder
-                    }
-                )
-            },
-            Err(e) => {
-                Err(std::io::Error::new::<String>(std::io::ErrorKind::InvalidData, e.as_ref().to_string().into()))
-            },
-        }
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
     }
 
-    fn push_back(&mut self, mut t: Self) {
-        self.events.append(&mut t.events);
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
     }
 }
+
+/// Event is its header line and one line per particle
+impl EventLayout for OSC97UrQMDParser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((1..lines.len()).filter(|i| {
+            let n = lines[*i].split_ascii_whitespace().count();
+            n != 0 && n != 4
+        }).collect())
+    }
+
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        lines[0] = replace_token(&lines[0], 1, &n.to_string());
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for OSC97UrQMDDataFile {
+    type Header = OSCEposHeader;
+
+    type BlockHeader = OSCEposBlockHeader;
+
+    type Block = OSCEposBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, OSC97UrQMDParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+#[test]
+fn test_epos_stream() {
+    let data = "# OSC1999A\n# (1,1)+(1,1) nncm 7000.0 1\n      0       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  100.0  100.0  0.938  0 0 0 0\n    2    120  1  0.3  0.2  1.0  1.1  0.139  0 0 0 0\n      1       1       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n\n";
+    let mut reader = OSCEposDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let first = reader.next().unwrap().unwrap();
+    assert_eq!(first.event.len(), 2);
+    assert_eq!(reader.take_header().unwrap().snn, 7000.0);
+    let second = reader.next().unwrap().unwrap();
+    assert_eq!(second.event.len(), 1);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn test_epos_error_policy() {
+    let data = "# (1,1)+(1,1) nncm 7000.0 1\n      0       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  1x0.0  100.0  0.938  0 0 0 0\n    2    120  1  0.3  0.2  1.0  1.1  0.139  0 0 0 0\n      1       1       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n";
+    let read = |policy: ErrorPolicy| {
+        let mut reader = OSCEposDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+        reader.set_source("epos.dat");
+        reader.set_policy(policy);
+        let events: Vec<_> = reader.by_ref().collect();
+        (events, reader.skipped())
+    };
+    let (events, _) = read(ErrorPolicy::Fail);
+    let e = events[0].as_ref().unwrap_err().to_string();
+    assert_eq!(e, "epos.dat:3: event 0: bad token \"1x0.0\", expected f64");
+    assert_eq!(events.len(), 1);
+    let (events, skipped) = read(ErrorPolicy::SkipEvent);
+    assert_eq!(events.len(), 1);
+    assert_eq!(skipped, super::error::SkipReport { events: 1, lines: 0 });
+    let (events, skipped) = read(ErrorPolicy::SkipLine);
+    assert_eq!(events.iter().map(|x| x.as_ref().unwrap().event.len()).collect::<Vec<_>>(), vec![1, 1]);
+    assert_eq!(skipped, super::error::SkipReport { events: 0, lines: 1 });
+}
+
+#[test]
+fn test_epos_integrity() {
+    let data = "# (1,1)+(1,1) nncm 7000.0 1\n      0       1       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n      1       3       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n    2    120  1  0.3  0.2  1.0  1.1  0.139  0 0 0 0\n      2       2       0.000  0.000  1\n    1    1120  0  0.1  0.2  3.0  3.2  0.938  0 0 0 0\n";
+    let read = |exclude: bool| {
+        let mut reader = OSCEposDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+        reader.set_exclude_bad(exclude);
+        let events = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        (events.len(), reader.integrity())
+    };
+    let (n, report) = read(false);
+    assert_eq!(n, 3);
+    assert_eq!((report.ok, report.short, report.truncated), (1, 1, 1));
+    let (n, report) = read(true);
+    assert_eq!(n, 1);
+    assert_eq!(report.bad(), 2);
+}
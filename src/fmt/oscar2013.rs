@@ -0,0 +1,687 @@
+use std::error::Error;
+use std::io::{BufWriter, Read, Write};
+
+use super::cache::ToCache;
+use super::database::ParticleDatabase;
+// OSCAR2013 (SMASH particle lists) format reader and interpreter, ASCII and binary
+
+use super::error::{ErrorPolicy, ParseError, ParseErrorKind, ParseToken};
+use super::generic::*;
+
+#[derive(Debug)]
+pub struct OSCAR2013Block {
+    /// Block Header
+    pub header: OSCAR2013BlockHeader,
+    /// Particles
+    pub event: Vec<OSCAR2013Particle>
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OSCAR2013BlockHeader {
+    pub event_id: usize,
+    /// ensemble number (binary format version >= 9)
+    pub ensemble: Option<u32>,
+    /// Count of particles in event description
+    pub nout: usize,
+    /// impact parameter from event end record
+    pub b: Option<f64>,
+    /// empty event flag from event end record:
+    /// projectile and target did not interact
+    pub empty: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+pub struct OSCAR2013Header {
+    /// "OSCAR2013" or "OSCAR2013Extended"
+    pub format: String,
+    /// output content, e.g. "particle_lists"
+    pub content: String,
+    /// generator version, e.g. "SMASH-3.0"
+    pub generator: String,
+    /// binary format version
+    pub format_version: Option<u16>,
+    /// binary format variant: 0 - default, 1 - extended
+    pub format_variant: Option<u16>,
+}
+
+impl OSCAR2013Header {
+    pub fn is_extended(&self) -> bool {
+        self.format.ends_with("Extended") || self.format_variant == Some(1)
+    }
+}
+
+/// t x y z mass p0 px py pz pdg ID charge [extended columns]
+#[derive(Debug, Default)]
+pub struct OSCAR2013Particle {
+    pub time: f64,
+    pub coords: (f64, f64, f64),
+    pub mass: f64,
+    pub p0: f64,
+    pub p: (f64, f64, f64),
+    pub code: i32,
+    pub id: i32,
+    pub charge: i32,
+    /// extended format: count of collisions
+    pub ncoll: Option<i32>,
+    /// extended format: formation time
+    pub formation_time: Option<f64>,
+    /// extended format: pdg codes of mothers
+    pub pdg_mothers: Option<(i32, i32)>,
+}
+
+impl<'a> TryFrom<&'a [&'a str]> for OSCAR2013Particle {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        if value.len() < 12 {
+            return Err(ParseError::new(ParseErrorKind::MissingColumns { expected: 12, found: value.len() }).into());
+        }
+        let extended = value.len() >= 20;
+        let s = Self {
+            time:   value[0].token()?,
+            coords: (value[1].token()?, value[2].token()?, value[3].token()?),
+            mass:   value[4].token()?,
+            p0:     value[5].token()?,
+            p:      (value[6].token()?, value[7].token()?, value[8].token()?),
+            code:   value[9].token()?,
+            id:     value[10].token()?,
+            charge: value[11].token()?,
+            ncoll: if extended { Some(value[12].token()?) } else { None },
+            formation_time: if extended { Some(value[13].token()?) } else { None },
+            pdg_mothers: if extended { Some((value[18].token()?, value[19].token()?)) } else { None },
+        };
+        Ok(s)
+    }
+}
+
+impl<'a> TryFrom<(OSCAR2013BlockHeader, &'a Vec<String>)> for OSCAR2013Block {
+    type Error = Box<dyn Error>;
+    fn try_from(value: (OSCAR2013BlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let particles = parse_lines(
+            value.1,
+            |line| {
+                let args: Vec<_> = line.split_ascii_whitespace().collect();
+                OSCAR2013Particle::try_from( args.as_slice() )
+            }
+        )?;
+        Ok(
+            Self {
+                header: value.0,
+                event: particles,
+            }
+        )
+    }
+}
+
+impl<'a> DataBlock<'a, OSCAR2013BlockHeader> for OSCAR2013Block {
+    fn get_header(&self) -> &OSCAR2013BlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for OSCAR2013Block {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Tokens of event line `event <id> [ensemble <M>] <out|end> ...`: id, ensemble and the rest.
+/// SMASH 2+ writes ensemble number into event lines of ASCII output
+fn event_tokens<'t>(toks: &'t [&'t str]) -> Option<(&'t str, Option<&'t str>, &'t [&'t str])> {
+    match toks {
+        ["event", id, "ensemble", m, rest @ ..] => Some((*id, Some(*m), rest)),
+        ["event", id, rest @ ..] => Some((*id, None, rest)),
+        _ => None,
+    }
+}
+
+/// "# event 0 end 0 impact 2.000 scattering_projectile_target yes" (SMASH 2+)
+/// or "# event 0 end 0 impact 2.000 empty no" (older)
+fn parse_event_end(toks: &[&str], hd: &mut OSCAR2013BlockHeader) -> Result<(), Box<dyn Error>> {
+    if let Some(i) = toks.iter().position(|x| *x == "impact") {
+        hd.b = toks.get(i + 1).map(|x| x.token()).transpose()?;
+    }
+    if let Some(i) = toks.iter().position(|x| *x == "scattering_projectile_target") {
+        hd.empty = toks.get(i + 1).map(|x| *x == "no");
+    } else if let Some(i) = toks.iter().position(|x| *x == "empty") {
+        hd.empty = toks.get(i + 1).map(|x| *x == "yes");
+    }
+    Ok(())
+}
+
+
+/* --------------------------------- ASCII --------------------------------------- */
+
+
+#[derive(Debug)]
+pub struct OSCAR2013DataFile;
+
+/// Line parser of OSCAR2013 ASCII output
+#[derive(Debug, Default)]
+pub struct OSCAR2013Parser {
+    header: Option<OSCAR2013Header>,
+    buf: EventBuffer<OSCAR2013BlockHeader>,
+}
+
+impl BlockParser for OSCAR2013Parser {
+    type Header = OSCAR2013Header;
+
+    type Block = OSCAR2013Block;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        if let Some(format) = line.strip_prefix("#!") {
+            // #!OSCAR2013 particle_lists t x y z mass p0 px py pz pdg ID charge
+            let toks: Vec<_> = format.split_ascii_whitespace().collect();
+            self.header = Some(OSCAR2013Header {
+                format: toks.first().unwrap_or(&"").to_string(),
+                content: toks.get(1).unwrap_or(&"").to_string(),
+                ..Default::default()
+            });
+            Ok(None)
+        } else if let Some(cmt) = line.strip_prefix("#") {
+            let toks: Vec<_> = cmt.split_ascii_whitespace().collect();
+            match event_tokens(&toks) {
+                Some((id, ensemble, ["out", n, ..])) => {
+                    // new event
+                    let header = (|| -> Result<_, Box<dyn Error>> {
+                        Ok(OSCAR2013BlockHeader {
+                            event_id: id.token()?,
+                            ensemble: ensemble.map(|x| x.token()).transpose()?,
+                            nout: n.token()?,
+                            ..Default::default()
+                        })
+                    })();
+                    return self.buf.start(idx, header)
+                },
+                Some((_, _, ["end", ..])) => {
+                    self.buf.update_header(idx, |hd| parse_event_end(&toks, hd));
+                    return self.buf.flush()
+                },
+                _ => {},
+            }
+            match toks.as_slice() {
+                ["Units:", ..] => Ok(None),
+                [generator] => {
+                    if let Some(header) = self.header.as_mut().filter(|x| x.generator.is_empty()) {
+                        header.generator = generator.to_string();
+                    }
+                    Ok(None)
+                },
+                _ => Ok(None),
+            }
+        } else if !line.trim().is_empty() {
+            // particle line
+            self.buf.push(idx, line);
+            Ok(None)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        // # event 0 out 120 or # event 0 ensemble 0 out 120
+        let toks: Vec<_> = line.strip_prefix('#').unwrap_or("").split_ascii_whitespace().collect();
+        matches!(event_tokens(&toks), Some((_, _, ["out", ..])))
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Event is `# event <id> out <n>` line, particle lines and `# event <id> end ...` line
+impl EventLayout for OSCAR2013Parser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((0..lines.len()).filter(|i| !lines[*i].starts_with('#') && !lines[*i].trim().is_empty()).collect())
+    }
+
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        if let Some(k) = lines[0].split_ascii_whitespace().position(|x| x == "out") {
+            lines[0] = replace_token(&lines[0], k + 1, &n.to_string());
+        }
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for OSCAR2013DataFile {
+    type Header = OSCAR2013Header;
+
+    type BlockHeader = OSCAR2013BlockHeader;
+
+    type Block = OSCAR2013Block;
+
+    type Reader<T: std::io::Read> = LineReader<T, OSCAR2013Parser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+
+/* --------------------------------- BINARY --------------------------------------- */
+
+
+/// First binary format version with ensemble number in event end block
+const BIN_ENSEMBLE_END_VERSION: u16 = 9;
+/// First binary format version with event and ensemble numbers in particle block
+/// and baryon number and strangeness in extended particle
+const BIN_EVENT_NUMBER_VERSION: u16 = 10;
+
+/// Streaming reader of SMASH binary OSCAR2013 output.
+///
+/// Layout (little endian):
+/// header `"SMSH" u16:format_version u16:format_variant u32:len [len]u8:smash_version`,
+/// then blocks: `'p'` particles, `'f'` event end, `'i'` interactions (skipped).
+pub struct OSCAR2013BinaryReader<T: Read> {
+    data: std::io::BufReader<T>,
+    header: Option<OSCAR2013Header>,
+    header_read: bool,
+    pending: Option<OSCAR2013Block>,
+    events_read: usize,
+    done: bool,
+    source: Option<String>,
+    integrity: IntegrityCheck,
+}
+
+impl<T: Read> OSCAR2013BinaryReader<T> {
+    pub fn new(data: std::io::BufReader<T>) -> Self {
+        Self {
+            data,
+            header: None,
+            header_read: false,
+            pending: None,
+            events_read: 0,
+            done: false,
+            source: None,
+            integrity: Default::default(),
+        }
+    }
+
+    /// Checks complete event, returns it if it is passed to analysis
+    fn accept(&mut self, block: OSCAR2013Block) -> Option<OSCAR2013Block> {
+        let event = self.integrity.report.ok + self.integrity.report.bad();
+        self.integrity.accept(block, self.done, event, self.source.as_deref())
+    }
+
+    /// Error of event being read, located in the input
+    fn locate(&self, e: std::io::Error) -> std::io::Error {
+        let mut pe = ParseError::format(e.to_string()).at_event(self.events_read);
+        if let Some(src) = &self.source {
+            pe = pe.in_file(src);
+        }
+        std::io::Error::new(e.kind(), pe)
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.data.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_i32(&mut self) -> std::io::Result<i32> {
+        Ok(i32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_f64(&mut self) -> std::io::Result<f64> {
+        Ok(f64::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_header(&mut self) -> std::io::Result<()> {
+        let magic = self.read_bytes::<4>()?;
+        if &magic != b"SMSH" {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Not a SMASH binary file"));
+        }
+        let format_version = self.read_u16()?;
+        let format_variant = self.read_u16()?;
+        let len = self.read_u32()? as usize;
+        let mut version = vec![0u8; len];
+        self.data.read_exact(&mut version)?;
+        self.header = Some(OSCAR2013Header {
+            format: if format_variant == 1 { "OSCAR2013Extended".to_owned() } else { "OSCAR2013".to_owned() },
+            content: "particle_lists".to_owned(),
+            generator: String::from_utf8_lossy(&version).to_string(),
+            format_version: Some(format_version),
+            format_variant: Some(format_variant),
+        });
+        Ok(())
+    }
+
+    fn read_particle(&mut self, version: u16, extended: bool) -> std::io::Result<OSCAR2013Particle> {
+        let mut p = OSCAR2013Particle {
+            time: self.read_f64()?,
+            coords: (self.read_f64()?, self.read_f64()?, self.read_f64()?),
+            mass: self.read_f64()?,
+            p0: self.read_f64()?,
+            p: (self.read_f64()?, self.read_f64()?, self.read_f64()?),
+            code: self.read_i32()?,
+            id: self.read_i32()?,
+            charge: self.read_i32()?,
+            ..Default::default()
+        };
+        if extended {
+            p.ncoll = Some(self.read_i32()?);
+            p.formation_time = Some(self.read_f64()?);
+            let _xsecfac = self.read_f64()?;
+            let _proc_id_origin = self.read_i32()?;
+            let _proc_type_origin = self.read_i32()?;
+            let _time_last_coll = self.read_f64()?;
+            p.pdg_mothers = Some((self.read_i32()?, self.read_i32()?));
+            if version >= BIN_EVENT_NUMBER_VERSION {
+                let _baryon_number = self.read_i32()?;
+                let _strangeness = self.read_i32()?;
+            }
+        }
+        Ok(p)
+    }
+
+    /// Reads next block, returns complete event if any
+    fn read_block(&mut self) -> std::io::Result<Option<OSCAR2013Block>> {
+        let (version, extended) = match &self.header {
+            Some(h) => (h.format_version.unwrap_or(0), h.is_extended()),
+            None => (0, false),
+        };
+        let mut tag = [0u8; 1];
+        if self.data.read(&mut tag)? == 0 {
+            self.done = true;
+            return Ok(self.pending.take());
+        }
+        match tag[0] {
+            b'p' => {
+                let (event_id, ensemble) = if version >= BIN_EVENT_NUMBER_VERSION {
+                    (self.read_u32()? as usize, Some(self.read_u32()?))
+                } else {
+                    (self.events_read, None)
+                };
+                let npart = self.read_u32()? as usize;
+                let mut event = Vec::with_capacity(npart);
+                for _ in 0..npart {
+                    match self.read_particle(version, extended) {
+                        Ok(p) => event.push(p),
+                        // file is cut off inside of event, it is reported as truncated
+                        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                            self.done = true;
+                            break;
+                        },
+                        Err(e) => return Err(e),
+                    }
+                }
+                let block = OSCAR2013Block {
+                    header: OSCAR2013BlockHeader {
+                        event_id,
+                        ensemble,
+                        nout: npart,
+                        ..Default::default()
+                    },
+                    event,
+                };
+                Ok(self.pending.replace(block))
+            },
+            b'f' => {
+                let event_id = self.read_u32()? as usize;
+                let ensemble = if version >= BIN_ENSEMBLE_END_VERSION { Some(self.read_u32()?) } else { None };
+                let b = self.read_f64()?;
+                let empty = self.read_bytes::<1>()?[0] != 0;
+                self.events_read += 1;
+                Ok(self.pending.take().map(
+                    |mut block| {
+                        block.header.event_id = event_id;
+                        block.header.ensemble = ensemble.or(block.header.ensemble);
+                        block.header.b = Some(b);
+                        block.header.empty = Some(empty);
+                        block
+                    }
+                ))
+            },
+            b'i' => {
+                let nin = self.read_u32()?;
+                let nout = self.read_u32()?;
+                let _rho = self.read_f64()?;
+                let _sigma = self.read_f64()?;
+                let _sigma_partial = self.read_f64()?;
+                let _process_type = self.read_u32()?;
+                for _ in 0..(nin + nout) {
+                    self.read_particle(version, extended)?;
+                }
+                Ok(None)
+            },
+            x => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unknown SMASH binary block '{}'", x as char))),
+        }
+    }
+}
+
+impl<T: Read> Iterator for OSCAR2013BinaryReader<T> {
+    type Item = Result<OSCAR2013Block, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.header_read {
+            self.header_read = true;
+            if let Err(e) = self.read_header() {
+                self.done = true;
+                return Some(Err(self.locate(e)));
+            }
+        }
+        while !self.done {
+            match self.read_block() {
+                Ok(Some(block)) => if let Some(block) = self.accept(block) {
+                    return Some(Ok(block));
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(self.locate(e)));
+                },
+            }
+        }
+        // event cut off by the end of file
+        let block = self.pending.take()?;
+        self.accept(block).map(Ok)
+    }
+}
+
+impl<T: Read> EventReader for OSCAR2013BinaryReader<T> {
+    type Header = OSCAR2013Header;
+    type Block = OSCAR2013Block;
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    /// Binary records can not be resynchronized after corruption,
+    /// so error policy is not applied and reading always stops on error
+    fn set_source(&mut self, source: &str) {
+        self.source = Some(source.to_owned());
+    }
+
+    fn set_exclude_bad(&mut self, exclude: bool) {
+        self.integrity.report.excluded = exclude;
+    }
+
+    fn integrity(&self) -> IntegrityReport {
+        self.integrity.report
+    }
+}
+
+#[derive(Debug)]
+pub struct OSCAR2013BinDataFile;
+
+impl<'a> GenericDataContainer<'a> for OSCAR2013BinDataFile {
+    type Header = OSCAR2013Header;
+
+    type BlockHeader = OSCAR2013BlockHeader;
+
+    type Block = OSCAR2013Block;
+
+    type Reader<T: std::io::Read> = OSCAR2013BinaryReader<T>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        OSCAR2013BinaryReader::new(data)
+    }
+}
+
+/// Writer of OSCAR2013 ASCII particle lists.
+/// Only final particles are written; positions missing in the input are written as zeros
+pub struct OSCAR2013Writer<'a, W: Write> {
+    data: BufWriter<W>,
+    codes: CodeMapper<'a>,
+    header_written: bool,
+    events: usize,
+}
+
+impl<'a, W: Write> OSCAR2013Writer<'a, W> {
+    pub fn new(data: W, dict: &'a dyn ParticleDatabase) -> Self {
+        Self {
+            data: BufWriter::new(data),
+            codes: CodeMapper::new(dict),
+            header_written: false,
+            events: 0,
+        }
+    }
+
+    fn write_header(&mut self) -> std::io::Result<()> {
+        self.header_written = true;
+        writeln!(self.data, "#!OSCAR2013 particle_lists t x y z mass p0 px py pz pdg ID charge")?;
+        writeln!(self.data, "# Units: fm fm fm fm GeV GeV GeV GeV GeV none none e")?;
+        writeln!(self.data, "# hega-rs-{}", env!("CARGO_PKG_VERSION"))
+    }
+}
+
+impl<W: Write> EventWriter for OSCAR2013Writer<'_, W> {
+    fn write_event<B: ToCache>(&mut self, block: &B) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        let event_id = block.event_id().unwrap_or(self.events);
+        let particles: Vec<_> = block.particles().into_iter().filter(|x| x.is_final).collect();
+        writeln!(self.data, "# event {} out {}", event_id, particles.len())?;
+        for (i, p) in particles.iter().enumerate() {
+            let (x, y, z, t) = p.position.unwrap_or_default();
+            let (pdg, mass, charge) = (self.codes.pdg(p), self.codes.mass(p), self.codes.charge(p));
+            writeln!(
+                self.data, "{} {} {} {} {} {} {} {} {} {} {} {}",
+                t, x, y, z, mass, p.p0, p.p.0, p.p.1, p.p.2, pdg, i, charge
+            )?;
+        }
+        write!(self.data, "# event {} end 0", event_id)?;
+        if let Some(b) = block.value("b") {
+            write!(self.data, " impact {}", b)?;
+        }
+        if let Some(empty) = block.value("empty") {
+            write!(self.data, " scattering_projectile_target {}", if empty != 0.0 { "no" } else { "yes" })?;
+        }
+        writeln!(self.data)?;
+        self.events += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        self.data.flush()
+    }
+}
+
+#[test]
+fn test_oscar2013_ascii() {
+    let data = "#!OSCAR2013 particle_lists t x y z mass p0 px py pz pdg ID charge
+# Units: fm fm fm fm GeV GeV GeV GeV GeV none none e
+# SMASH-3.0
+# event 0 out 2
+200 1 2 3 0.938 1.2 0.1 0.2 0.7 2212 0 1
+200 1 2 3 0.138 0.2 0.1 0.1 0.0 111 1 0
+# event 0 end 0 impact   7.500 scattering_projectile_target yes
+# event 1 out 0
+# event 1 end 0 impact  12.000 scattering_projectile_target no
+";
+    let mut reader = OSCAR2013DataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let first = reader.next().unwrap().unwrap();
+    let second = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let header = reader.take_header().unwrap();
+    assert_eq!((header.format.as_str(), header.content.as_str(), header.generator.as_str()), ("OSCAR2013", "particle_lists", "SMASH-3.0"));
+    assert_eq!(first.event.len(), 2);
+    assert_eq!(first.event[0].code, 2212);
+    assert_eq!((first.event[1].id, first.event[1].charge), (1, 0));
+    assert_eq!(first.header.b, Some(7.5));
+    assert_eq!(first.header.empty, Some(false));
+    assert_eq!(second.header.empty, Some(true));
+}
+
+#[test]
+fn test_oscar2013_ascii_ensembles() {
+    // SMASH-3 particle_lists.oscar
+    let data = "#!OSCAR2013 particle_lists t x y z mass p0 px py pz pdg ID charge
+# Units: fm fm fm fm GeV GeV GeV GeV GeV none none e
+# SMASH-3.1
+# event 0 ensemble 0 out 2
+200 -3.81683 4.53426 68.1316 0.938 3.25473 -0.180214 0.213681 3.10809 2212 5 1
+200 1.36284 -0.80447 24.3745 0.138 0.356302 0.0578093 -0.0407208 0.322651 111 9 0
+# event 0 ensemble 0 end 0 impact   1.234 scattering_projectile_target yes
+# event 1 ensemble 0 out 0
+# event 1 ensemble 0 end 0 impact   9.000 scattering_projectile_target no
+";
+    let mut reader = OSCAR2013DataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let first = reader.next().unwrap().unwrap();
+    let second = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!((first.header.event_id, first.header.ensemble, first.event.len()), (0, Some(0), 2));
+    assert_eq!((first.header.b, first.header.empty), (Some(1.234), Some(false)));
+    assert_eq!((second.header.event_id, second.header.empty), (1, Some(true)));
+    assert!(OSCAR2013Parser::is_event_start(0, "", "# event 1 ensemble 0 out 0"));
+    assert!(!OSCAR2013Parser::is_event_start(0, "", "# event 1 ensemble 0 end 0 impact   9.000 scattering_projectile_target no"));
+    let mut lines = vec!["# event 0 ensemble 0 out 2".to_string()];
+    OSCAR2013Parser::set_particle_count(&mut lines, 1);
+    assert_eq!(lines[0], "# event 0 ensemble 0 out 1");
+}
+
+#[test]
+fn test_oscar2013_binary() {
+    let mut data: Vec<u8> = vec![];
+    data.extend(b"SMSH");
+    data.extend(7u16.to_le_bytes());
+    data.extend(0u16.to_le_bytes());
+    data.extend(9u32.to_le_bytes());
+    data.extend(b"SMASH-1.8");
+    data.push(b'p');
+    data.extend(1u32.to_le_bytes());
+    [200.0f64, 1.0, 2.0, 3.0, 0.938, 1.2, 0.1, 0.2, 0.7].iter().for_each(|x| data.extend(x.to_le_bytes()));
+    [2212i32, 7, 1].iter().for_each(|x| data.extend(x.to_le_bytes()));
+    data.push(b'f');
+    data.extend(0u32.to_le_bytes());
+    data.extend(3.5f64.to_le_bytes());
+    data.push(0);
+
+    let mut reader = OSCAR2013BinDataFile::reader(std::io::BufReader::new(data.as_slice()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let header = reader.take_header().unwrap();
+    assert_eq!((header.content.as_str(), header.generator.as_str()), ("particle_lists", "SMASH-1.8"));
+    assert_eq!(event.event.len(), 1);
+    assert_eq!((event.event[0].code, event.event[0].id, event.event[0].charge), (2212, 7, 1));
+    assert_eq!(event.event[0].p.2, 0.7);
+    assert_eq!(event.header.b, Some(3.5));
+    assert_eq!(event.header.empty, Some(false));
+}
@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+use std::io::BufRead;
+
+use super::decoder::{EposDict, EposDictParticle};
+use super::error::{ParseError, ParseToken};
+
+// Reader of the PDG Review of Particle Physics mass and width table (`mass_width_<year>.mcd`)
+
+/// Default RPP table, compiled into the binary
+pub const PDG_PARTICLES: &str = include_str!("../../dicts/PDG.particles.txt");
+
+/// Charged leptons and neutrinos of all generations
+const PDG_LEPTONS: [i32; 8] = [11, 12, 13, 14, 15, 16, 17, 18];
+
+/// One line of the table: particle with up to four charge states
+#[derive(Debug, Clone, PartialEq)]
+pub struct PDGRecord {
+    /// Monte Carlo codes of charge states
+    pub ids: Vec<i32>,
+    /// mass, its positive and negative errors (GeV)
+    pub mass: Option<(f64, f64, f64)>,
+    /// width, its positive and negative errors (GeV)
+    pub width: Option<(f64, f64, f64)>,
+    pub name: String,
+    /// charges of states, in order of `ids`
+    pub charges: Vec<String>,
+}
+
+/// Trimmed columns `a..b` (one-based, inclusive) of FORTRAN fixed-column line
+fn field(line: &str, a: usize, b: usize) -> &str {
+    line.get(a - 1..b.min(line.len())).unwrap_or("").trim()
+}
+
+/// Value with errors in columns `a..`: central value (18 columns), positive and negative errors (8 columns each)
+fn value_with_errors(line: &str, a: usize) -> Result<Option<(f64, f64, f64)>, ParseError> {
+    let value = field(line, a, a + 17);
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let err = |x: &str| if x.is_empty() { Ok(0.0) } else { x.token::<f64>() };
+    Ok(Some((value.token()?, err(field(line, a + 19, a + 26))?, err(field(line, a + 28, a + 35))?.abs())))
+}
+
+impl PDGRecord {
+    /// `FORMAT (BN, 4I8, 2(1X,E18.0, 1X,E8.0, 1X,E8.0), 1X,A21)`
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let ids = (0..4).map(|i| field(line, 8 * i + 1, 8 * i + 8)).filter(|x| !x.is_empty()).map(
+            |x| x.token::<i32>()
+        ).collect::<Result<Vec<_>, _>>()?;
+        let name_field: Vec<_> = field(line, 108, 128).split_ascii_whitespace().collect();
+        let (name, charges) = match name_field.as_slice() {
+            [name, .., charges] => (name.to_string(), charges.split(',').map(|x| x.to_string()).collect::<Vec<_>>()),
+            _ => return Err(ParseError::format("no particle name and charges")),
+        };
+        if ids.is_empty() || ids.len() != charges.len() {
+            return Err(ParseError::format(format!("{} particle codes for {} charge states", ids.len(), charges.len())));
+        }
+        Ok(Self {
+            ids,
+            mass: value_with_errors(line, 34)?,
+            width: value_with_errors(line, 71)?,
+            name,
+            charges,
+        })
+    }
+}
+
+/// Charge written as `-`, `0`, `+`, `++` or fraction `-1/3`
+pub fn charge_value(s: &str) -> Result<f64, ParseError> {
+    match s {
+        "0" => Ok(0.0),
+        _ if s.chars().all(|c| c == '+') => Ok(s.len() as f64),
+        _ if s.chars().all(|c| c == '-') => Ok(-(s.len() as f64)),
+        _ => match s.split_once('/') {
+            Some((a, b)) => Ok(a.token::<f64>()? / b.token::<f64>()?),
+            None => Err(ParseError::format(format!("bad charge \"{}\"", s))),
+        },
+    }
+}
+
+/// EPOS quark numbering: PDG has `1 = d, 2 = u`, EPOS `1 = u, 2 = d`
+fn epos_quark(q: i32) -> i32 {
+    match q {
+        1 => 2,
+        2 => 1,
+        _ => q,
+    }
+}
+
+/// Quark flavours of PDG code in form of EPOS `ifl1, ifl2, ifl3`: signed EPOS quark codes, antiquarks are negative.
+/// Baryons have three quarks, mesons quark and antiquark, other particles no quarks
+pub fn flavours(code: i32) -> (i32, i32, i32) {
+    let c = code.abs();
+    let s = code.signum();
+    if c < 10 {
+        // quark
+        return (0, 0, s * epos_quark(c));
+    }
+    if c < 100 {
+        return (0, 0, 0);
+    }
+    let (q1, q2, q3) = (c / 1000 % 10, c / 100 % 10, c / 10 % 10);
+    if q1 != 0 {
+        (s * epos_quark(q1), s * epos_quark(q2), s * epos_quark(q3))
+    } else if q2 % 2 == 0 {
+        // heavier up-type quark of meson is a quark for positive codes (D0 = c ubar)
+        (0, s * epos_quark(q2), -s * epos_quark(q3))
+    } else {
+        // heavier down-type quark is an antiquark for positive codes (K+ = u sbar)
+        (0, -s * epos_quark(q2), s * epos_quark(q3))
+    }
+}
+
+/// Spin degeneracy `2J + 1` of PDG code: the last digit for hadrons (`K0L`, `K0S` with zero last digit are spinless),
+/// 2 for quarks and leptons, 3 for gauge bosons and 1 for Higgs
+pub fn spin_degeneracy(code: i32) -> Option<i32> {
+    match code.abs() {
+        1..=8 | 11..=18 => Some(2),
+        21..=24 => Some(3),
+        25 => Some(1),
+        c if c > 100 && c < 1000000000 => Some((c % 10).max(1)),
+        _ => None,
+    }
+}
+
+impl EposDict {
+    /// Particle database keyed by PDG codes, made of RPP table.
+    /// Every charge state is a particle named with its charge, e.g. `pi+`, `pi0`, `p+`, `Delta(1232)++`
+    pub fn upload_pdg<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self {
+        let mut particles = vec![];
+        data.lines().enumerate().for_each(
+            |(i, s)| {
+                let s = s.unwrap_or_else(|e| panic!("ERROR READING PDG TABLE: {}", e));
+                if s.starts_with('*') || s.trim().is_empty() {
+                    return;
+                }
+                let record = PDGRecord::parse(&s)
+                    .unwrap_or_else(|e| panic!("ERROR READING PDG TABLE: {}", e.at_line(i)));
+                for (code, charge) in record.ids.iter().zip(&record.charges) {
+                    let value = charge_value(charge)
+                        .unwrap_or_else(|e| panic!("ERROR READING PDG TABLE: {}", e.at_line(i)));
+                    let (ifl1, ifl2, ifl3) = flavours(*code);
+                    let name = if charge.contains('/') { record.name.clone() } else { format!("{}{}", record.name, charge) };
+                    particles.push((*code, EposDictParticle {
+                        id_EPOS: None,
+                        id_PDG: Some(*code),
+                        id_QGSJET: None,
+                        id_GHEISHA: None,
+                        id_SIBYLL: None,
+                        name,
+                        ifl1: Some(ifl1),
+                        ifl2: Some(ifl2),
+                        ifl3: Some(ifl3),
+                        counter: None,
+                        // only neutrinos have no mass value
+                        mass: Some(record.mass.map(|x| x.0).unwrap_or(0.0)),
+                        charge: Some(value),
+                        width: record.width.map(|x| x.0),
+                        multiplicity: None,
+                        degeneracy: spin_degeneracy(*code),
+                        status: "PDG".to_string(),
+                        lepton_charge: if PDG_LEPTONS.contains(code) { 1.0 } else { 0.0 },
+                    }));
+                }
+            }
+        );
+        EposDict::from_particles(particles, PDG_LEPTONS.into_iter().collect::<HashSet<_>>())
+    }
+}
+
+#[test]
+fn test_pdg_table() {
+    use crate::anlz::Particle;
+    use super::database::ParticleDatabase;
+    let rho = PDGRecord::parse("     113     213                  7.7526E-01        +2.3E-04 -2.3E-04  1.491E-01         +8.0E-04 -8.0E-04 rho(770)          0,+").unwrap();
+    assert_eq!(rho.ids, vec![113, 213]);
+    assert_eq!(rho.mass, Some((0.77526, 2.3e-4, 2.3e-4)));
+    assert_eq!(rho.width, Some((0.1491, 8.0e-4, 8.0e-4)));
+    assert_eq!((rho.name.as_str(), rho.charges.clone()), ("rho(770)", vec!["0".to_string(), "+".to_string()]));
+    assert_eq!(charge_value("-1/3").unwrap(), -1.0 / 3.0);
+    assert_eq!(charge_value("++").unwrap(), 2.0);
+
+    let dict = EposDict::upload_pdg(std::io::BufReader::new(PDG_PARTICLES.as_bytes()));
+    let p = dict.get(&2212).unwrap();
+    assert_eq!((p.name.as_str(), p.charge, p.b_charge(&())), ("p+", Some(1.0), 1.0));
+    assert!((p.mass.unwrap() - 0.938272).abs() < 1e-6);
+    assert_eq!(dict.get(&-2212).map(|x| x.name.clone()), None);
+    assert_eq!(dict.get(&221).unwrap().b_charge(&()), 0.0);
+    assert_eq!(dict.get(&2224).unwrap().charge, Some(2.0));
+    assert_eq!(dict.get(&12).unwrap().mass, Some(0.0));
+    assert!(dict.is_lepton(&11));
+    assert_eq!(dict.get_particle_code("pi+"), Some(211));
+    assert_eq!(flavours(321), (0, -3, 1));
+    assert_eq!(flavours(2212), (1, 1, 2));
+    assert_eq!((dict.strangeness(321), dict.strangeness(-321), dict.strangeness(3122)), (Some(1.0), Some(-1.0), Some(-1.0)));
+}
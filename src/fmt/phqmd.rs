@@ -1,10 +1,8 @@
 use std::error::Error;
-use std::io::prelude::*;
-use with_position::{WithPosition, Position};
 
-use super::decoder::EposDict;
 /// PHQMD format reader and interpreter
 
+use super::error::{columns, ErrorPolicy, ParseToken};
 use super::generic::*;
 
 #[derive(Debug)]
@@ -15,22 +13,26 @@ pub struct PHQMDBlock {
     pub event: Vec<PHQMDParticle>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PHQMDBlockHeader {
     /// Count of particles in OSCAR event description
     pub nout: usize
 }
 
+impl<'a> TryFrom<&'a [&'a str]> for PHQMDBlockHeader {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        Ok(Self { nout: value[0].token()? })
+    }
+}
+
 #[derive(Debug)]
 pub struct PHQMDHeader {
 }
 
 #[derive(Debug)]
-pub struct PHQMDDataFile<'a> {
-    header: PHQMDHeader,
-    events: Vec<PHQMDBlock>,
-    pub decoder: &'a EposDict
-}
+pub struct PHQMDDataFile;
 
 #[derive(Debug)]
 pub struct PHQMDParticle {
@@ -48,11 +50,11 @@ impl<'a> TryFrom<[&'a str; 9]> for PHQMDParticle {
     fn try_from(value: [&'a str; 9]) -> Result<Self, Self::Error> {
         let s = Self {
             
-            code:   value[0].parse()?,
-            charge: value[1].parse()?,
-            p:      (value[2].parse()?, value[3].parse()?, value[4].parse()?),
-            E:      value[5].parse()?,
-            id:     value[8].parse()?,
+            code:   value[0].token()?,
+            charge: value[1].token()?,
+            p:      (value[2].token()?, value[3].token()?, value[4].token()?),
+            E:      value[5].token()?,
+            id:     value[8].token()?,
         };
         Ok(s)
     }
@@ -61,22 +63,9 @@ impl<'a> TryFrom<[&'a str; 9]> for PHQMDParticle {
 impl<'a> TryFrom<(PHQMDBlockHeader, &'a Vec<String>)> for PHQMDBlock {
     type Error = Box<dyn Error>;
     fn try_from(value: (PHQMDBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
-        let particles = value.1.iter().try_fold(
-            vec![],
-            |mut v, line| -> Result<Vec<_>, Box<dyn Error>> {
-                let mut iter = line.split_ascii_whitespace();
-                let mut args = [""; 9];
-                [0usize, 1, 2, 3, 4, 5 ,6 ,7 ,8].iter()
-                    .try_fold(&mut args, |a, x| {
-                        let v = iter.next();
-                        a[*x] = v?;
-                        Some(a)
-                });
-                v.push(
-                    PHQMDParticle::try_from( args )?
-                );
-                Ok(v)
-            }
+        let particles = parse_lines(
+            value.1,
+            |line| PHQMDParticle::try_from( columns::<9>(line)? )
         )?;
         Ok(
             Self {
@@ -93,108 +82,97 @@ impl<'a> DataBlock<'a, PHQMDBlockHeader> for PHQMDBlock {
     }
 }
 
-impl<'a, 'b> GenericDataContainer<'a, 'b> for PHQMDDataFile<'b> {
-    type Header = PHQMDHeader;
+impl ParticleCount for PHQMDBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
 
-    type BlockHeader = PHQMDBlockHeader;
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Line parser of PHQMD output
+#[derive(Debug, Default)]
+pub struct PHQMDParser {
+    skip: bool,
+    buf: EventBuffer<PHQMDBlockHeader>,
+}
+
+impl BlockParser for PHQMDParser {
+    type Header = PHQMDHeader;
 
     type Block = PHQMDBlock;
 
-    type Decoder = EposDict;
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        if self.skip {
+            self.skip = false;
+            return Ok(None);
+        }
+        // DATA OR EMPTY
+        let tr = line.trim();
+        let tokens: Vec<_> = tr.split_ascii_whitespace().collect();
+        match tokens.len() {
+            5 => {
+                // new event
+                self.skip = true; // skip is for skipping second event-header line on each event (block)
+                self.buf.start(idx, PHQMDBlockHeader::try_from(tokens.as_slice()))
+            },
+            0 => {
+                Ok(None)
+            },
+            _ => {
+                // line event, short lines are reported by block parsing
+                self.buf.push(idx, line);
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_event_start(_idx: usize, prev: &str, line: &str) -> bool {
+        // line after the event header is skipped, it can not start an event
+        line.split_ascii_whitespace().count() == 5 && prev.split_ascii_whitespace().count() != 5
+    }
 
-    fn get_header(&self) -> &Self::Header {
-        &self.header
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
     }
 
-    fn get_blocks(&self) -> &Vec<Self::Block> {
-        &self.events
+    /// PHQMD output has no file header
+    fn take_header(&mut self) -> Option<Self::Header> {
+        Some(PHQMDHeader {})
     }
 
-    fn borrow_blocks(self) -> Vec<Self::Block> {
-        self.events
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
     }
 
-    fn upload<T: Sized + std::io::Read>(data: std::io::BufReader<T>, decoder: &'b Self::Decoder) -> Result<Self, std::io::Error> {
-        let mut dit = data.lines().with_position().enumerate();
-        let header = Some (
-            PHQMDHeader {}
-        );
-        match dit.try_fold(
-            (
-                false,
-                None,
-                Vec::<String>::new(),
-                vec![]
-            ),
-            |
-                ( mut skip, mut bufheader, mut buf, mut events)
-                , (i, (position, _line))| -> Result<_, Box<dyn Error> > {
-                match _line {
-                    Ok(line) => {
-                        if (skip) { Ok((false, bufheader, buf, events)) } else
-                        {
-                            // DATA OR EMPTY
-                            let tr = line.trim();
-                            let tokens: Vec<_> = tr.split_ascii_whitespace().collect();
-                            match tokens.len() {
-                                5 => {
-                                    // new event
-                                    if let Some(hd) = bufheader {
-                                        let block = PHQMDBlock::try_from(
-                                            (hd, &buf)
-                                        )?;
-                                        events.push(block);
-                                        buf.clear();
-                                    }
-                                    bufheader = Some(PHQMDBlockHeader {
-                                        nout: tokens.first().unwrap().parse().unwrap(),
-                                    });
-                                    skip = true; // skip is for skipping second event-header line on each event (block)
-                                },
-                                (6..) => {
-                                    // line event
-                                    buf.push(line);
-                                    if let Position::Last = position {
-                                        if let Some(hd) = bufheader {
-                                            let block = PHQMDBlock::try_from(
-                                                (hd, &buf)
-                                            )?;
-                                            events.push(block);
-                                            buf.clear();
-                                        }
-                                        bufheader = None;
-                                    }
-                                },
-                                _ => {
-                                    // warn!("Bad line!");
-
-                                }
-                            }
-                            Ok((skip, bufheader, buf, events))
-                        }
-                    },
-                    Err(e) => {
-                        Err(Box::new(e))
-                    },
-                }
-            }
-        ) {
-            Ok((_skip, _a, _b , events)) => {
-                Ok(
-                    Self {
-                        header: header.unwrap(),
-                        events: events,
-                        decoder: decoder
-                    }
-                )
-            },
-            Err(e) => {
-                Err(std::io::Error::new::<String>(std::io::ErrorKind::InvalidData, e.as_ref().to_string().into()))
-            },
-        }
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Event is two header lines and one line per particle
+impl EventLayout for PHQMDParser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((2..lines.len()).filter(|i| !lines[*i].trim().is_empty()).collect())
     }
 
-    fn push_back(&mut self, mut t: Self) {
-        self.events.append(&mut t.events);
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        lines[0] = replace_token(&lines[0], 0, &n.to_string());
     }
-}
\ No newline at end of file
+}
+
+impl<'a> GenericDataContainer<'a> for PHQMDDataFile {
+    type Header = PHQMDHeader;
+
+    type BlockHeader = PHQMDBlockHeader;
+
+    type Block = PHQMDBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, PHQMDParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
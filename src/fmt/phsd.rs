@@ -0,0 +1,238 @@
+use std::error::Error;
+
+// PHSD (phsd.dat) format reader and interpreter
+
+use super::error::{columns, ErrorPolicy, ParseToken};
+use super::generic::*;
+
+#[derive(Debug)]
+pub struct PHSDBlock {
+    /// Block Header
+    pub header: PHSDBlockHeader,
+    /// Particles
+    pub event: Vec<PHSDParticle>
+}
+
+/// Two event header lines:
+/// `N ISUB IRUN BIMP IBweight` and
+/// `Np psi2 eps2 psi3 eps3 psi4 eps4 psi5 eps5`
+#[derive(Debug, Default, Clone)]
+pub struct PHSDBlockHeader {
+    /// Count of particles in event
+    pub nout: usize,
+    /// parallel ensemble index
+    pub isub: i32,
+    /// run index
+    pub irun: i32,
+    /// impact parameter, fm
+    pub b: f64,
+    /// impact parameter weight
+    pub b_weight: f64,
+    /// count of participants
+    pub npart: Option<i32>,
+    /// event plane angles and eccentricities (psi_n, eps_n) for n = 2..5
+    pub event_planes: Vec<(f64, f64)>,
+}
+
+#[derive(Debug)]
+pub struct PHSDHeader {
+}
+
+#[derive(Debug)]
+pub struct PHSDDataFile;
+
+/// `ID IDQ PX PY PZ P0 iHist IPI5`
+#[derive(Debug)]
+pub struct PHSDParticle {
+    pub code: i32,
+    pub charge: i32,
+    pub p: (f64, f64, f64),
+    /// off-shell energy
+    pub e: f64,
+}
+
+impl<'a> TryFrom<&'a [&'a str]> for PHSDBlockHeader {
+    type Error = Box<dyn Error>;
+
+    /// first header line
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        Ok(Self {
+            nout: value[0].token()?,
+            isub: value[1].token()?,
+            irun: value[2].token()?,
+            b: value[3].token()?,
+            b_weight: value[4].token()?,
+            ..Default::default()
+        })
+    }
+}
+
+impl PHSDParticle {
+    /// Off-shell mass from E^2 - p^2
+    pub fn offshell_mass(&self) -> f64 {
+        (self.e.powi(2) - self.p.0.powi(2) - self.p.1.powi(2) - self.p.2.powi(2)).max(0.0).sqrt()
+    }
+}
+
+impl<'a> TryFrom<[&'a str; 8]> for PHSDParticle {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: [&'a str; 8]) -> Result<Self, Self::Error> {
+        let s = Self {
+            code:    value[0].token()?,
+            charge:  value[1].token()?,
+            p:       (value[2].token()?, value[3].token()?, value[4].token()?),
+            e:       value[5].token()?,
+            // value[6..=7] are production history and channel
+        };
+        Ok(s)
+    }
+}
+
+impl<'a> TryFrom<(PHSDBlockHeader, &'a Vec<String>)> for PHSDBlock {
+    type Error = Box<dyn Error>;
+    fn try_from(value: (PHSDBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let particles = parse_lines(
+            value.1,
+            |line| PHSDParticle::try_from( columns::<8>(line)? )
+        )?;
+        Ok(
+            Self {
+                header: value.0,
+                event: particles,
+            }
+        )
+    }
+}
+
+impl<'a> DataBlock<'a, PHSDBlockHeader> for PHSDBlock {
+    fn get_header(&self) -> &PHSDBlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for PHSDBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Line parser of PHSD output
+#[derive(Debug, Default)]
+pub struct PHSDParser {
+    /// next line is the second event header line
+    second_header: bool,
+    buf: EventBuffer<PHSDBlockHeader>,
+}
+
+impl BlockParser for PHSDParser {
+    type Header = PHSDHeader;
+
+    type Block = PHSDBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let tr = line.trim();
+        let tokens: Vec<_> = tr.split_ascii_whitespace().collect();
+        if self.second_header {
+            self.second_header = false;
+            self.buf.update_header(idx, |hd| {
+                hd.npart = tokens.first().map(|x| x.token()).transpose()?;
+                hd.event_planes = tokens[1.min(tokens.len())..].chunks_exact(2).map(
+                    |x| -> Result<_, Box<dyn Error>> { Ok((x[0].token()?, x[1].token()?)) }
+                ).collect::<Result<Vec<_>, _>>()?;
+                Ok(())
+            });
+            return Ok(None);
+        }
+        match tokens.len() {
+            5 => {
+                // new event
+                self.second_header = true;
+                self.buf.start(idx, PHSDBlockHeader::try_from(tokens.as_slice()))
+            },
+            0 => {
+                Ok(None)
+            },
+            _ => {
+                // line event, short lines are reported by block parsing
+                self.buf.push(idx, line);
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_event_start(_idx: usize, prev: &str, line: &str) -> bool {
+        // second header line of event may have 5 columns too
+        line.split_ascii_whitespace().count() == 5 && prev.split_ascii_whitespace().count() != 5
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    /// PHSD output has no file header
+    fn take_header(&mut self) -> Option<Self::Header> {
+        Some(PHSDHeader {})
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Event is two header lines and one line per particle
+impl EventLayout for PHSDParser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((2..lines.len()).filter(|i| !lines[*i].trim().is_empty()).collect())
+    }
+
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        lines[0] = replace_token(&lines[0], 0, &n.to_string());
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for PHSDDataFile {
+    type Header = PHSDHeader;
+
+    type BlockHeader = PHSDBlockHeader;
+
+    type Block = PHSDBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, PHSDParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+#[test]
+fn test_phsd_stream() {
+    let data = "     2     1     1   5.5000   1.0000
+   310   0.1   0.05   0.2   0.01   0.0   0.0   0.0   0.0
+  2212   1   0.1   0.2   1.0   1.3900   0   1
+  -211  -1   0.0   0.0   0.0   0.1300   2   15
+     1     2     1   5.5000   1.0000
+   300   0.1   0.05   0.2   0.01   0.0   0.0   0.0   0.0
+   111   0   0.3   0.0   0.4   0.5000   0   0
+";
+    let mut reader = PHSDDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let first = reader.next().unwrap().unwrap();
+    let second = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!(first.header.isub, 1);
+    assert_eq!(first.header.b, 5.5);
+    assert_eq!(first.header.npart, Some(310));
+    assert_eq!(first.header.event_planes.len(), 4);
+    assert_eq!(first.event.len(), 2);
+    assert_eq!(second.header.isub, 2);
+    assert!((second.event[0].offshell_mass() - 0.0).abs() < 1e-12);
+    assert!((first.event[1].offshell_mass() - 0.13).abs() < 1e-12);
+}
@@ -1,10 +1,11 @@
 use std::error::Error;
-use std::io::prelude::*;
-use with_position::{WithPosition, Position};
+use std::io::{BufWriter, Write};
 
-use super::decoder::EposDict;
+use super::cache::ToCache;
+use super::database::ParticleDatabase;
 /// OSCAR1999 format reader and interpreter
 
+use super::error::{columns, ErrorPolicy, ParseError, ParseToken};
 use super::generic::*;
 
 #[derive(Debug)]
@@ -15,7 +16,7 @@ pub struct QGSMBlock {
     pub event: Vec<QGSMParticle>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct QGSMBlockHeader {
     pub event_id: usize,
     /// Count of particles in OSCAR event description
@@ -27,6 +28,20 @@ pub struct QGSMBlockHeader {
     
 }
 
+impl<'a> TryFrom<&'a [&'a str]> for QGSMBlockHeader {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        Ok(Self {
+            event_id: value[0].token()?,
+            nout: value[1].token()?,
+            b: value[2].token()?,
+            bx: value[3].token()?,
+            by: value[4].token()?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct QGSMHeader {
     /// Collision Energy
@@ -51,22 +66,9 @@ pub struct QGSMParticle {
 impl<'a> TryFrom<(QGSMBlockHeader, &'a Vec<String>)> for QGSMBlock {
     type Error = Box<dyn Error>;
     fn try_from(value: (QGSMBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
-        let particles = value.1.iter().try_fold(
-            vec![],
-            |mut v, line| -> Result<Vec<_>, Box<dyn Error>> {
-                let mut iter = line.split_ascii_whitespace();
-                let mut args = [""; 11];
-                [0usize, 1, 2, 3, 4, 5 ,6 ,7 ,8 ,9 ,10].iter()
-                    .try_fold(&mut args, |a, x| {
-                        let v = iter.next();
-                        a[*x] = v?;
-                        Some(a)
-                });
-                v.push(
-                    QGSMParticle::try_from( args )?
-                );
-                Ok(v)
-            }
+        let particles = parse_lines(
+            value.1,
+            |line| QGSMParticle::try_from( columns::<11>(line)? )
         )?;
         Ok(
             Self {
@@ -82,15 +84,15 @@ impl<'a> TryFrom<[&'a str; 11]> for QGSMParticle {
 
     fn try_from(value: [&'a str; 11]) -> Result<Self, Self::Error> {
         let s = Self {
-            charge: value[0].parse()?,
-            lepton_number: value[1].parse()?,
-            strangeness: value[2].parse()?,
-            baryon_number: value[3].parse()?,
-            code: value[4].parse()?,
-            p: (value[5].parse()?, value[6].parse()?, value[7].parse()?),
-            p_lab_z: value[8].parse()?,
+            charge: value[0].token()?,
+            lepton_number: value[1].token()?,
+            strangeness: value[2].token()?,
+            baryon_number: value[3].token()?,
+            code: value[4].token()?,
+            p: (value[5].token()?, value[6].token()?, value[7].token()?),
+            p_lab_z: value[8].token()?,
             // velue[9] is unknown in QGSM data, //TODO
-            mass: value[10].parse()?
+            mass: value[10].token()?
         };
         Ok(s)
     }
@@ -102,135 +104,181 @@ impl<'a> DataBlock<'a, QGSMBlockHeader> for QGSMBlock {
     }
 }
 
+impl ParticleCount for QGSMBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
 
 #[derive(Debug)]
-pub struct QGSMDataFile<'a> {
-    header: QGSMHeader,
-    events: Vec<QGSMBlock>,
-    pub decoder: &'a EposDict
+pub struct QGSMDataFile;
+
+
+/// Line parser of QGSM output
+#[derive(Debug, Default)]
+pub struct QGSMParser {
+    header: Option<QGSMHeader>,
+    buf: EventBuffer<QGSMBlockHeader>,
+}
+
+impl BlockParser for QGSMParser {
+    type Header = QGSMHeader;
+
+    type Block = QGSMBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        if idx <= 3 && line.contains("Results of QGSM") {
+            // COMMENT
+            let cmt = line.trim();
+            self.header = Some (
+                QGSMHeader {
+                    snn: 0.0,
+                    event_signature: cmt.to_owned(),
+                }
+            );
+            Ok(None)
+            // END COMMENT
+        } else if idx <= 3 && line.contains("sqrt(s)=") {
+            let s: f64 = line.split_once("sqrt(s)=")
+                .and_then(|x| x.1.split_once(")"))
+                .ok_or_else(|| ParseError::format("bad sqrt(s) record"))?
+                .0.trim().token()?;
+            self.header.as_mut().ok_or_else(|| ParseError::format("sqrt(s) record before QGSM header"))?.snn = s;
+            Ok(None)
+        } else if idx > 3 {
+            // DATA OR EMPTY
+            let tr = line.trim();
+            let tokens: Vec<_> = tr.split_ascii_whitespace().filter(|x| x.len() > 0).collect();
+            match tokens.len() {
+                5 => {
+                    // new event
+                    self.buf.start(idx, QGSMBlockHeader::try_from(tokens.as_slice()))
+                },
+                0 => {
+                    Ok(None)
+                },
+                _ => {
+                    // line event, short lines are reported by block parsing
+                    self.buf.push(idx, tokens.join(" "));
+                    Ok(None)
+                }
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn is_event_start(idx: usize, _prev: &str, line: &str) -> bool {
+        idx > 3 && line.split_ascii_whitespace().count() == 5
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
 }
 
+/// Event is its header line and one line per particle
+impl EventLayout for QGSMParser {
+    fn particle_lines(lines: &[String]) -> Option<Vec<usize>> {
+        Some((1..lines.len()).filter(|i| {
+            let n = lines[*i].split_ascii_whitespace().count();
+            n != 0 && n != 5
+        }).collect())
+    }
 
-impl<'a, 'b> GenericDataContainer<'a, 'b> for QGSMDataFile<'b> {
+    fn set_particle_count(lines: &mut [String], n: usize) {
+        lines[0] = replace_token(&lines[0], 1, &n.to_string());
+    }
+}
+
+impl<'a> GenericDataContainer<'a> for QGSMDataFile {
     type Header = QGSMHeader;
 
     type BlockHeader = QGSMBlockHeader;
 
     type Block = QGSMBlock;
 
-    type Decoder = EposDict;
+    type Reader<T: std::io::Read> = LineReader<T, QGSMParser>;
 
-    fn get_header(&self) -> &Self::Header {
-        &self.header
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
     }
+}
 
-    fn get_blocks(&self) -> &Vec<Self::Block> {
-        &self.events
-    }
-
-    fn borrow_blocks(self)  -> Vec<Self::Block> {
-        self.events
-    }
-
-    fn upload<T: Sized + std::io::Read>(data: std::io::BufReader<T>, decoder: &'b Self::Decoder) -> Result<Self, std::io::Error> {
-        match data.lines().with_position().enumerate().try_fold(
-            (
-                None,
-                None,
-                Vec::<String>::new(),
-                vec![]
-            ),
-            |
-                (mut header, mut bufheader, mut buf, mut events)
-                , (idx, (position, _line))
-            | -> Result<_, Box<dyn Error> > {
-                match _line {
-                    Ok(line) => {
-                        if idx <= 3 && line.contains("Results of QGSM") {
-                            // COMMENT
-                            let cmt = line.trim();
-                            // let s: Vec<_> = line.split_ascii_whitespace().map(|x| x.trim()).filter(|x| x.len()>0).collect();
-                            header = Some (
-                                    Self::Header {
-                                        snn: 0.0,
-                                        event_signature: cmt.to_owned(),
-                                    }
-                            );
-                            Ok((header, bufheader, buf, events))
-                            // END COMMENT
-                        } else if idx <= 3 && line.contains("sqrt(s)=") {
-                            let s: f64 = line.split_once("sqrt(s)=").unwrap().1.split_once(")").unwrap().0.trim().parse()?;
-                            header.as_mut().unwrap().snn = s;
-                            Ok((header, bufheader, buf, events))
-                        } else if idx > 3 {
-                            // DATA OR EMPTY
-                            let tr = line.trim();
-                            let tokens: Vec<_> = tr.split_ascii_whitespace().filter(|x| x.len() > 0).collect();
-                            match tokens.len() {
-                                5 => {
-                                    
-                                    // new event
-                                    if let Some(hd) = bufheader {
-                                        let block = Self::Block::try_from(
-                                            (hd, &buf)
-                                        )?;
-                                        events.push(block);
-                                        buf.clear();
-                                    }
-                                    bufheader = Some(Self::BlockHeader {                                        
-                                        event_id: tokens[0].parse()?,
-                                        nout: tokens[1].parse()?,
-                                        b: tokens[2].parse()?,
-                                        bx: tokens[3].parse()?,
-                                        by: tokens[4].parse()?,
-                                    });
-                                    
-                                },
-                                (6..) => {
-                                    // line event
-                                    buf.push({
-                                        tokens.join(" ")
-                                    });
-                                    if let Position::Last = position {
-                                        if let Some(hd) = bufheader {
-                                            let block = Self::Block::try_from(
-                                                (hd, &buf)
-                                            )?;
-                                            events.push(block);
-                                            buf.clear();
-                                        }
-                                        bufheader = None;
-                                    }
-                                },
-                                _ => {
-                                    println!("To little symbols in line or empty, skipped..");
-                                }
-                            }
-                            Ok((header, bufheader, buf, events))
-                        } else {Ok((header, bufheader, buf, events))}
-                    },
-                    Err(e) => {
-                        Err(Box::new(e))
-                    },
-                }
-            }
-        ) {
-            Ok((a, b ,c, events)) => {
-                Ok(
-                    Self {
-                        header: a.unwrap(),
-                        events: events,
-                        decoder: decoder
-                    }
-                )
-            },
-            Err(e) => {
-                Err(std::io::Error::new::<String>(std::io::ErrorKind::InvalidData, e.as_ref().to_string().into()))
-            },
+/// Writer of QGSM-style text output.
+/// Only final particles are written; strangeness is taken from dictionary,
+/// lab momentum is the momentum of the input frame
+pub struct QGSMWriter<'a, W: Write> {
+    data: BufWriter<W>,
+    codes: CodeMapper<'a>,
+    header_written: bool,
+    events: usize,
+}
+
+impl<'a, W: Write> QGSMWriter<'a, W> {
+    pub fn new(data: W, dict: &'a dyn ParticleDatabase) -> Self {
+        Self {
+            data: BufWriter::new(data),
+            codes: CodeMapper::new(dict),
+            header_written: false,
+            events: 0,
         }
     }
 
-    fn push_back(&mut self, mut t: Self) {
-        self.events.append(&mut t.events);
+    /// Four header lines, events start from the fifth line
+    fn write_header(&mut self) -> std::io::Result<()> {
+        self.header_written = true;
+        writeln!(self.data, " Results of QGSM-style output, written by hega-rs {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(self.data, " (sqrt(s)= 0.0)")?;
+        writeln!(self.data, " event nout b bx by / charge lepton strangeness baryon code px py pz pz_lab 0 mass")?;
+        writeln!(self.data)
+    }
+}
+
+impl<W: Write> EventWriter for QGSMWriter<'_, W> {
+    fn write_event<B: ToCache>(&mut self, block: &B) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        let particles: Vec<_> = block.particles().into_iter().filter(|x| x.is_final).collect();
+        writeln!(
+            self.data, " {} {} {} {} {}",
+            block.event_id().unwrap_or(self.events), particles.len(),
+            block.value("b").unwrap_or(0.0), block.value("bx").unwrap_or(0.0), block.value("by").unwrap_or(0.0)
+        )?;
+        for p in particles.iter() {
+            let (charge, lepton, baryon) = (self.codes.charge(p), self.codes.lepton(p), self.codes.baryon(p));
+            let (strangeness, pdg, mass) = (self.codes.strangeness(p), self.codes.pdg(p), self.codes.mass(p));
+            writeln!(
+                self.data, " {} {} {} {} {} {} {} {} {} 0 {}",
+                charge, lepton, strangeness, baryon, pdg, p.p.0, p.p.1, p.p.2, p.p.2, mass
+            )?;
+        }
+        self.events += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+        }
+        self.data.flush()
     }
 }
@@ -0,0 +1,103 @@
+use std::io::BufRead;
+
+use super::database::ParticleDatabase;
+use super::decoder::{EposDict, EposDictParticle};
+use super::error::{ParseError, ParseToken};
+
+// Remapping of private generator codes to particles of dictionary
+
+/// Default remap table, compiled into the binary
+pub const CODE_REMAP: &str = include_str!("../../dicts/CODE_REMAP.txt");
+
+/// Particle decoding a private code
+#[derive(Debug, Clone)]
+pub enum RemapTarget {
+    /// particle of dictionary with this PDG code
+    Pdg(i32),
+    /// particle defined in the table
+    Particle(EposDictParticle),
+}
+
+/// Line of remap table: `<format> <code> <target>`
+#[derive(Debug, Clone)]
+pub struct CodeRemap {
+    /// input format name, `*` for all formats
+    pub format: String,
+    pub code: i32,
+    pub target: RemapTarget,
+}
+
+impl CodeRemap {
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let tokens: Vec<_> = line.split_ascii_whitespace().collect();
+        let target = match tokens.len() {
+            3 => RemapTarget::Pdg(tokens[2].token()?),
+            n if n > 3 => RemapTarget::Particle(EposDictParticle::from_str(tokens[2..].join(" "))?),
+            _ => return Err(ParseError::format("expected `<format> <code> <PDG code or particle>`")),
+        };
+        Ok(Self { format: tokens[0].to_string(), code: tokens[1].token()?, target })
+    }
+
+    pub fn applies_to(&self, format: &str) -> bool {
+        self.format == "*" || self.format.eq_ignore_ascii_case(format)
+    }
+}
+
+/// Entries of remap table, lines starting with `!` are comments
+pub fn parse_remap_table(data: impl BufRead) -> Result<Vec<CodeRemap>, ParseError> {
+    let mut entries = vec![];
+    for (i, s) in data.lines().enumerate() {
+        let s = s.map_err(|e| ParseError::format(e.to_string()).at_line(i))?;
+        let s = s.trim();
+        if s.starts_with('!') || s.is_empty() {
+            continue;
+        }
+        entries.push(CodeRemap::parse(s).map_err(|e| e.at_line(i))?);
+    }
+    Ok(entries)
+}
+
+impl EposDict {
+    /// Adds code of `entry` to dictionary as alias of its target.
+    /// Alias of antiparticle missing in table is added as negated code of the particle
+    pub fn remap(&mut self, entry: &CodeRemap) -> Result<(), ParseError> {
+        match &entry.target {
+            RemapTarget::Pdg(pdg) => {
+                let (code, particle) = match self.code_of_pdg(*pdg) {
+                    Some(k) if k < 0 => (-entry.code, self.get(&-k).cloned()),
+                    Some(k) => (entry.code, self.get(&k).cloned()),
+                    None => (entry.code, EposDict::get_nuclei_from_code(*pdg, None, None)),
+                };
+                let particle = particle.ok_or_else(
+                    || ParseError::format(format!("remap target {} of code {} is not in particle table", pdg, entry.code))
+                )?;
+                let is_lepton = self.is_lepton(&self.code_of_pdg(*pdg).unwrap_or(0));
+                self.insert_code(code, particle, is_lepton);
+            },
+            RemapTarget::Particle(particle) => self.insert_code(entry.code, particle.clone(), false),
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_code_remap() {
+    use std::io::BufReader;
+    use super::decoder::{DctCoding, EPOS_PARTICLES, NUCLEI_LIST};
+    let entries = parse_remap_table(BufReader::new(
+        "! comment\nphqmd 100121 1000010020\n* 800001 -2212\nurqmdf19 800002 800002 99 99 99 99 X 1 1 2 0 2.5 1.0 0.1 2 2 R\n".as_bytes()
+    )).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0].applies_to("PHQMD") && !entries[0].applies_to("epos") && entries[1].applies_to("epos"));
+    assert!(parse_remap_table(BufReader::new("phqmd 100121\n".as_bytes())).is_err_and(|e| e.line == Some(0)));
+
+    let mut dict = EposDict::upload(BufReader::new(EPOS_PARTICLES.as_bytes()), DctCoding::PDG, None);
+    dict.upload_nuclei(BufReader::new(NUCLEI_LIST.as_bytes()));
+    entries.iter().for_each(|x| dict.remap(x).unwrap());
+    let dict: &dyn ParticleDatabase = &dict;
+    assert_eq!((dict.charge(100121), dict.baryon(100121), dict.pdg_code(&100121)), (Some(1.0), Some(2.0), Some(1000010020)));
+    assert_eq!((dict.charge(800001), dict.baryon(800001), dict.pdg_code(&800001)), (Some(-1.0), Some(-1.0), Some(-2212)));
+    assert_eq!((dict.charge(800002), dict.baryon(800002), dict.mass(800002)), (Some(1.0), Some(1.0), Some(2.5)));
+    assert!(EposDict::upload(BufReader::new("".as_bytes()), DctCoding::PDG, None)
+        .remap(&CodeRemap::parse("* 1 2212").unwrap()).is_err());
+}
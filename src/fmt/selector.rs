@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use super::database::ParticleDatabase;
+use super::decoder::EposDictParticle;
+use super::nuclei::NuclearCode;
+
+// Particle selections: names, aliases and classes of particles resolved to particle codes of dictionary
+
+/// Common names of particles and their PDG codes
+const ALIASES: [(&str, i32); 44] = [
+    ("p", 2212), ("pbar", -2212), ("n", 2112), ("nbar", -2112),
+    ("pi+", 211), ("pi-", -211), ("pi0", 111),
+    ("K+", 321), ("K-", -321), ("K0", 311), ("K0bar", -311), ("K0S", 310), ("K0L", 130),
+    ("eta", 221), ("eta'", 331), ("rho0", 113), ("omega", 223), ("phi", 333),
+    ("Lambda", 3122), ("Lambdabar", -3122),
+    ("Sigma+", 3222), ("Sigma0", 3212), ("Sigma-", 3112),
+    ("Xi0", 3322), ("Xi-", 3312), ("Xibar+", -3312), ("Omega-", 3334), ("Omegabar+", -3334),
+    ("D0", 421), ("D+", 411), ("D-", -411), ("J/psi", 443),
+    ("e-", 11), ("e+", -11), ("mu-", 13), ("mu+", -13), ("nu_e", 12), ("nu_mu", 14),
+    ("gamma", 22),
+    ("d", 1000010020), ("t", 1000010030), ("He3", 1000020030), ("alpha", 1000020040), ("He4", 1000020040),
+];
+
+/// PDG code of particle with common name `name`
+pub fn alias_pdg(name: &str) -> Option<i32> {
+    ALIASES.iter().find(|(x, _)| *x == name).map(|(_, code)| *code)
+}
+
+/// Predefined class of particles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleClass {
+    /// `charged-hadrons`
+    ChargedHadrons,
+    /// `baryons`, antibaryons are not included
+    Baryons,
+    /// `antibaryons`
+    Antibaryons,
+    /// `strange-hadrons`: hadrons with open strangeness
+    StrangeHadrons,
+    /// `leptons` and antileptons
+    Leptons,
+    /// `photons`
+    Photons,
+    /// `nuclei` of any charge or `nuclei(Z)`, antinuclei are not included
+    Nuclei(Option<i32>),
+}
+
+/// Mesons and baryons: quark and antiquark or three quarks, not diquarks
+fn is_hadron(x: &EposDictParticle) -> bool {
+    let quarks = x.flavours().iter().filter(|&&q| q != 0).count();
+    quarks >= 2 && x.flavours().iter().map(|q| q.signum()).sum::<i32>() % 3 == 0
+}
+
+impl ParticleClass {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "charged-hadrons" => Some(Self::ChargedHadrons),
+            "baryons" => Some(Self::Baryons),
+            "antibaryons" => Some(Self::Antibaryons),
+            "strange-hadrons" => Some(Self::StrangeHadrons),
+            "leptons" => Some(Self::Leptons),
+            "photons" => Some(Self::Photons),
+            "nuclei" => Some(Self::Nuclei(None)),
+            _ => name.strip_prefix("nuclei(")
+                .and_then(|x| x.strip_suffix(')'))
+                .and_then(|z| z.trim().parse().ok())
+                .map(|z| Self::Nuclei(Some(z))),
+        }
+    }
+
+    /// True for particle `code` of dictionary belonging to the class
+    pub fn contains(&self, dict: &dyn ParticleDatabase, code: i32) -> bool {
+        let hadron = || dict.lookup(code).is_some_and(|(x, _)| is_hadron(x));
+        match self {
+            Self::ChargedHadrons => hadron() && dict.charge(code).is_some_and(|q| q != 0.0),
+            Self::Baryons => hadron() && dict.baryon(code) == Some(1.0),
+            Self::Antibaryons => hadron() && dict.baryon(code) == Some(-1.0),
+            Self::StrangeHadrons => hadron() && dict.strangeness(code).is_some_and(|s| s != 0.0),
+            Self::Leptons => dict.lepton(code) != 0.0,
+            Self::Photons => dict.pdg_code(&code) == Some(22),
+            Self::Nuclei(z) => dict.pdg_code(&code).and_then(NuclearCode::decode).is_some_and(
+                |x| !x.anti && z.is_none_or(|z| x.z == z)
+            ),
+        }
+    }
+}
+
+/// Named set of particle codes of dictionary, e.g. `p,pbar` or `baryons`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleCodeSelector {
+    pub name: String,
+    pub codes: HashSet<i32>,
+}
+
+impl ParticleCodeSelector {
+    /// Parses comma-separated items: particle codes, names of table (`-name` for antiparticles),
+    /// common names (`p`, `pbar`, `K0S`, `Lambda`) and classes (`baryons`, `nuclei(2)`).
+    /// Classes contain particles of table, their antiparticles and nuclei of nuclei list
+    pub fn parse(s: &str, dict: &dyn ParticleDatabase) -> Result<Self, String> {
+        let mut codes = HashSet::new();
+        let mut table = None;
+        for item in s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            if let Ok(code) = item.parse::<i32>() {
+                codes.insert(code);
+            } else if let Some(code) = dict.code_by_name(item) {
+                codes.insert(code);
+            } else if let Some(class) = ParticleClass::parse(item) {
+                let table = table.get_or_insert_with(|| {
+                    let codes = dict.table_codes();
+                    let anti: Vec<_> = codes.iter().map(|x| -x).filter(|x| dict.get(x).is_none()).collect();
+                    codes.into_iter().chain(anti).collect::<Vec<_>>()
+                });
+                codes.extend(table.iter().filter(|&&x| class.contains(dict, x)));
+            } else {
+                return Err(format!("unknown particle or class `{}` in selection `{}`", item, s));
+            }
+        }
+        if codes.is_empty() {
+            return Err(format!("no particles in selection `{}`", s));
+        }
+        Ok(Self { name: s.trim().to_string(), codes })
+    }
+}
+
+#[test]
+fn test_particle_selector() {
+    use std::io::BufReader;
+    use super::decoder::{DctCoding, EposDict, EPOS_LEPTONS, EPOS_PARTICLES, NUCLEI_LIST};
+    let leptons = EposDict::upload(BufReader::new(EPOS_LEPTONS.as_bytes()), DctCoding::EPOS, None);
+    let mut dict = EposDict::upload(
+        BufReader::new(EPOS_PARTICLES.as_bytes()),
+        DctCoding::EPOS,
+        Some(leptons.codes().cloned().collect())
+    );
+    dict.upload_nuclei(BufReader::new(NUCLEI_LIST.as_bytes()));
+    let dict: &dyn ParticleDatabase = &dict;
+    assert_eq!((dict.code_by_name("Proton"), dict.code_by_name("-Proton")), (Some(1120), Some(-1120)));
+    assert_eq!((dict.code_by_name("p"), dict.code_by_name("pbar"), dict.code_by_name("Lambda")), (Some(1120), Some(-1120), Some(2130)));
+    let x = ParticleCodeSelector::parse("p, pbar", dict).unwrap();
+    assert_eq!(x.codes, HashSet::from([1120, -1120]));
+    let baryons = ParticleCodeSelector::parse("baryons", dict).unwrap().codes;
+    assert!(baryons.contains(&1120) && baryons.contains(&2130) && !baryons.contains(&-1120) && !baryons.contains(&120));
+    let charged = ParticleCodeSelector::parse("charged-hadrons", dict).unwrap().codes;
+    assert!(charged.contains(&120) && charged.contains(&-120) && !charged.contains(&110) && !charged.contains(&12));
+    let strange = ParticleCodeSelector::parse("strange-hadrons", dict).unwrap().codes;
+    assert!(strange.contains(&130) && strange.contains(&-2130) && !strange.contains(&1120));
+    assert!(ParticleCodeSelector::parse("antibaryons", dict).unwrap().codes.contains(&-1120));
+    assert!(ParticleCodeSelector::parse("leptons", dict).unwrap().codes.contains(&-12));
+    assert_eq!(ParticleCodeSelector::parse("photons", dict).unwrap().codes, HashSet::from([10]));
+    let helium = ParticleCodeSelector::parse("nuclei(2)", dict).unwrap().codes;
+    assert!(helium.contains(&1000020040) && !helium.contains(&1000010020));
+    assert!(ParticleCodeSelector::parse("p,quarkonium", dict).is_err());
+}
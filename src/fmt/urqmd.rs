@@ -0,0 +1,756 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+// UrQMD standard output reader and interpreter:
+// f13 (freeze-out), f14 (final state or time steps), f15 (collision history)
+
+use super::error::{ErrorPolicy, ParseError, ParseErrorKind, ParseToken};
+use super::generic::*;
+
+/// Private code of UrQMD particle missing in `urqmd_to_pdg` table:
+/// `±(99000000 + 100 * |ityp| + iso3 + 10)`, with sign and `iso3` of the particle for antiparticles.
+/// Such codes are decoded with code remap tables
+pub fn urqmd_private_code(ityp: i32, iso3: i32) -> i32 {
+    let sign = if ityp < 0 { -1 } else { 1 };
+    sign * (99000000 + 100 * ityp.abs() + sign * iso3 + 10)
+}
+
+/// PDG code of UrQMD particle `ityp` with doubled isospin projection `iso3`.
+/// Antiparticles have negative `ityp` and opposite `iso3`.
+/// Returns None for particles missing in the table.
+pub fn urqmd_to_pdg(ityp: i32, iso3: i32) -> Option<i32> {
+    if ityp < 0 {
+        return urqmd_to_pdg(-ityp, -iso3).map(|x| -x);
+    }
+    let code = match (ityp, iso3) {
+        // nucleons and N*
+        (1, 1) => 2212,     (1, -1) => 2112,
+        (2, 1) => 12212,    (2, -1) => 12112,
+        (3, 1) => 2124,     (3, -1) => 1214,
+        (4, 1) => 22212,    (4, -1) => 22112,
+        (5, 1) => 32212,    (5, -1) => 32112,
+        (6, 1) => 2216,     (6, -1) => 2116,
+        (7, 1) => 12216,    (7, -1) => 12116,
+        (8, 1) => 22124,    (8, -1) => 21214,
+        (9, 1) => 42212,    (9, -1) => 42112,
+        (10, 1) => 32124,   (10, -1) => 31214,
+        (14, 1) => 2128,    (14, -1) => 1218,
+        // Delta(1232) and Delta*
+        (17, 3) => 2224,    (17, 1) => 2214,    (17, -1) => 2114,   (17, -3) => 1114,
+        (18, 3) => 32224,   (18, 1) => 32214,   (18, -1) => 32114,  (18, -3) => 31114,
+        (19, 3) => 2222,    (19, 1) => 2122,    (19, -1) => 1212,   (19, -3) => 1112,
+        (20, 3) => 12224,   (20, 1) => 12214,   (20, -1) => 12114,  (20, -3) => 11114,
+        (21, 3) => 12222,   (21, 1) => 12122,   (21, -1) => 11212,  (21, -3) => 11112,
+        (22, 3) => 2226,    (22, 1) => 2126,    (22, -1) => 1216,   (22, -3) => 1116,
+        (23, 3) => 22222,   (23, 1) => 22122,   (23, -1) => 21212,  (23, -3) => 21112,
+        (24, 3) => 22224,   (24, 1) => 22214,   (24, -1) => 22114,  (24, -3) => 21114,
+        (25, 3) => 12226,   (25, 1) => 12126,   (25, -1) => 11216,  (25, -3) => 11116,
+        (26, 3) => 2228,    (26, 1) => 2218,    (26, -1) => 2118,   (26, -3) => 1118,
+        // Lambda, Sigma, Xi, Omega
+        (27, 0) => 3122,
+        (28, 0) => 13122,
+        (29, 0) => 3124,
+        (30, 0) => 23122,
+        (31, 0) => 33122,
+        (32, 0) => 13124,
+        (33, 0) => 43122,
+        (34, 0) => 53122,
+        (35, 0) => 3126,
+        (36, 0) => 13126,
+        (37, 0) => 3128,
+        (38, 0) => 23126,
+        (40, 2) => 3222,    (40, 0) => 3212,    (40, -2) => 3112,
+        (41, 2) => 3224,    (41, 0) => 3214,    (41, -2) => 3114,
+        (42, 2) => 13222,   (42, 0) => 13212,   (42, -2) => 13112,
+        (43, 2) => 13224,   (43, 0) => 13214,   (43, -2) => 13114,
+        (44, 2) => 23222,   (44, 0) => 23212,   (44, -2) => 23112,
+        (45, 2) => 3226,    (45, 0) => 3216,    (45, -2) => 3116,
+        (46, 2) => 13226,   (46, 0) => 13216,   (46, -2) => 13116,
+        (47, 2) => 23224,   (47, 0) => 23214,   (47, -2) => 23114,
+        (48, 2) => 3228,    (48, 0) => 3218,    (48, -2) => 3118,
+        (49, 1) => 3322,    (49, -1) => 3312,
+        (50, 1) => 3324,    (50, -1) => 3314,
+        (52, 1) => 13324,   (52, -1) => 13314,
+        (55, 0) => 3334,
+        // mesons
+        (100, 0) => 22,
+        (101, 2) => 211,    (101, 0) => 111,    (101, -2) => -211,
+        (102, 0) => 221,
+        (103, 0) => 223,
+        (104, 2) => 213,    (104, 0) => 113,    (104, -2) => -213,
+        (105, 0) => 9010221,
+        (106, 1) => 321,    (106, -1) => 311,
+        (107, 0) => 331,
+        (108, 1) => 323,    (108, -1) => 313,
+        (109, 0) => 333,
+        (110, 1) => 10321,  (110, -1) => 10311,
+        (111, 2) => 9000211, (111, 0) => 9000111, (111, -2) => -9000211,
+        (112, 0) => 10221,
+        (113, 1) => 10323,  (113, -1) => 10313,
+        (114, 2) => 20213,  (114, 0) => 20113,  (114, -2) => -20213,
+        (115, 0) => 20223,
+        (117, 1) => 325,    (117, -1) => 315,
+        (118, 2) => 215,    (118, 0) => 115,    (118, -2) => -215,
+        (119, 0) => 225,
+        (120, 0) => 335,
+        (121, 1) => 20323,  (121, -1) => 20313,
+        (122, 2) => 10213,  (122, 0) => 10113,  (122, -2) => -10213,
+        (123, 0) => 10223,
+        (124, 0) => 10333,
+        (125, 1) => 100323, (125, -1) => 100313,
+        (126, 2) => 100213, (126, 0) => 100113, (126, -2) => -100213,
+        (127, 0) => 100223,
+        (128, 0) => 100333,
+        (129, 1) => 30323,  (129, -1) => 30313,
+        (130, 2) => 30213,  (130, 0) => 30113,  (130, -2) => -30213,
+        (131, 0) => 30223,
+        // D+ = c dbar, D0 = c ubar
+        (133, 1) => 411,    (133, -1) => 421,
+        (134, 1) => 413,    (134, -1) => 423,
+        (135, 0) => 443,
+        (137, 0) => 100443,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Freeze-out (last collision) point of f13 particle: `frr0 frrx frry frrz frp0 frpx frpy frpz`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrQMDFreezeOut {
+    pub time: f64,
+    pub coords: (f64, f64, f64),
+    pub p0: f64,
+    pub p: (f64, f64, f64),
+}
+
+/// `r0 rx ry rz p0 px py pz m ityp 2*I3 chg lcl# ncl orr`, f13 adds freeze-out point
+#[derive(Debug, Clone)]
+pub struct UrQMDParticle {
+    /// PDG code from `urqmd_to_pdg`, `urqmd_private_code` if UrQMD particle is not in the table
+    pub code: i32,
+    pub ityp: i32,
+    /// doubled isospin projection
+    pub iso3: i32,
+    pub charge: i32,
+    pub time: f64,
+    pub coords: (f64, f64, f64),
+    pub p0: f64,
+    pub p: (f64, f64, f64),
+    pub mass: f64,
+    /// index of last collision partner
+    pub last_partner: i32,
+    /// count of collisions
+    pub ncoll: i32,
+    /// process of particle origin
+    pub parent_process: i32,
+    /// freeze-out point, f13 only
+    pub freeze_out: Option<UrQMDFreezeOut>,
+}
+
+/// Fortran output may use D exponent
+fn fortran_f64(x: &str) -> Result<f64, ParseError> {
+    match x.token() {
+        Ok(v) => Ok(v),
+        Err(e) => x.replace('D', "E").token().map_err(|_| e),
+    }
+}
+
+impl<'a> TryFrom<&'a [&'a str]> for UrQMDParticle {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &'a [&'a str]) -> Result<Self, Self::Error> {
+        if value.len() < 15 {
+            return Err(ParseError::new(ParseErrorKind::MissingColumns { expected: 15, found: value.len() }).into());
+        }
+        let f = fortran_f64;
+        let ityp: i32 = value[9].token()?;
+        let iso3: i32 = value[10].token()?;
+        let s = Self {
+            code: urqmd_to_pdg(ityp, iso3).unwrap_or_else(|| urqmd_private_code(ityp, iso3)),
+            ityp,
+            iso3,
+            charge: value[11].token()?,
+            time: f(value[0])?,
+            coords: (f(value[1])?, f(value[2])?, f(value[3])?),
+            p0: f(value[4])?,
+            p: (f(value[5])?, f(value[6])?, f(value[7])?),
+            mass: f(value[8])?,
+            last_partner: value[12].token()?,
+            ncoll: value[13].token()?,
+            parent_process: value[14].token()?,
+            freeze_out: match value.get(15..23) {
+                Some(x) => Some(UrQMDFreezeOut {
+                    time: f(x[0])?,
+                    coords: (f(x[1])?, f(x[2])?, f(x[3])?),
+                    p0: f(x[4])?,
+                    p: (f(x[5])?, f(x[6])?, f(x[7])?),
+                }),
+                None => None,
+            },
+        };
+        Ok(s)
+    }
+}
+
+/// Event header of UrQMD output, repeated before each event
+#[derive(Debug, Default, Clone)]
+pub struct UrQMDHeader {
+    pub version: String,
+    /// (A, Z) of projectile
+    pub projectile: (i32, i32),
+    /// (A, Z) of target
+    pub target: (i32, i32),
+    pub snn: f64,
+    pub e_lab: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct UrQMDBlockHeader {
+    pub event_id: usize,
+    /// impact parameter, fm
+    pub b: f64,
+    /// output time of the snapshot, fm/c
+    pub time: f64,
+    /// Count of particles in event description
+    pub nout: usize,
+    /// collision counters line written before particles
+    pub collision_counters: Vec<i64>,
+}
+
+/// Snapshot of an event (f13, f14)
+#[derive(Debug)]
+pub struct UrQMDBlock {
+    /// Block Header
+    pub header: UrQMDBlockHeader,
+    /// Particles
+    pub event: Vec<UrQMDParticle>
+}
+
+impl<'a> TryFrom<(UrQMDBlockHeader, &'a Vec<String>)> for UrQMDBlock {
+    type Error = Box<dyn Error>;
+    fn try_from(value: (UrQMDBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let particles = parse_lines(
+            value.1,
+            |line| {
+                let args: Vec<_> = line.split_ascii_whitespace().collect();
+                UrQMDParticle::try_from( args.as_slice() )
+            }
+        )?;
+        Ok(
+            Self {
+                header: value.0,
+                event: particles,
+            }
+        )
+    }
+}
+
+impl<'a> DataBlock<'a, UrQMDBlockHeader> for UrQMDBlock {
+    fn get_header(&self) -> &UrQMDBlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for UrQMDBlock {
+    fn declared_count(&self) -> Option<usize> {
+        Some(self.header.nout)
+    }
+
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Event header lines of UrQMD output.
+/// Returns true if line belongs to event header
+fn parse_event_header_line(line: &str, header: &mut UrQMDHeader, event: &mut UrQMDBlockHeader) -> Result<bool, Box<dyn Error>> {
+    let toks: Vec<_> = line.split_ascii_whitespace().collect();
+    let value_after = |name: &str| -> Option<&str> {
+        toks.iter().position(|x| *x == name).and_then(|i| toks.get(i + 1).cloned())
+    };
+    let f = fortran_f64;
+    match toks.first() {
+        Some(&"UQMD") => {
+            header.version = value_after("version:").unwrap_or("").to_owned();
+            Ok(true)
+        },
+        Some(&"projectile:") => {
+            // projectile:  (mass, char)  197  79   target:  (mass, char)  197  79
+            let nums: Vec<i32> = toks.iter().filter_map(|x| x.parse().ok()).collect();
+            if nums.len() >= 4 {
+                header.projectile = (nums[0], nums[1]);
+                header.target = (nums[2], nums[3]);
+            }
+            Ok(true)
+        },
+        Some(x) if x.starts_with("impact_parameter") => {
+            event.b = toks.get(1).map(|x| f(x)).transpose()?.unwrap_or(0.0);
+            Ok(true)
+        },
+        Some(&"equation_of_state:") => {
+            if let Some(e) = value_after("E_lab(GeV/u):") { header.e_lab = f(e)?; }
+            if let Some(s) = value_after("sqrt(s)(GeV):") { header.snn = f(s)?; }
+            Ok(true)
+        },
+        Some(&"event#") => {
+            event.event_id = toks.get(1).map(|x| x.token()).transpose()?.unwrap_or(0);
+            Ok(true)
+        },
+        Some(&"transformation") | Some(&"op") | Some(&"pa") | Some(&"pvec:") => Ok(true),
+        _ => Ok(false)
+    }
+}
+
+
+/* --------------------------------- f13, f14 --------------------------------------- */
+
+
+#[derive(Debug, Default, PartialEq)]
+enum UrQMDSnapshotState {
+    /// expecting "N time" line
+    #[default]
+    Counts,
+    /// expecting collision counters line
+    Counters,
+    /// reading particle lines
+    Particles,
+}
+
+/// Line parser of UrQMD f13 / f14 output.
+/// Each output time step of an event is a separate block
+#[derive(Debug, Default)]
+pub struct UrQMDParser {
+    header: Option<UrQMDHeader>,
+    event: UrQMDBlockHeader,
+    state: UrQMDSnapshotState,
+    buf: EventBuffer<UrQMDBlockHeader>,
+}
+
+impl BlockParser for UrQMDParser {
+    type Header = UrQMDHeader;
+
+    type Block = UrQMDBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let header = self.header.get_or_insert_with(UrQMDHeader::default);
+        if parse_event_header_line(&line, header, &mut self.event)? {
+            // header of the next event
+            self.state = UrQMDSnapshotState::Counts;
+            return self.buf.flush();
+        }
+        let toks: Vec<_> = line.split_ascii_whitespace().collect();
+        if toks.is_empty() {
+            return Ok(None);
+        }
+        match self.state {
+            UrQMDSnapshotState::Counts => {
+                let (event_id, b) = (self.event.event_id, self.event.b);
+                let header = (|| -> Result<_, Box<dyn Error>> {
+                    Ok(UrQMDBlockHeader {
+                        event_id,
+                        b,
+                        nout: toks[0].token()?,
+                        time: toks.get(1).map(|x| fortran_f64(x)).transpose()?.unwrap_or(0.0),
+                        collision_counters: vec![],
+                    })
+                })();
+                self.state = UrQMDSnapshotState::Counters;
+                self.buf.start(idx, header)
+            },
+            UrQMDSnapshotState::Counters => {
+                self.buf.update_header(idx, |hd| {
+                    hd.collision_counters = toks.iter().map(|x| x.token()).collect::<Result<Vec<_>, _>>()?;
+                    Ok(())
+                });
+                let nout = self.buf.header().map(|x| x.nout).unwrap_or(0);
+                self.state = if nout > 0 { UrQMDSnapshotState::Particles } else { UrQMDSnapshotState::Counts };
+                Ok(None)
+            },
+            UrQMDSnapshotState::Particles => {
+                self.buf.push(idx, line);
+                if self.buf.len() >= self.buf.header().map(|x| x.nout).unwrap_or(0) {
+                    self.state = UrQMDSnapshotState::Counts;
+                    return self.buf.flush();
+                }
+                Ok(None)
+            },
+        }
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        line.starts_with("UQMD")
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Event may hold several time steps, so only whole events are selected
+impl EventLayout for UrQMDParser {}
+
+/// UrQMD f13 / f14 output file
+#[derive(Debug)]
+pub struct UrQMDDataFile;
+
+impl<'a> GenericDataContainer<'a> for UrQMDDataFile {
+    type Header = UrQMDHeader;
+
+    type BlockHeader = UrQMDBlockHeader;
+
+    type Block = UrQMDBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, UrQMDParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+
+/* --------------------------------- f15 --------------------------------------- */
+
+
+/// Interaction record of f15:
+/// `nin nout process collision_number time sqrt_s sigma_tot sigma_partial density`
+#[derive(Debug, Default)]
+pub struct UrQMDCollision {
+    pub nin: i32,
+    pub nout: i32,
+    pub process: i32,
+    pub number: i32,
+    pub time: f64,
+    pub sqrt_s: f64,
+    pub sigma_tot: f64,
+    pub sigma_partial: f64,
+    pub density: f64,
+    /// (index in particle array, particle)
+    pub incoming: Vec<(usize, UrQMDParticle)>,
+    /// (index in particle array, particle)
+    pub outgoing: Vec<(usize, UrQMDParticle)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrQMDCollisionBlockHeader {
+    pub event_id: usize,
+    /// impact parameter, fm
+    pub b: f64,
+    /// Count of final particles
+    pub nout: usize,
+}
+
+/// Collision history of an event (f15).
+/// `event` is the final state obtained by applying all collisions to the initial particles
+#[derive(Debug)]
+pub struct UrQMDCollisionBlock {
+    /// Block Header
+    pub header: UrQMDCollisionBlockHeader,
+    /// Initial particles (record with nin = -1)
+    pub initial: Vec<UrQMDParticle>,
+    /// Collisions and decays in time order
+    pub collisions: Vec<UrQMDCollision>,
+    /// Final particles
+    pub event: Vec<UrQMDParticle>,
+}
+
+impl UrQMDCollisionBlock {
+    /// Particles existing at `time`, fm/c
+    pub fn particles_at(&self, time: f64) -> Vec<&UrQMDParticle> {
+        let mut state: BTreeMap<usize, &UrQMDParticle> = self.initial.iter().enumerate().map(|(i, x)| (i + 1, x)).collect();
+        self.collisions.iter().take_while(|c| c.time <= time).for_each(
+            |c| {
+                c.incoming.iter().for_each(|(i, _)| { state.remove(i); });
+                c.outgoing.iter().for_each(|(i, p)| { state.insert(*i, p); });
+            }
+        );
+        state.into_values().collect()
+    }
+}
+
+fn parse_indexed_particle(line: &str) -> Result<(usize, UrQMDParticle), Box<dyn Error>> {
+    let toks: Vec<_> = line.split_ascii_whitespace().collect();
+    if toks.len() < 16 {
+        return Err(ParseError::new(ParseErrorKind::MissingColumns { expected: 16, found: toks.len() }).into());
+    }
+    Ok((toks[0].token()?, UrQMDParticle::try_from(&toks[1..])?))
+}
+
+/// Particle line of f15 record, `what` names the record in errors
+fn next_indexed_particle<'l>(lines: &mut impl Iterator<Item = (usize, &'l String)>, what: &str) -> Result<(usize, UrQMDParticle), Box<dyn Error>> {
+    let (i, line) = lines.next().ok_or_else(|| ParseError::format(format!("Unexpected end of UrQMD f15 {}", what)))?;
+    Ok(parse_indexed_particle(line).map_err(|e| ParseError::from_boxed(e).at_event_line(i))?)
+}
+
+impl<'a> TryFrom<(UrQMDCollisionBlockHeader, &'a Vec<String>)> for UrQMDCollisionBlock {
+    type Error = Box<dyn Error>;
+
+    /// lines: interaction records followed by their particle lines
+    fn try_from(value: (UrQMDCollisionBlockHeader, &'a Vec<String>)) -> Result<Self, Self::Error> {
+        let mut header = value.0;
+        let mut initial = vec![];
+        let mut collisions = vec![];
+        let mut lines = value.1.iter().enumerate();
+        while let Some((i, line)) = lines.next() {
+            let toks: Vec<_> = line.split_ascii_whitespace().collect();
+            if toks.len() < 2 {
+                continue;
+            }
+            let record = || -> Result<UrQMDCollision, ParseError> {
+                let f = |i: usize| toks.get(i).map(|x| fortran_f64(x)).transpose().map(|x| x.unwrap_or(0.0));
+                let (nin, nout) = (toks[0].token()?, toks[1].token()?);
+                if nin < 0 {
+                    // initial state record: `-1 N 0 b 0 0 0 0`
+                    return Ok(UrQMDCollision { nin, nout, ..Default::default() });
+                }
+                Ok(UrQMDCollision {
+                    nin,
+                    nout,
+                    process: toks.get(2).map(|x| x.token()).transpose()?.unwrap_or(0),
+                    number: toks.get(3).map(|x| x.token()).transpose()?.unwrap_or(0),
+                    time: f(4)?,
+                    sqrt_s: f(5)?,
+                    sigma_tot: f(6)?,
+                    sigma_partial: f(7)?,
+                    density: f(8)?,
+                    incoming: vec![],
+                    outgoing: vec![],
+                })
+            };
+            let mut collision = record().map_err(|e| e.at_event_line(i))?;
+            if collision.nin < 0 {
+                // initial state
+                for _ in 0..collision.nout {
+                    initial.push(next_indexed_particle(&mut lines, "initial state")?.1);
+                }
+                continue;
+            }
+            for _ in 0..collision.nin {
+                collision.incoming.push(next_indexed_particle(&mut lines, "collision")?);
+            }
+            for _ in 0..collision.nout {
+                collision.outgoing.push(next_indexed_particle(&mut lines, "collision")?);
+            }
+            collisions.push(collision);
+        }
+        let mut block = Self {
+            header: UrQMDCollisionBlockHeader { ..header },
+            initial,
+            collisions,
+            event: vec![],
+        };
+        block.event = block.particles_at(f64::INFINITY).into_iter().cloned().collect();
+        header = block.header;
+        header.nout = block.event.len();
+        block.header = header;
+        Ok(block)
+    }
+}
+
+impl<'a> DataBlock<'a, UrQMDCollisionBlockHeader> for UrQMDCollisionBlock {
+    fn get_header(&self) -> &UrQMDCollisionBlockHeader {
+        &self.header
+    }
+}
+
+impl ParticleCount for UrQMDCollisionBlock {
+    fn particle_count(&self) -> usize {
+        self.event.len()
+    }
+}
+
+/// Line parser of UrQMD f15 output
+#[derive(Debug, Default)]
+pub struct UrQMDCollisionParser {
+    header: Option<UrQMDHeader>,
+    event: UrQMDBlockHeader,
+    buf: EventBuffer<UrQMDCollisionBlockHeader>,
+}
+
+impl BlockParser for UrQMDCollisionParser {
+    type Header = UrQMDHeader;
+
+    type Block = UrQMDCollisionBlock;
+
+    fn parse_line(&mut self, idx: usize, line: String) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        let header = self.header.get_or_insert_with(UrQMDHeader::default);
+        if parse_event_header_line(&line, header, &mut self.event)? {
+            return if line.starts_with("UQMD") { self.buf.flush() } else { Ok(None) };
+        }
+        if !line.trim().is_empty() {
+            if !self.buf.is_open() {
+                // event header is complete at its first record
+                let hd = UrQMDCollisionBlockHeader { event_id: self.event.event_id, b: self.event.b, nout: 0 };
+                self.buf.start::<Self::Block>(idx, Ok(hd))?;
+            }
+            self.buf.push(idx, line);
+        }
+        Ok(None)
+    }
+
+    fn is_event_start(_idx: usize, _prev: &str, line: &str) -> bool {
+        line.starts_with("UQMD")
+    }
+
+    fn finish(&mut self) -> Result<Option<Self::Block>, Box<dyn Error>> {
+        self.buf.flush()
+    }
+
+    fn take_header(&mut self) -> Option<Self::Header> {
+        self.header.take()
+    }
+
+    fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.buf.policy = policy;
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.buf.skipped_lines
+    }
+}
+
+/// Collision records are selected with their whole event
+impl EventLayout for UrQMDCollisionParser {}
+
+/// UrQMD f15 collision history file
+#[derive(Debug)]
+pub struct UrQMDCollisionDataFile;
+
+impl<'a> GenericDataContainer<'a> for UrQMDCollisionDataFile {
+    type Header = UrQMDHeader;
+
+    type BlockHeader = UrQMDCollisionBlockHeader;
+
+    type Block = UrQMDCollisionBlock;
+
+    type Reader<T: std::io::Read> = LineReader<T, UrQMDCollisionParser>;
+
+    fn reader<T: Sized + std::io::Read>(data: std::io::BufReader<T>) -> Self::Reader<T> {
+        LineReader::new(data)
+    }
+}
+
+#[test]
+fn test_urqmd_f14_time_steps() {
+    use crate::anlz::Particle;
+    let data = "UQMD   version:       30400   1000  30400  output_file  14
+projectile:  (mass, char)  197  79   target:  (mass, char)  197  79
+transformation betas (NN,lab,pro)     0.0000000  0.9999559 -0.9999559
+impact_parameter_real/min/max(fm):    8.50  0.00 10.00  total_cross_section(mbarn):    6157.52
+equation_of_state:    0  E_lab(GeV/u):  0.2131E+05  sqrt(s)(GeV):  0.2000E+03  p_lab(GeV/u):  0.2131E+05
+event#         7 random seed:    12345 (auto)   total_time(fm/c):         200 Delta(t)_O(fm/c):   100.000
+op  0    0    0    0    0    0    0    0    0    0    0    0    0    0    0
+pa  0.1000E+01  0.5200E+00  0.2000E+01  0.3000E+00  0.0000E+00  0.3700E+00  0.0000E+00
+pvec: r0              rx              ry              rz              p0              px              py              pz              m          ityp 2i3 chg lcl#  ncl or
+
+2 100
+0 0 0 0 0 0 0 0
+ 0.1E+03  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  1  1  0  1  20
+ 0.1E+03  0.1E+01  0.2E+01  0.3E+01  0.2E+00  0.1E+00  0.0E+00  0.0E+00  0.138E+00  -106  -1  -1  0  0  20
+2 200
+3 1 2 0 0 0 0 0
+ 0.2E+03  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  -1  -1  -1  0  1  20
+ 0.2E+03  0.1E+01  0.2E+01  0.3E+01  0.2E+01  0.1E+00  0.2E+00  0.3E+00  0.187E+01  133  1  1  0  0  20
+";
+    let mut reader = UrQMDDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let first = reader.next().unwrap().unwrap();
+    let second = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let header = reader.take_header().unwrap();
+    assert_eq!(header.projectile, (197, 79));
+    assert_eq!(header.snn, 200.0);
+    assert_eq!(first.header.event_id, 7);
+    assert_eq!(first.header.b, 8.5);
+    assert_eq!(first.header.time, 100.0);
+    assert_eq!(first.event[0].code, 2212);
+    assert_eq!(first.event[1].code, -321);
+    assert_eq!(second.header.time, 200.0);
+    assert_eq!(second.header.collision_counters[0], 3);
+    assert_eq!(second.event[0].code, -2212);
+    // D+
+    assert_eq!(second.event[1].code, 411);
+    // isospin is taken from the file, not from dictionary
+    let dict = super::decoder::EposDict::from_particles([], Default::default());
+    let dict: &dyn super::database::ParticleDatabase = &dict;
+    assert_eq!((second.event[1].isospin3(dict), first.event[1].isospin3(dict)), (0.5, -0.5));
+    // D0bar = anti-(c ubar)
+    assert_eq!(urqmd_to_pdg(-133, 1), Some(-421));
+    // N(1440), Delta(1232)-, Delta(1600)++
+    assert_eq!((urqmd_to_pdg(2, 1), urqmd_to_pdg(17, -3), urqmd_to_pdg(18, 3)), (Some(12212), Some(1114), Some(32224)));
+    // K*(892)0, K*0(1430)+, anti-K*(892)-
+    assert_eq!((urqmd_to_pdg(108, -1), urqmd_to_pdg(110, 1), urqmd_to_pdg(-108, -1)), (Some(313), Some(10321), Some(-323)));
+    // Delta(1950)-, Sigma(1775)+, a1(1260)-, D*0
+    assert_eq!((urqmd_to_pdg(26, -3), urqmd_to_pdg(45, 2), urqmd_to_pdg(114, -2), urqmd_to_pdg(134, -1)), (Some(1118), Some(3226), Some(-20213), Some(423)));
+    // particles missing in the table keep ityp and iso3 in private codes
+    assert_eq!((urqmd_private_code(116, 0), urqmd_private_code(-51, -1)), (99011610, -99005111));
+    assert_eq!((first.event[0].last_partner, first.event[0].ncoll, first.event[0].parent_process), (0, 1, 20));
+    assert!(first.event[0].freeze_out.is_none());
+}
+
+#[test]
+fn test_urqmd_f13_freeze_out() {
+    let data = "UQMD   version:       30400   1000  30400  output_file  13
+projectile:  (mass, char)  197  79   target:  (mass, char)  197  79
+impact_parameter_real/min/max(fm):    2.00  0.00 10.00  total_cross_section(mbarn):    6157.52
+equation_of_state:    0  E_lab(GeV/u):  0.2131E+05  sqrt(s)(GeV):  0.2000E+03  p_lab(GeV/u):  0.2131E+05
+event#         1 random seed:    12345 (auto)   total_time(fm/c):         200 Delta(t)_O(fm/c):   200.000
+pvec: r0              rx              ry              rz              p0              px              py              pz              m          ityp 2i3 chg lcl#  ncl or
+
+1 200
+0 0 0 0 0 0 0 0
+ 0.2E+03  0.1E+01  0.2E+01  0.3E+01  0.2E+01  0.1E+00  0.2E+00  0.3E+00  0.1E+01  116  0  0  5  3  15  0.5E+02  0.4E+01  0.5E+01  0.6E+01  0.2E+01  0.1E+00  0.2E+00  0.3E+00
+";
+    let mut reader = UrQMDDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let p = &event.event[0];
+    assert_eq!((p.code, p.last_partner, p.ncoll, p.parent_process), (urqmd_private_code(116, 0), 5, 3, 15));
+    assert_eq!(p.freeze_out, Some(UrQMDFreezeOut { time: 50.0, coords: (4.0, 5.0, 6.0), p0: 2.0, p: (0.1, 0.2, 0.3) }));
+}
+
+#[test]
+fn test_urqmd_f15_history() {
+    let data = "UQMD   version:       30400   1000  30400  output_file  14
+projectile:  (mass, char)  197  79   target:  (mass, char)  197  79
+transformation betas (NN,lab,pro)     0.0000000  0.9999559 -0.9999559
+impact_parameter_real/min/max(fm):    8.50  0.00 10.00  total_cross_section(mbarn):    6157.52
+equation_of_state:    0  E_lab(GeV/u):  0.2131E+05  sqrt(s)(GeV):  0.2000E+03  p_lab(GeV/u):  0.2131E+05
+event#         7 random seed:    12345 (auto)   total_time(fm/c):         200 Delta(t)_O(fm/c):   100.000
+op  0    0    0    0    0    0    0    0    0    0    0    0    0    0    0
+pa  0.1000E+01  0.5200E+00  0.2000E+01  0.3000E+00  0.0000E+00  0.3700E+00  0.0000E+00
+pvec: r0              rx              ry              rz              p0              px              py              pz              m          ityp 2i3 chg lcl#  ncl or
+
+-1 2 0 8.5 0 0 0 0
+1  0.0E+00  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  1  1  0  0  0
+2  0.0E+00  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  -1  0  0  0  0
+2 3 22 1 1.5 2.5 40.0 10.0 0.1
+1  0.1E+01  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  1  1  0  0  0
+2  0.1E+01  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  -1  0  0  0  0
+1  0.1E+01  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  1  1  2  1  22
+2  0.1E+01  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.938E+00  1  1  1  1  1  22
+3  0.1E+01  0.1E+01  0.2E+01  0.3E+01  0.1E+01  0.1E+00  0.2E+00  0.3E+00  0.138E+00  101  -2  -1  1  1  22
+";
+    let mut reader = UrQMDCollisionDataFile::reader(std::io::BufReader::new(data.as_bytes()));
+    let event = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    assert_eq!(event.initial.len(), 2);
+    assert_eq!(event.collisions.len(), 1);
+    let c = &event.collisions[0];
+    assert_eq!((c.process, c.number, c.time, c.sqrt_s), (22, 1, 1.5, 2.5));
+    assert_eq!((c.sigma_tot, c.sigma_partial, c.density), (40.0, 10.0, 0.1));
+    assert_eq!((c.outgoing[0].1.last_partner, c.outgoing[0].1.ncoll, c.outgoing[2].1.parent_process), (2, 1, 22));
+    assert_eq!(event.header.nout, 3);
+    assert_eq!(event.particles_at(1.0).len(), 2);
+    assert_eq!(event.event.iter().map(|x| x.charge).sum::<i32>(), 1);
+}
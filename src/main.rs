@@ -3,18 +3,20 @@ mod fmt;
 mod custom_criteria;
 mod cli;
 mod api_macro;
+mod convert;
+mod skim;
 
 use api_macro::*;
 use cli::*;
 use std::{
-    collections::HashSet, f64::consts::PI, fs::File, io::{BufReader, Write}
+    collections::HashSet, f64::consts::PI, fs::File, io::Write
 };
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use clap::{Parser, *};
 use std::time::SystemTime;
 
 use anlz::{fncs::lab_momentum, DistributionCritetia, HEPEventAnalyzer, ScalarCriteria, StandardCriteria, StandardDistributionCriteria};
-use fmt::{decoder::EposDict, generic::GenericDataContainer, oscar::OSCEposBlock, phqmd::PHQMDBlock, qgsm::QGSMDataFile, hepmc::HepMCDataFile, hepmc::HepMCBlock};
+use fmt::{generic::GenericDataContainer, oscar::OSCEposBlock, phqmd::PHQMDBlock, qgsm::QGSMDataFile, hepmc::HepMCDataFile, hepmc::HepMCBlock, hepmc2::HepMC2DataFile, oscar2013::{OSCAR2013DataFile, OSCAR2013BinDataFile}, phsd::PHSDDataFile, ampt::AMPTDataFile, lhe::LHEDataFile, urqmd::{UrQMDDataFile, UrQMDCollisionDataFile}, cache::CacheDataFile};
 use crate::{anlz::{HEPEvent, StandardDistributionCriteraDefiner}, fmt::{oscar::OSC97UrQMDDataFile, phqmd::PHQMDDataFile}};
 use crate::anlz::ParticleListCompiler;
 use crate::fmt::oscar::OSCEposDataFile;
@@ -31,95 +33,146 @@ const NU_CNT: usize = 2000;
 
 
 fn main() {
-    let args = cli::Args::parse();
+    let mut args = cli::Args::parse();
+    args.ftype = Some(
+        fmt::detect::resolve_type(args.ftype.as_ref(), &args.filenames).unwrap_or_else(
+            |e| { eprintln!("{}", e); std::process::exit(1) }
+        )
+    );
+
+    // cache is analysed with dictionary of its source format
+    let dict_type = if *args.input_type() == AcceptedTypes::Cache {
+        if args.target.contains(&CalcTarget::Cache) {
+            eprintln!("Input is already a cache");
+            std::process::exit(1)
+        }
+        fmt::cache::cache_source(&args.filenames).unwrap_or_else(
+            |e| { eprintln!("{}", e); std::process::exit(1) }
+        )
+    } else {
+        args.input_type().clone()
+    };
+    if args.final_state.is_epos_only() && dict_type != AcceptedTypes::EPOS {
+        eprintln!(
+            "--final-state {} is defined by EPOS statuses and is available for EPOS input only",
+            clap::ValueEnum::to_possible_value(&args.final_state).map(|x| x.get_name().to_string()).unwrap_or_default()
+        );
+        std::process::exit(1)
+    }
+    let table = generate_dictionary(&dict_type, &args).unwrap_or_else(
+        |e| { eprintln!("{}", e); std::process::exit(1) }
+    );
+    let dict = table.as_ref();
 
-    let dict = generate_dictionary(&args.ftype);
+    if let Some(cli::Command::Convert { to }) = &args.command {
+        convert::run_convert(&args, dict, *to);
+        return;
+    }
+    if let Some(cli::Command::Skim { select, keep_codes, final_only }) = &args.command {
+        let filter = skim::SkimFilter { select, keep_codes: keep_codes.iter().copied().collect(), final_only: *final_only, dict };
+        skim::run_skim(&args, &filter);
+        return;
+    }
 
     // ANALYSER
 
     // println!("{:?}", args.filename);
     let calc_target = args.target.iter().collect::<HashSet<_>>();
     println!(">>>> {:?}", calc_target);
+
+    // `--particles` selections
+    let selections = args.particles.iter().map(|x| fmt::selector::ParticleCodeSelector::parse(x, dict))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) });
+    let criteria = [
+        StandardCriteria::FinEnergy,
+        StandardCriteria::ECharge,
+        StandardCriteria::BCharge,
+        StandardCriteria::LCharge,
+        StandardCriteria::FinCnt,
+        StandardCriteria::FinChargedCnt,
+
+        StandardCriteria::PseudorapidityFilterCnt(-0.5, 0.5),
+        StandardCriteria::PseudorapidityFilterCnt(-1.0, 1.0),
+        StandardCriteria::PseudorapidityFilterCnt(-1.5, 1.5),
+
+        StandardCriteria::PseudorapidityFilterCnt(3.5, 5.8),
+        StandardCriteria::PseudorapidityFilterCnt(-5.8, -3.5),
+        StandardCriteria::PseudorapidityFilterCnt(4.4, 5.8),
+        StandardCriteria::PseudorapidityFilterCnt(-5.8, -4.4),
+    ].into_iter().chain(args.criteria.iter().cloned()).chain(
+        selections.iter().map(|x| StandardCriteria::ParticleSetCounter(x.name.clone(), x.codes.clone()))
+    ).collect::<Vec<_>>();
+    let selected_nu = selections.iter().map(
+        |x| (format!("N(Nu, [{}])", x.name.replace('/', "_")), x.codes.clone(), NU_MIN, NU_MAX, NU_CNT)
+    ).collect::<Vec<_>>();
     
     let start = SystemTime::now();
     let (scalar_results, distr_results, list_resutls) =  {
         run_criteria_list!(
             &args,
-            &dict,
+            dict,
             &calc_target,
-            vec![
-                &StandardCriteria::FinEnergy,
-                &StandardCriteria::ECharge,
-                &StandardCriteria::BCharge,
-                &StandardCriteria::LCharge,
-                &StandardCriteria::FinCnt,
-                &StandardCriteria::FinChargedCnt,
-
-                &StandardCriteria::PseudorapidityFilterCnt(-0.5, 0.5),
-                &StandardCriteria::PseudorapidityFilterCnt(-1.0, 1.0),
-                &StandardCriteria::PseudorapidityFilterCnt(-1.5, 1.5),
-
-                &StandardCriteria::PseudorapidityFilterCnt(3.5, 5.8),
-                &StandardCriteria::PseudorapidityFilterCnt(-5.8, -3.5),
-                &StandardCriteria::PseudorapidityFilterCnt(4.4, 5.8),
-                &StandardCriteria::PseudorapidityFilterCnt(-5.8, -4.4),
-            ],
+            criteria.iter().map(|x| x as &dyn ScalarCriteria<'_, _, _>).collect(),
             vec![
                 ParticleListCompiler::new( {
-                    let code = dict.get_particle_code("Proton").unwrap();
+                    let code = dict.code_of_pdg(2212).unwrap();
                     let mut set = HashSet::new();
                     set.insert(-code);
                     set
                 }),
                 ParticleListCompiler::new( {
-                    let code = dict.get_particle_code("Proton").unwrap();
+                    let code = dict.code_of_pdg(2212).unwrap();
                     let mut set = HashSet::new();
                     set.insert(code);
                     set
                 }),
-            ],
+            ].into_iter().chain(
+                selections.iter().map(|x| ParticleListCompiler::named(x.name.clone(), x.codes.clone()))
+            ).collect(),
+            &selected_nu,
             [
                 ( StandardDistributionCriteraDefiner::PdirTheta, DEG_MIN, DEG_MAX, DEG_CNT, "N(Theta_p)".to_string() ),
                 ( StandardDistributionCriteraDefiner::PNu, NU_MIN, NU_MAX, NU_CNT, "N(Nu)".to_string() ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [p])".to_string(), arg=( {
-                    let code = dict.get_particle_code("Proton").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(2212).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [~p])".to_string(), arg=( {
-                    let code = dict.get_particle_code("Proton").unwrap();
-                    vec![-code]
+                    let code = dict.code_of_pdg(2212).unwrap();
+                    HashSet::from([-code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [n])".to_string(), arg=( {
-                    let code = dict.get_particle_code("Neutron").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(2112).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [~n])".to_string(), arg=( {
-                    let code = dict.get_particle_code("Neutron").unwrap();
-                    vec![-code]
+                    let code = dict.code_of_pdg(2112).unwrap();
+                    HashSet::from([-code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [pi0])".to_string(), arg=( {
-                    let code = dict.get_particle_code("pi0").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(111).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [pi+])".to_string(), arg=( {
-                    let code = dict.get_particle_code("pi+").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(211).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [pi-])".to_string(), arg=( {
-                    let code = dict.get_particle_code("pi-").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(-211).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [K+])".to_string(), arg=( {
-                    let code = dict.get_particle_code("K+").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(321).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [K-])".to_string(), arg=( {
-                    let code = dict.get_particle_code("K-").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(-321).unwrap();
+                    HashSet::from([code])
                 }, ) ),
                 ( StandardDistributionCriteraDefiner::PNu_selected, NU_MIN, NU_MAX, NU_CNT, "N(Nu, [K0])".to_string(), arg=( {
-                    let code = dict.get_particle_code("K0").unwrap();
-                    vec![code]
+                    let code = dict.code_of_pdg(311).unwrap();
+                    HashSet::from([code])
                 }, ) )
             ]
         )
@@ -143,7 +196,7 @@ fn main() {
             format!(
                 "# hega-rs ver.{} statistics: \n#{:?} in Lab: {}\n",
                 VERSION,
-                args.ftype,
+                args.input_type(),
                 false // args.lab
             ).as_bytes()
         ).unwrap();
@@ -163,7 +216,7 @@ fn main() {
                         "# hega-rs ver.{} distribution : {}; total-items={}\n lbin;\t rbin;\t value\n#{:?} in Lab: {}\n",
                         VERSION,
                         pref, size,
-                        args.ftype,
+                        args.input_type(),
                         false // args.lab
                     ).as_bytes()
                 ).unwrap();
@@ -189,23 +242,44 @@ fn main() {
 
     if calc_target.contains(&CalcTarget::ParticleList) {
         let suff = args.o.clone();
+        // particle codes in `--output-codes` numbering
+        let label = |code: i32| match args.output_codes {
+            Some(to) => dict.translate(code, to).map(|x| x.to_string()).unwrap_or("NA".to_string()),
+            None => code.to_string(),
+        };
         list_resutls.iter().for_each(
             |list_res| {
-                let pref = format!("Particles({:?})", {
-                    let mut v = list_res.id_filter.iter().collect::<Vec<_>>();
-                    v.sort();
-                    v
-                } );
+                let pref = match &list_res.name {
+                    // names like `J/psi` are not valid in file names
+                    Some(name) => format!("Particles([{}])", name.replace('/', "_")),
+                    None => format!("Particles([{}])", {
+                        let mut v = list_res.id_filter.iter().collect::<Vec<_>>();
+                        v.sort();
+                        v.into_iter().map(|x| label(*x)).collect::<Vec<_>>().join(", ")
+                    } ),
+                };
                 let mut f = File::create(format!("{}{}-{}", sysprx, pref, suff)).unwrap();
                 f.write(
                     format!(
                         "# hega-rs ver.{} particle compilation : {}; total-items={}\n#\t source{:?} in Lab: {}\n",
                         VERSION,
                         pref, list_res.data.len(),
-                        args.ftype,
+                        args.input_type(),
                         false // args.lab
                     ).as_bytes()
                 ).unwrap();
+                // table records of listed particles
+                let mut codes = list_res.id_filter.iter().collect::<Vec<_>>();
+                codes.sort();
+                let records = codes.into_iter().map(
+                    |x| format!(
+                        "#\t {}: {}, width={}\n",
+                        label(*x),
+                        dict.name(*x).unwrap_or("NA".to_string()),
+                        dict.width(*x).map(|w| w.to_string()).unwrap_or("NA".to_string())
+                    )
+                ).collect::<String>();
+                f.write_all(records.as_bytes()).unwrap();
                 f.write(
                     "id;\tmass;\tcharge;\tp;\tbeta;\n".as_bytes()
                 ).unwrap();
@@ -214,7 +288,7 @@ fn main() {
                     "".to_string(),
                     |x, d| {
                         x + &format!(
-                            "{};\t{};\t{};\t{};\t{};\n", d.id, d.mass, d.q_charge ,d.p, d.beta
+                            "{};\t{};\t{};\t{};\t{};\n", label(d.id), d.mass, d.q_charge ,d.p, d.beta
                         )
                     }
                 );
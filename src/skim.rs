@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::SystemTime;
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::anlz::{EventSelection, HEPEvent, Particle};
+use crate::cli::{AcceptedTypes, Args};
+use crate::fmt::{
+    ampt::AMPTParser, database::ParticleDatabase, error::{ErrorPolicy, ParseError},
+    generic::{BlockParser, EventLayout, ParticleCount},
+    hepmc::HepMCParser, hepmc2::HepMC2Parser, lhe::LHEParser,
+    oscar::{OSC97UrQMDParser, OSCEposParser}, oscar2013::OSCAR2013Parser,
+    phqmd::PHQMDParser, phsd::PHSDParser, qgsm::QGSMParser,
+    urqmd::{UrQMDCollisionParser, UrQMDParser},
+};
+
+// Skimming: copies events passing selections into a file of the input format
+
+/// Suffix appended to input path of skimmed file
+pub const SKIM_SUFFIX: &str = ".skim";
+
+/// Event selections and particle filters of `skim`
+pub struct SkimFilter<'a> {
+    pub select: &'a [EventSelection],
+    /// kept particle codes, all if empty
+    pub keep_codes: HashSet<i32>,
+    pub final_only: bool,
+    pub dict: &'a (dyn ParticleDatabase + 'static),
+}
+
+impl SkimFilter<'_> {
+    fn has_particle_filter(&self) -> bool {
+        self.final_only || !self.keep_codes.is_empty()
+    }
+
+    fn passes<B: HEPEvent>(&self, block: &B) -> bool
+    where B::P: Particle<Decoder = dyn ParticleDatabase> + 'static
+    {
+        self.select.iter().all(|x| x.passes(block, self.dict))
+    }
+
+    fn keeps<T: Particle<Decoder = dyn ParticleDatabase>>(&self, p: &T) -> bool {
+        (!self.final_only || p.is_final(self.dict))
+            && (self.keep_codes.is_empty() || self.keep_codes.contains(&p.code(self.dict)))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SkimCounts {
+    pub read: usize,
+    pub written: usize,
+    pub skipped: usize,
+}
+
+/// Raw event lines of input with parsed blocks
+struct Skimmer<'a, P: BlockParser, W: Write> {
+    filter: &'a SkimFilter<'a>,
+    out: W,
+    policy: ErrorPolicy,
+    exclude_bad: bool,
+    source: Option<&'a str>,
+    counts: SkimCounts,
+    _parser: std::marker::PhantomData<P>,
+}
+
+impl<'a, P, W> Skimmer<'a, P, W>
+where
+    P: BlockParser + EventLayout,
+    P::Block: HEPEvent,
+    <P::Block as HEPEvent>::P: Particle<Decoder = dyn ParticleDatabase> + 'static,
+    W: Write,
+{
+    fn write_lines(&mut self, lines: &[String]) -> std::io::Result<()> {
+        lines.iter().try_for_each(|x| writeln!(self.out, "{}", x))
+    }
+
+    /// Error of event; returns it if skimming has to stop
+    fn on_error(&mut self, e: ParseError, idx: usize) -> std::io::Result<()> {
+        let mut e = e.at_line(idx).at_event(self.counts.read - 1);
+        if let Some(src) = self.source {
+            e = e.in_file(src);
+        }
+        if self.policy == ErrorPolicy::Fail {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+        }
+        eprintln!("[WARNING]: {}, event skipped", e);
+        self.counts.skipped += 1;
+        Ok(())
+    }
+
+    /// Parses event of lines starting at line `start` and writes it if it passes
+    fn event(&mut self, start: usize, mut lines: Vec<String>) -> std::io::Result<()> {
+        self.counts.read += 1;
+        let mut parser = P::default();
+        parser.set_policy(self.policy);
+        let mut blocks = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            match parser.parse_line(start + i, line.clone()) {
+                Ok(Some(block)) => blocks.push(block),
+                Ok(None) => {},
+                Err(e) => return self.on_error(ParseError::from_boxed(e), start + i),
+            }
+        }
+        match parser.finish() {
+            Ok(Some(block)) => blocks.push(block),
+            Ok(None) => {},
+            Err(e) => return self.on_error(ParseError::from_boxed(e), start + lines.len()),
+        }
+        if self.exclude_bad && blocks.iter().any(|x| x.declared_count().is_some_and(|n| n != x.particle_count())) {
+            eprintln!("[WARNING]: event {} at line {} has wrong particle count, event excluded", self.counts.read - 1, start + 1);
+            self.counts.skipped += 1;
+            return Ok(());
+        }
+        // events holding several blocks (time steps) are kept if any of them passes
+        if !blocks.iter().any(|x| self.filter.passes(x)) {
+            return Ok(());
+        }
+        if self.filter.has_particle_filter() {
+            let Some(particle_lines) = P::particle_lines(&lines) else {
+                return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "particle filters are not supported for this format"));
+            };
+            let keep: Vec<_> = match blocks.as_slice() {
+                [block] => block.particles().map(|p| self.filter.keeps(p)).collect(),
+                _ => vec![],
+            };
+            if keep.len() != particle_lines.len() {
+                return self.on_error(ParseError::format("particle lines do not match parsed particles"), start);
+            }
+            let dropped: HashSet<_> = particle_lines.iter().zip(&keep).filter(|x| !x.1).map(|x| *x.0).collect();
+            lines = lines.into_iter().enumerate().filter(|(i, _)| !dropped.contains(i)).map(|x| x.1).collect();
+            P::set_particle_count(&mut lines, keep.len() - dropped.len());
+        }
+        self.counts.written += 1;
+        self.write_lines(&lines)
+    }
+}
+
+/// Copies file header, events passing `filter` and file footer from `input` to `out`
+pub fn skim<P, W>(input: impl BufRead, out: W, filter: &SkimFilter, policy: ErrorPolicy, exclude_bad: bool, source: Option<&str>) -> std::io::Result<SkimCounts>
+where
+    P: BlockParser + EventLayout,
+    P::Block: HEPEvent,
+    <P::Block as HEPEvent>::P: Particle<Decoder = dyn ParticleDatabase> + 'static,
+    W: Write,
+{
+    let mut skimmer = Skimmer::<P, W> {
+        filter, out, policy, exclude_bad, source,
+        counts: Default::default(),
+        _parser: Default::default(),
+    };
+    // lines of the current event, the file header before the first event
+    let mut lines: Vec<String> = vec![];
+    let mut start = None;
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        if P::is_event_start(idx, lines.last().map(|x| x.as_str()).unwrap_or(""), &line) {
+            match start {
+                Some(a) => skimmer.event(a, std::mem::take(&mut lines))?,
+                None => skimmer.write_lines(&std::mem::take(&mut lines))?,
+            }
+            start = Some(idx);
+        }
+        lines.push(line);
+    }
+    let footer = lines.iter().position(|x| P::is_footer(x)).map(|i| lines.split_off(i)).unwrap_or_default();
+    match start {
+        Some(a) => skimmer.event(a, lines)?,
+        None => skimmer.write_lines(&lines)?,
+    }
+    skimmer.write_lines(&footer)?;
+    skimmer.out.flush()?;
+    Ok(skimmer.counts)
+}
+
+/// Skims one file into `<path>.skim`, returns summary lines
+fn skim_file<P>(path: &str, args: &Args, filter: &SkimFilter) -> std::io::Result<Vec<String>>
+where
+    P: BlockParser + EventLayout,
+    P::Block: HEPEvent,
+    <P::Block as HEPEvent>::P: Particle<Decoder = dyn ParticleDatabase> + 'static,
+{
+    println!(">> FILE SKIMMING [{}]", path);
+    let input = BufReader::new(File::open(path)?);
+    let out_path = format!("{}{}", path, SKIM_SUFFIX);
+    let out = BufWriter::new(File::create(&out_path)?);
+    let counts = skim::<P, _>(input, out, filter, args.on_error, args.exclude_bad, Some(path))?;
+    let mut summary = vec![format!(">> FILE DONE [{}]: {} of {} events written to {}", path, counts.written, counts.read, out_path)];
+    if counts.skipped > 0 {
+        summary.push(format!("{} events skipped", counts.skipped));
+    }
+    Ok(summary)
+}
+
+fn skim_files<P>(args: &Args, filter: &SkimFilter)
+where
+    P: BlockParser + EventLayout,
+    P::Block: HEPEvent,
+    <P::Block as HEPEvent>::P: Particle<Decoder = dyn ParticleDatabase> + 'static,
+{
+    let skim = |x: &String| {
+        skim_file::<P>(x, args, filter).unwrap_or_else(|e| { eprintln!("{}", e); std::process::exit(1) })
+    };
+    let per_file: Vec<_> = if args.sequential {
+        args.filenames.iter().map(skim).collect()
+    } else {
+        args.filenames.par_iter().map(skim).collect()
+    };
+    // summaries are printed in input order
+    per_file.iter().flatten().for_each(|x| println!("{}", x));
+}
+
+/// Writes events of every input file passing `filter` into `<input>.skim`
+pub fn run_skim(args: &Args, filter: &SkimFilter) {
+    let start = SystemTime::now();
+    match args.input_type() {
+        AcceptedTypes::EPOS => skim_files::<OSCEposParser>(args, filter),
+        AcceptedTypes::UrQmdF19 => skim_files::<OSC97UrQMDParser>(args, filter),
+        AcceptedTypes::PHQMD => skim_files::<PHQMDParser>(args, filter),
+        AcceptedTypes::QGSM => skim_files::<QGSMParser>(args, filter),
+        AcceptedTypes::HepMC => skim_files::<HepMCParser>(args, filter),
+        AcceptedTypes::HepMC2 => skim_files::<HepMC2Parser>(args, filter),
+        AcceptedTypes::Oscar2013 => skim_files::<OSCAR2013Parser>(args, filter),
+        AcceptedTypes::PHSD => skim_files::<PHSDParser>(args, filter),
+        AcceptedTypes::AMPT => skim_files::<AMPTParser>(args, filter),
+        AcceptedTypes::LHE => skim_files::<LHEParser>(args, filter),
+        AcceptedTypes::UrQMDF13 | AcceptedTypes::UrQMDF14 => skim_files::<UrQMDParser>(args, filter),
+        AcceptedTypes::UrQMDF15 => skim_files::<UrQMDCollisionParser>(args, filter),
+        AcceptedTypes::Oscar2013Bin | AcceptedTypes::Cache => {
+            eprintln!("Skimming is supported for text formats only");
+            std::process::exit(1)
+        },
+    }
+    println!("SKIMMING DONE: {} s", start.elapsed().unwrap().as_secs_f64());
+}
+
+#[test]
+fn test_skim_epos() {
+    let data = "# OSC1999A
+# final_id_p_x
+# EPOS 3.4  (1, 1) + (1, 1)   nncm  7000.0  0
+# END_OF_HEADER
+     0      3    0.000    0.000  0
+     1     1120    0  0.1  0.2  60.0  60.0  0.938  0 0 0 0
+     2    -1120    0  0.1  0.2  -1.0  1.5  0.938  0 0 0 0
+     3      120    0  0.3  0.1  0.5  0.6  0.139  0 0 0 0
+     1      2    0.000    0.000  0
+     1      120    0  0.3  0.1  0.5  0.6  0.139  0 0 0 0
+     2     -120    0  0.3  0.1  0.5  0.6  0.139  0 0 0 0
+";
+    let dict = crate::fmt::decoder::EposDict::upload(BufReader::new("".as_bytes()), crate::fmt::decoder::DctCoding::EPOS, None);
+    let select = ["ParticleIdCounter(1120) >= 1".parse().unwrap()];
+    let filter = SkimFilter { select: &select, keep_codes: HashSet::new(), final_only: false, dict: &dict };
+    let mut out = vec![];
+    let counts = skim::<OSCEposParser, _>(data.as_bytes(), &mut out, &filter, ErrorPolicy::Fail, false, None).unwrap();
+    assert_eq!(counts, SkimCounts { read: 2, written: 1, skipped: 0 });
+    let lines: Vec<&str> = data.lines().collect();
+    assert_eq!(String::from_utf8(out).unwrap(), lines[..8].join("\n") + "\n");
+
+    let filter = SkimFilter { select: &[], keep_codes: HashSet::from([1120, -1120]), final_only: false, dict: &dict };
+    let mut out = vec![];
+    skim::<OSCEposParser, _>(data.as_bytes(), &mut out, &filter, ErrorPolicy::Fail, false, None).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let out: Vec<&str> = out.lines().collect();
+    assert_eq!(&out[..4], &lines[..4]);
+    assert_eq!(out[4], "     0      2    0.000    0.000  0");
+    assert_eq!(&out[5..7], &lines[5..7]);
+    assert_eq!(out[7], "     1      0    0.000    0.000  0");
+    assert_eq!(out.len(), 8);
+}